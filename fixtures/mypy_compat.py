@@ -0,0 +1,88 @@
+"""Verdicts of mypy --disallow-untyped-defs --disallow-incomplete-defs, per function."""
+
+from typing import overload
+
+
+def some_decorator(function): ...  # mypy: error
+
+
+def untyped(a, b):  # mypy: error
+    pass
+
+
+def fully_typed(a: int, b: str) -> None:  # mypy: ok
+    pass
+
+
+def incomplete(a: int, b) -> None:  # mypy: error
+    pass
+
+
+def missing_return(a: int):  # mypy: error
+    pass
+
+
+def main():  # mypy: error
+    pass
+
+
+def function_with_self(self) -> None:  # mypy: error
+    pass
+
+
+def untyped_args(*args, **kwargs) -> None:  # mypy: error
+    pass
+
+
+def typed_args(*args: int, **kwargs: str) -> None:  # mypy: ok
+    pass
+
+
+def keyword_and_positional_only(a: int, /, b: int, *, c: int = 1) -> None:  # mypy: ok
+    pass
+
+
+class Example:
+    def __init__(self, a: int):  # mypy: ok
+        self.a = a
+
+    def __init_subclass__(cls, **kwargs: object):  # mypy: ok
+        pass
+
+    def renamed_self(this, a: int) -> None:  # mypy: ok
+        pass
+
+    @classmethod
+    def create(cls) -> "Example":  # mypy: ok
+        return cls(1)
+
+    @staticmethod
+    def static(a) -> None:  # mypy: error
+        pass
+
+    @staticmethod
+    def static_typed(a: int) -> None:  # mypy: ok
+        pass
+
+
+class Bare:
+    def __init__(self):  # mypy: error
+        pass
+
+
+@overload
+def parse(a: int) -> int: ...  # mypy: ok
+@overload
+def parse(a: str) -> str: ...  # mypy: ok
+def parse(a):  # mypy: error
+    return a
+
+
+@some_decorator
+async def fetch(url: str) -> bytes:  # mypy: ok
+    return b""
+
+
+def outer() -> None:  # mypy: ok
+    def inner(x):  # mypy: error
+        pass
@@ -0,0 +1,2 @@
+def shout(message) -> str:
+    return message.upper()
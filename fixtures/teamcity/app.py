@@ -0,0 +1,10 @@
+from __future__ import annotations
+
+
+def greet(name, punctuation: str = "!"):
+    return f"Hello, {name}{punctuation}"
+
+
+class Greeter:
+    def __init__(self, prefix: "list[str]") -> None:
+        self.prefix = prefix
@@ -0,0 +1,109 @@
+use std::fmt::Write;
+
+use crate::{report::get_finding_message, Position};
+
+/// Columns between tab stops, as in Python's tokenizer and most terminals.
+const TAB_WIDTH: usize = 8;
+
+/// Renders the whole file with line numbers and, under each line with findings, one
+/// marker line per finding with carets spanning it, in column order. Findings spanning
+/// several lines, such as missing return types, are marked up to the end of their first
+/// line.
+pub fn annotate_source(source: &str, positions: &[Position]) -> String {
+    let mut positions: Vec<&Position> = positions.iter().collect();
+    positions.sort_by_key(|position| (position.start.row, position.start.column));
+
+    let lines: Vec<&str> = source.lines().collect();
+    let width = lines.len().max(1).to_string().len();
+    let mut annotated = String::new();
+    let mut positions = positions.into_iter().peekable();
+
+    for (row, line) in lines.iter().enumerate() {
+        let expanded = expand_tabs(line);
+        let _ = writeln!(
+            annotated,
+            "{:>width$} |{}{}",
+            row + 1,
+            if expanded.is_empty() { "" } else { " " },
+            expanded
+        );
+
+        while let Some(position) = positions.next_if(|position| position.start.row == row) {
+            let start = display_column(line, position.start.column);
+            let end = if position.end.row == row {
+                display_column(line, position.end.column)
+            } else {
+                expanded.trim_end().chars().count()
+            };
+
+            let _ = writeln!(
+                annotated,
+                "{:width$} | {}{} {}",
+                "",
+                " ".repeat(start),
+                "^".repeat(end.saturating_sub(start).max(1)),
+                get_finding_message(position)
+            );
+        }
+    }
+
+    annotated
+}
+
+fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::with_capacity(line.len());
+    for character in line.chars() {
+        if character == '\t' {
+            let spaces = TAB_WIDTH - expanded.chars().count() % TAB_WIDTH;
+            expanded.extend(std::iter::repeat_n(' ', spaces));
+        } else {
+            expanded.push(character);
+        }
+    }
+
+    expanded
+}
+
+/// The column at which the character starting at `byte` is displayed once tabs are
+/// expanded.
+fn display_column(line: &str, byte: usize) -> usize {
+    let prefix = line.get(..byte.min(line.len())).unwrap_or(line);
+
+    expand_tabs(prefix).chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_python_parser, find_missing_types_positions, get_tree_from_source};
+
+    #[test]
+    fn annotate_source_test() {
+        let source = "\
+import os
+
+def greet(name, greeting):
+\tdef inner(x): return x
+\treturn greeting
+";
+        let mut parser = create_python_parser();
+        let (tree, source_code) = get_tree_from_source(&mut parser, source.as_bytes().to_vec());
+        let (positions, _) = find_missing_types_positions(&source_code, tree, &Default::default());
+
+        assert_eq!(
+            annotate_source(source, &positions),
+            "\
+1 | import os
+2 |
+3 | def greet(name, greeting):
+  | ^^^^^^^^^^^^^^^^^^^^^^^^^^ Function 'greet' is missing a return type.
+  |           ^^^^ Parameter 'name' is missing a type hint.
+  |                 ^^^^^^^^ Parameter 'greeting' is missing a type hint.
+4 |         def inner(x): return x
+  |         ^^^^^^^^^^^^^^^^^^^^^^ Function 'inner' is missing a return type.
+  |                   ^ Parameter 'x' is missing a type hint.
+5 |         return greeting
+"
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use crate::json_report::{fingerprint, FindingRecord, JsonReport};
+
+/// A file moved from `from` to `to` between the compared runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rename {
+    from: String,
+    to: String,
+}
+
+impl FromStr for Rename {
+    type Err = String;
+
+    fn from_str(rename: &str) -> Result<Self, Self::Err> {
+        match rename.split_once('=') {
+            Some((from, to)) if !from.is_empty() && !to.is_empty() => Ok(Rename {
+                from: normalize(from).to_string(),
+                to: normalize(to).to_string(),
+            }),
+            _ => Err(format!("'{rename}' is not a rename such as old.py=new.py")),
+        }
+    }
+}
+
+/// Reads the renames out of `git diff --name-status` output, whose rename lines look
+/// like `R087<TAB>old.py<TAB>new.py`. Other lines are ignored.
+pub fn parse_name_status(contents: &str) -> Vec<Rename> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let status = fields.next()?;
+            if !status.starts_with('R') {
+                return None;
+            }
+
+            Some(Rename {
+                from: normalize(fields.next()?).to_string(),
+                to: normalize(fields.next()?).to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Paths as reports and git spell them differently, e.g. `./src/app.py` and
+/// `src/app.py`.
+fn normalize(path: &str) -> &str {
+    path.trim_start_matches("./")
+}
+
+/// Findings of each report that the other does not have, matched by fingerprint so
+/// that findings moved to another line are still matched.
+#[derive(Debug, Default, serde::Serialize)]
+pub struct Comparison<'a> {
+    pub new: Vec<&'a FindingRecord>,
+    pub fixed: Vec<&'a FindingRecord>,
+    pub unchanged: usize,
+}
+
+/// Matches the findings of the two reports, once the paths of the old one are renamed.
+/// A finding reported twice under the same fingerprint needs to be in the old report
+/// twice to be unchanged.
+pub fn compare<'a>(old: &'a JsonReport, new: &'a JsonReport, renames: &[Rename]) -> Comparison<'a> {
+    let renamed: HashMap<&str, &str> = renames
+        .iter()
+        .map(|rename| (rename.from.as_str(), rename.to.as_str()))
+        .collect();
+    let key = |finding: &FindingRecord, renamed: &HashMap<&str, &str>| {
+        let path = normalize(&finding.path);
+        let path = renamed.get(path).copied().unwrap_or(path);
+        fingerprint(
+            path,
+            finding.function.as_deref(),
+            &finding.kind,
+            &finding.name,
+        )
+    };
+
+    let mut remaining: HashMap<String, Vec<&FindingRecord>> = HashMap::new();
+    for finding in &old.findings {
+        remaining
+            .entry(key(finding, &renamed))
+            .or_default()
+            .push(finding);
+    }
+
+    let mut comparison = Comparison::default();
+    for finding in &new.findings {
+        match remaining
+            .get_mut(&key(finding, &HashMap::new()))
+            .and_then(|old| old.pop())
+        {
+            Some(_) => comparison.unchanged += 1,
+            None => comparison.new.push(finding),
+        }
+    }
+
+    comparison.fixed = old
+        .findings
+        .iter()
+        .filter(|finding| {
+            remaining
+                .get(&key(finding, &renamed))
+                .is_some_and(|old| old.iter().any(|old| std::ptr::eq(*old, *finding)))
+        })
+        .collect();
+
+    comparison
+}
+
+/// Renders findings as `path:line:column: code message` lines.
+pub fn render_text(findings: &[&FindingRecord]) -> String {
+    findings
+        .iter()
+        .map(|finding| {
+            format!(
+                "{}:{}:{}: {} {}\n",
+                finding.path, finding.line, finding.column, finding.code, finding.message
+            )
+        })
+        .collect()
+}
+
+/// Reads a report written by `--format json`, rejecting other schema versions.
+#[cfg(feature = "formats-json")]
+pub fn read_report(path: &Path) -> Result<JsonReport, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+    let version: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e| format!("could not parse {}: {e}", path.display()))?;
+    let schema_version = version.get("schema_version").and_then(|v| v.as_u64());
+    if schema_version != Some(crate::json_report::SCHEMA_VERSION.into()) {
+        return Err(format!(
+            "{} is not a --format json report with schema version {}",
+            path.display(),
+            crate::json_report::SCHEMA_VERSION
+        ));
+    }
+
+    serde_json::from_value(version).map_err(|e| format!("could not parse {}: {e}", path.display()))
+}
+
+#[cfg(not(feature = "formats-json"))]
+pub fn read_report(_path: &Path) -> Result<JsonReport, String> {
+    unreachable!("compare should have been rejected at startup.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_report::Totals;
+
+    fn finding(path: &str, line: usize, name: &str) -> FindingRecord {
+        FindingRecord {
+            path: path.to_string(),
+            line,
+            column: 7,
+            end_line: line,
+            end_column: 8,
+            code: "THC001".to_string(),
+            kind: "missing_parameter".to_string(),
+            name: name.to_string(),
+            function: Some("f".to_string()),
+            message: format!("Parameter '{name}' is missing a type hint."),
+            suggestion: None,
+            url: None,
+            fingerprint: fingerprint(path, Some("f"), "missing_parameter", name),
+        }
+    }
+
+    fn report(findings: Vec<FindingRecord>) -> JsonReport {
+        JsonReport {
+            schema_version: crate::json_report::SCHEMA_VERSION,
+            version: String::new(),
+            options_fingerprint: String::new(),
+            findings,
+            files: Vec::new(),
+            summary: Totals {
+                files_checked: 1,
+                ..Default::default()
+            },
+            partial: false,
+        }
+    }
+
+    #[test]
+    fn rename_test() {
+        assert_eq!(
+            "./a.py=b.py".parse(),
+            Ok(Rename {
+                from: "a.py".to_string(),
+                to: "b.py".to_string()
+            })
+        );
+        assert!("a.py".parse::<Rename>().is_err());
+        assert!("=b.py".parse::<Rename>().is_err());
+
+        assert_eq!(
+            parse_name_status("M\tkept.py\nR087\told.py\tnew.py\nD\tgone.py\n"),
+            [Rename {
+                from: "old.py".to_string(),
+                to: "new.py".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn compare_test() {
+        let old = report(vec![
+            finding("old.py", 3, "x"),
+            finding("kept.py", 10, "y"),
+            finding("kept.py", 20, "z"),
+        ]);
+        let new = report(vec![
+            finding("./new.py", 5, "x"),
+            finding("kept.py", 12, "y"),
+            finding("kept.py", 30, "w"),
+        ]);
+
+        let comparison = compare(&old, &new, &["old.py=new.py".parse().unwrap()]);
+        assert_eq!(comparison.new, [&new.findings[2]]);
+        assert_eq!(comparison.fixed, [&old.findings[2]]);
+        assert_eq!(comparison.unchanged, 2);
+
+        let comparison = compare(&old, &new, &[]);
+        assert_eq!(comparison.new, [&new.findings[0], &new.findings[2]]);
+
+        let twice = report(vec![
+            finding("kept.py", 10, "y"),
+            finding("kept.py", 11, "y"),
+        ]);
+        assert_eq!(compare(&old, &twice, &[]).new, [&twice.findings[1]]);
+
+        assert_eq!(
+            render_text(&comparison.new[1..]),
+            "kept.py:30:7: THC001 Parameter 'w' is missing a type hint.\n"
+        );
+    }
+}
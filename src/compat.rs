@@ -0,0 +1,247 @@
+/// Other checkers whose definition of an untyped function can be matched.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compat {
+    /// mypy's `--disallow-untyped-defs` and `--disallow-incomplete-defs`.
+    Mypy,
+}
+
+const DEFAULT_RULES: &str = "\
+Every parameter of every function definition needs a type hint, including `*args`,
+`**kwargs` and the parameters around the `/` and `*` separators, except for the first
+parameter of methods when it is named `self`, or `cls` in class methods. Functions
+outside of classes and staticmethods have no such exception, and --strict-self removes
+it altogether.
+
+Every function needs a return type, except for the functions named in --ignore-names
+(`main` by default) or --ignore-return-for, and dunders that usually return `None`:
+`__init__`, `__init_subclass__`, `__post_init__`, `__set_name__`, `__setattr__`,
+`__delattr__`, `__setitem__`, `__delitem__`, `__set__`, `__delete__`, `__exit__` and
+`__aexit__`, unless --require-init-return is given. Their parameters are still checked.
+
+Findings carry the code of their rule: THC001 for parameters and THC002 for return
+types. Type parameter lists and `type` statements are reported as THC201 when
+--python-version (by default, the oldest version allowed by requires-python) is older
+than 3.12, which introduced them. Files that do not parse get a THC202 finding at their
+first syntax error, and are only checked outside of the broken regions; they fail the
+run with --fail-on-parse-error. Opt-in rules have their own codes:
+
+- THC101 (--flag-empty-collections): unannotated assignments of `[]`, `{}`, `()` or
+  `set()` in module and class bodies, or anywhere with `=all`.
+- THC102 (--flag-bare-generics): generics such as `list`, `typing.Dict` or `Callable`
+  annotated without type arguments, except those given to --allow-bare-generics.
+- THC103 (--flag-deprecated-typing): `typing` aliases replaced by builtins in Python
+  3.9 (`List`, `Dict`, `Set`, `FrozenSet`, `Tuple`, `Type`) and by `|` in 3.10
+  (`Optional`, `Union`), depending on --python-version.
+- THC104 (--flag-quoted-annotations): string annotations in modules with
+  `from __future__ import annotations`, or in any module when targeting 3.14, whose
+  annotations are evaluated lazily. Strings that are not valid expressions are skipped.
+- THC105 (--annotation-style): optional annotations spelled `Optional[X]` when the
+  style is `union`, or `X | None` when it is `optional`.
+- THC106 (--check-lambdas): lambdas assigned to a name without an annotation, such as
+  `handler = lambda request: ...`, which leave no place for type hints.
+- THC107 (--strictness consistent): functions annotating some of their parameters but
+  not the others, reported once per function. Nested functions are judged on their own.
+- THC108 (--strictness strict, on top of THC107): parameters and return types annotated
+  as `Any` or `typing.Any`.
+";
+
+const MYPY_RULES: &str = "\
+With --compat mypy, functions are checked the way mypy's --disallow-untyped-defs and
+--disallow-incomplete-defs check them:
+
+- The first parameter of methods (functions defined directly in a class body) is not
+  checked, whatever its name, unless the method is decorated with @staticmethod.
+  Parameters named `self` elsewhere are checked.
+- `main` and dunders need a return type like any other function, and --ignore-names
+  and --ignore-return-for are not applied.
+- `__init__` and `__init_subclass__` do not need a return type as long as one of
+  their parameters is annotated, since mypy then infers `-> None`.
+
+Known divergences, which cannot be matched without type information:
+
+- Functions annotated with `# type:` comments are reported, while mypy reads them.
+- Staticmethods are only recognized by a literal @staticmethod decorator, not by
+  aliases of it or by `staticmethod(f)` assignments.
+- Functions inside blocks mypy considers unreachable, such as `if sys.version_info`
+  or `if sys.platform` checks for another target, are still reported.
+- Untyped decorators are not reported, as --disallow-untyped-decorators needs to know
+  the decorator's type.
+- Per-module overrides in mypy's configuration, such as `ignore_errors`, are not read.
+";
+
+/// Describes the rules in effect with the given compatibility mode.
+pub fn explain(compat: Option<Compat>) -> &'static str {
+    match compat {
+        None => DEFAULT_RULES,
+        Some(Compat::Mypy) => MYPY_RULES,
+    }
+}
+
+/// The function itself, or the decorated definition wrapping it.
+fn definition(function: tree_sitter::Node) -> tree_sitter::Node {
+    match function.parent() {
+        Some(parent) if parent.kind() == "decorated_definition" => parent,
+        _ => function,
+    }
+}
+
+/// Whether the function is defined directly in a class body.
+pub fn is_method(function: tree_sitter::Node) -> bool {
+    definition(function)
+        .parent()
+        .filter(|parent| parent.kind() == "block")
+        .and_then(|block| block.parent())
+        .is_some_and(|parent| parent.kind() == "class_definition")
+}
+
+/// Whether the function is a method whose first parameter is bound implicitly (`self`
+/// or `cls`), which mypy does not require to be annotated.
+pub fn binds_first_parameter(function: tree_sitter::Node, source_code: &[u8]) -> bool {
+    if !is_method(function) {
+        return false;
+    }
+
+    !has_decorator(function, source_code, "staticmethod")
+}
+
+/// Whether the function is decorated with exactly `@name`.
+fn has_decorator(function: tree_sitter::Node, source_code: &[u8], name: &str) -> bool {
+    let definition = definition(function);
+
+    let mut cursor = definition.walk();
+    let has_decorator = definition
+        .children(&mut cursor)
+        .filter(|child| child.kind() == "decorator")
+        .any(|decorator| {
+            decorator
+                .utf8_text(source_code)
+                .is_ok_and(|text| text.trim_start_matches('@').trim() == name)
+        });
+    has_decorator
+}
+
+/// The name the implicitly bound first parameter of a method goes by: `cls` for class
+/// methods, including the implicit ones such as `__new__`, and `self` for the others.
+pub fn bound_parameter_name(
+    function: tree_sitter::Node,
+    source_code: &[u8],
+) -> Option<&'static str> {
+    if !binds_first_parameter(function, source_code) {
+        return None;
+    }
+
+    let implicit_classmethod = function
+        .child_by_field_name("name")
+        .and_then(|name| name.utf8_text(source_code).ok())
+        .is_some_and(|name| matches!(name, "__new__" | "__init_subclass__" | "__class_getitem__"));
+    if implicit_classmethod || has_decorator(function, source_code, "classmethod") {
+        Some("cls")
+    } else {
+        Some("self")
+    }
+}
+
+/// Dunders that do not need a return type by default, as they usually return `None`.
+pub const DUNDERS_WITHOUT_RETURN: &[&str] = &[
+    "__init__",
+    "__init_subclass__",
+    "__post_init__",
+    "__set_name__",
+    "__setattr__",
+    "__delattr__",
+    "__setitem__",
+    "__delitem__",
+    "__set__",
+    "__delete__",
+    "__exit__",
+    "__aexit__",
+];
+
+/// mypy infers `-> None` for initializers that have at least one annotated parameter.
+pub fn returns_none_implicitly(name: &str, has_annotated_parameter: bool) -> bool {
+    matches!(name, "__init__" | "__init_subclass__") && has_annotated_parameter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        create_python_parser, find_missing_types_positions, get_tree_from_file,
+        get_tree_from_source, Rules,
+    };
+
+    /// Each `def` line of the fixture ends with a `# mypy: ok` or `# mypy: error` comment
+    /// giving mypy's verdict under --disallow-untyped-defs and --disallow-incomplete-defs.
+    #[test]
+    fn mypy_fixture_test() {
+        let mut parser = create_python_parser();
+        let (tree, source_code) =
+            get_tree_from_file(&mut parser, "fixtures/mypy_compat.py").unwrap();
+        let rules = Rules {
+            compat: Some(Compat::Mypy),
+            ..Default::default()
+        };
+        let (positions, _) = find_missing_types_positions(&source_code, tree, &rules);
+
+        let source = std::str::from_utf8(&source_code).unwrap();
+        let verdicts: Vec<(usize, &str, bool)> = source
+            .lines()
+            .enumerate()
+            .filter_map(|(row, line)| {
+                let (definition, verdict) = line.split_once("# mypy: ")?;
+                let name = definition.split("def ").nth(1)?.split('(').next()?;
+                Some((row, name, verdict.trim() == "error"))
+            })
+            .collect();
+        assert_eq!(verdicts.len(), 23);
+
+        for (row, name, expected) in verdicts {
+            let reported = positions
+                .iter()
+                .filter_map(|position| position.function.as_ref())
+                .any(|function| function.start.row == row);
+            assert_eq!(reported, expected, "verdicts differ for '{name}'");
+        }
+    }
+
+    #[test]
+    fn bound_parameter_test() {
+        let source = b"def compute(self, x: int) -> int: ...
+
+class A:
+    def method(self) -> None: ...
+
+    @classmethod
+    def create(cls) -> None: ...
+
+    @staticmethod
+    def helper(self) -> None: ...
+
+    def misnamed(cls) -> None: ...
+
+    def __new__(cls) -> None: ...
+";
+        let flagged = |rules: &Rules| {
+            let mut parser = create_python_parser();
+            let (tree, source_code) = get_tree_from_source(&mut parser, source.to_vec());
+            let (positions, _) = find_missing_types_positions(&source_code, tree, rules);
+            positions
+                .iter()
+                .filter_map(|position| Some(position.function.as_ref()?.name.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            flagged(&Rules::default()),
+            ["compute", "helper", "misnamed"]
+        );
+        assert_eq!(
+            flagged(&Rules {
+                strict_self: true,
+                ..Default::default()
+            }),
+            ["compute", "method", "create", "helper", "misnamed", "__new__"]
+        );
+    }
+}
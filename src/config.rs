@@ -0,0 +1,237 @@
+use std::path::Path;
+
+use clap::parser::ValueSource;
+
+use crate::Args;
+
+/// An effective option value along with where it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigEntry {
+    pub key: String,
+    pub value: toml::Value,
+    pub source: String,
+}
+
+/// Lists every option of `args` with its resolved value, keyed by its command-line
+/// name, and its source as recorded by the argument parser. Options that were not given
+/// take their value from `detected` when it has one, such as a version read from
+/// `pyproject.toml`. Options in `sources` were set by `--set` or the configuration file,
+/// which is given as their source instead of their default. `env` reads the environment
+/// variables the options were parsed with.
+pub fn effective_config(
+    args: &Args,
+    matches: &clap::ArgMatches,
+    command: &clap::Command,
+    detected: &[ConfigEntry],
+    sources: &[(String, String)],
+    env: impl Fn(&str) -> Option<String>,
+) -> Vec<ConfigEntry> {
+    let table = toml::Table::try_from(args).expect("Options should be serializable.");
+
+    command
+        .get_arguments()
+        .filter_map(|argument| {
+            let id = argument.get_id().as_str();
+            let key = argument.get_long().unwrap_or(id);
+
+            let set_by = sources.iter().find(|(name, _)| name == key);
+            let from_command_line = matches.value_source(id) == Some(ValueSource::CommandLine);
+            // Flags given on the command line take precedence over their variable.
+            let variable = argument
+                .get_env()
+                .map(|variable| variable.to_string_lossy().into_owned())
+                .filter(|variable| !from_command_line && env(variable).is_some());
+            let given = from_command_line || variable.is_some() || set_by.is_some();
+            if let Some(entry) = detected.iter().find(|entry| entry.key == key) {
+                if !given {
+                    return Some(entry.clone());
+                }
+            }
+
+            let value = table.get(id)?.clone();
+
+            let source = match (variable, set_by) {
+                _ if from_command_line => "cli".to_string(),
+                (Some(variable), _) => format!("env:{variable}"),
+                (None, Some((_, source))) => source.clone(),
+                (None, None) => "default".to_string(),
+            };
+
+            Some(ConfigEntry {
+                key: key.to_string(),
+                value,
+                source,
+            })
+        })
+        .collect()
+}
+
+/// Renders the entries as TOML, with each value's source as a trailing comment.
+pub fn render_config(path: &Path, entries: &[ConfigEntry]) -> String {
+    let mut rendered = format!("# Effective configuration for '{}'\n", path.display());
+
+    for entry in entries {
+        rendered += &format!("{} = {}  # {}\n", entry.key, entry.value, entry.source);
+    }
+
+    rendered
+}
+
+/// Options deciding which part of the files a run gets to check, which runs over the
+/// parts of a tree, such as its shards, differ in without being configured differently.
+const PARTITIONING_OPTIONS: &[&str] = &["shard", "timeout", "timeout_soft"];
+
+/// A short hash of every option value, identifying runs made with the same configuration.
+pub fn options_fingerprint(args: &Args) -> String {
+    let mut table = toml::Table::try_from(args).expect("Options should be serializable.");
+    for option in PARTITIONING_OPTIONS {
+        table.remove(*option);
+    }
+
+    format!("{:016x}", stable_hash(table.to_string().as_bytes()))
+}
+
+/// FNV-1a, which keeps hashes stable across builds and platforms, unlike the standard
+/// library's hasher.
+pub fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, FromArgMatches, Parser};
+
+    use super::*;
+    use crate::{
+        overrides::{self, Assignment},
+        Cli,
+    };
+
+    fn entry<'a>(entries: &'a [ConfigEntry], key: &str) -> &'a ConfigEntry {
+        entries.iter().find(|entry| entry.key == key).unwrap()
+    }
+
+    #[test]
+    fn effective_config_test() {
+        let environment = [
+            (
+                "THC_MAX_LINE_LENGTH",
+                "max-line-length",
+                "max_line_length",
+                "500",
+            ),
+            (
+                "THC_IGNORE_DIRS",
+                "ignore-dirs",
+                "ignore_dirs",
+                "vendored,build",
+            ),
+            (
+                "THC_IGNORE_RETURN",
+                "ignore-return",
+                "ignore_return",
+                "true",
+            ),
+        ];
+        let env = |name: &str| {
+            environment
+                .iter()
+                .find(|(variable, ..)| *variable == name)
+                .map(|(.., value)| value.to_string())
+        };
+        // Parsed with the variables' values as defaults, rather than with variables set
+        // for the whole process, which the tests parsing arguments in parallel would read.
+        let parsed: Vec<Assignment> = environment
+            .iter()
+            .map(|(_, key, id, value)| Assignment {
+                key: key.to_string(),
+                id: id.to_string(),
+                value: value.to_string(),
+            })
+            .collect();
+        let command = overrides::apply(Cli::command(), None, &parsed, false);
+        let matches = command
+            .clone()
+            .try_get_matches_from(["pythcheck", "src", "--ignore-dirs", "migrations"])
+            .unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+
+        let detected = [ConfigEntry {
+            key: "python-version".to_string(),
+            value: "3.11".into(),
+            source: "pyproject.toml".to_string(),
+        }];
+        let entries = effective_config(&cli.args, &matches, &command, &detected, &[], env);
+
+        assert_eq!(
+            entry(&entries, "ignore-dirs"),
+            &ConfigEntry {
+                key: "ignore-dirs".to_string(),
+                value: toml::Value::Array(vec!["migrations".into()]),
+                source: "cli".to_string(),
+            }
+        );
+        assert_eq!(entry(&entries, "max-line-length").value, 500.into());
+        assert_eq!(
+            entry(&entries, "max-line-length").source,
+            "env:THC_MAX_LINE_LENGTH"
+        );
+        assert_eq!(entry(&entries, "ignore-return").value, true.into());
+        assert_eq!(
+            entry(&entries, "ignore-return").source,
+            "env:THC_IGNORE_RETURN"
+        );
+        assert_eq!(entry(&entries, "ignore-hidden").value, false.into());
+        assert_eq!(entry(&entries, "ignore-hidden").source, "default");
+        assert_eq!(
+            entry(&entries, "extension").value,
+            toml::Value::Array(vec!["py".into()])
+        );
+        assert!(entries.iter().all(|entry| entry.key != "print-config"));
+        assert_eq!(entry(&entries, "python-version"), &detected[0]);
+
+        let rendered = render_config(Path::new("src"), &entries);
+        assert!(rendered.contains("ignore-dirs = [\"migrations\"]  # cli\n"));
+        assert!(rendered.contains("progress = \"none\"  # default\n"));
+
+        let sources = [
+            ("ignore-hidden".to_string(), "cli:--set".to_string()),
+            ("ignore-dirs".to_string(), "cli:--set".to_string()),
+            (
+                "max-line-length".to_string(),
+                "config:pyhints.toml".to_string(),
+            ),
+            ("extension".to_string(), "config:pyhints.toml".to_string()),
+        ];
+        let entries = effective_config(&cli.args, &matches, &command, &detected, &sources, env);
+        assert_eq!(entry(&entries, "ignore-hidden").source, "cli:--set");
+        assert_eq!(entry(&entries, "ignore-dirs").source, "cli");
+        assert_eq!(
+            entry(&entries, "max-line-length").source,
+            "env:THC_MAX_LINE_LENGTH"
+        );
+        assert_eq!(entry(&entries, "extension").source, "config:pyhints.toml");
+    }
+
+    #[test]
+    fn options_fingerprint_test() {
+        let parse = |arguments: &[&str]| Cli::try_parse_from(arguments).unwrap().args;
+
+        let fingerprint = options_fingerprint(&parse(&["pythcheck", "src"]));
+        assert_eq!(fingerprint.len(), 16);
+        assert_eq!(
+            fingerprint,
+            options_fingerprint(&parse(&["pythcheck", "other"]))
+        );
+        assert_ne!(
+            fingerprint,
+            options_fingerprint(&parse(&["pythcheck", "src", "--ignore-return"]))
+        );
+        assert_eq!(
+            fingerprint,
+            options_fingerprint(&parse(&["pythcheck", "src", "--shard", "2/4"]))
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use std::path::{Path, PathBuf};
+
+use clap::parser::ValueSource;
+
+use crate::overrides::{expected_type, validate, Assignment};
+
+/// Name of the configuration file discovered in the checked directory and its ancestors.
+pub const FILE_NAME: &str = "pyhints.toml";
+
+/// Table of `pyproject.toml` that configures the checker, under `[tool]`.
+const PYPROJECT_TABLE: &str = "python-type-hints-checker";
+
+/// Settings read from `pyhints.toml` or from the `[tool.python-type-hints-checker]`
+/// table of `pyproject.toml`, keyed by the options' command-line names.
+#[derive(Debug, PartialEq)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    settings: toml::Table,
+}
+
+impl ConfigFile {
+    /// Reads the file given to --config, or else looks for one in `start` and its
+    /// ancestors, the closest one winning and `pyhints.toml` taking precedence over
+    /// `pyproject.toml` in the same directory.
+    pub fn locate(explicit: Option<&Path>, start: &Path) -> Result<Option<Self>, String> {
+        if let Some(path) = explicit {
+            return match ConfigFile::read(path)? {
+                Some(config) => Ok(Some(config)),
+                None => Err(format!(
+                    "{} has no [tool.{PYPROJECT_TABLE}] table",
+                    path.display()
+                )),
+            };
+        }
+
+        let start = std::path::absolute(start).map_err(|e| e.to_string())?;
+        let start = if start.is_dir() {
+            start.as_path()
+        } else {
+            start.parent().unwrap_or(&start)
+        };
+        for directory in start.ancestors() {
+            for candidate in [FILE_NAME, "pyproject.toml"] {
+                let path = directory.join(candidate);
+                if !path.is_file() {
+                    continue;
+                }
+                if let Some(config) = ConfigFile::read(&path)? {
+                    return Ok(Some(config));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Reads the file, returning `None` for a `pyproject.toml` without a table for the
+    /// checker.
+    fn read(path: &Path) -> Result<Option<Self>, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+        let mut table: toml::Table = contents
+            .parse()
+            .map_err(|e: toml::de::Error| format!("could not parse {}: {e}", path.display()))?;
+
+        let settings = if path
+            .file_name()
+            .is_some_and(|name| name == "pyproject.toml")
+        {
+            let Some(settings) = table
+                .remove("tool")
+                .and_then(|mut tool| tool.as_table_mut()?.remove(PYPROJECT_TABLE))
+            else {
+                return Ok(None);
+            };
+            match settings {
+                toml::Value::Table(settings) => settings,
+                _ => {
+                    return Err(format!(
+                        "{}: tool.{PYPROJECT_TABLE} should be a table",
+                        path.display()
+                    ))
+                }
+            }
+        } else {
+            table
+        };
+
+        Ok(Some(ConfigFile {
+            path: path.to_path_buf(),
+            settings,
+        }))
+    }
+
+    /// Resolves the settings into assignments, like `--set`, leaving out the options
+    /// in `overridden` by it and those given on the command line or by their environment
+    /// variable, which all take precedence over the settings.
+    pub fn assignments(
+        &self,
+        command: &clap::Command,
+        matches: &clap::ArgMatches,
+        overridden: &[String],
+    ) -> Result<Vec<Assignment>, String> {
+        let mut assignments = Vec::new();
+
+        for (key, setting) in &self.settings {
+            let key = key.replace('_', "-");
+            if overridden.contains(&key) {
+                continue;
+            }
+            let argument = command
+                .get_arguments()
+                .find(|argument| argument.get_long() == Some(key.as_str()))
+                .filter(|argument| {
+                    argument.get_env().is_some()
+                        && !matches!(argument.get_id().as_str(), "set" | "config")
+                })
+                .ok_or_else(|| {
+                    format!(
+                        "{}: '{key}' is not an option that can be configured",
+                        self.path.display()
+                    )
+                })?;
+            let id = argument.get_id().to_string();
+
+            let invalid = |setting: &toml::Value| {
+                format!(
+                    "{}: {key} expects {}, got {setting}",
+                    self.path.display(),
+                    expected_type(argument)
+                )
+            };
+            let is_list = argument.get_value_delimiter().is_some();
+            let values: Vec<String> = match setting {
+                toml::Value::Array(elements) if is_list => elements
+                    .iter()
+                    .map(|element| scalar(element).ok_or_else(|| invalid(setting)))
+                    .collect::<Result<_, _>>()?,
+                _ => vec![scalar(setting).ok_or_else(|| invalid(setting))?],
+            };
+            for value in &values {
+                validate(argument, value).map_err(|reason| invalid(setting) + &reason)?;
+            }
+
+            if matches!(
+                matches.value_source(&id),
+                Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+            ) {
+                continue;
+            }
+
+            assignments.push(Assignment {
+                key,
+                id,
+                value: values.join(","),
+            });
+        }
+
+        Ok(assignments)
+    }
+}
+
+/// The value of a setting as it would be written on the command line.
+fn scalar(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(value) => Some(value.clone()),
+        toml::Value::Integer(value) => Some(value.to_string()),
+        toml::Value::Float(value) => Some(value.to_string()),
+        toml::Value::Boolean(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+    use crate::{tests::fixture_dir, Cli};
+
+    #[test]
+    fn locate_test() {
+        let dir = fixture_dir();
+        std::fs::create_dir_all(dir.path().join("src/app")).unwrap();
+        std::fs::write(
+            dir.path().join("pyproject.toml"),
+            "[project]\nname = \"app\"\n\n[tool.python-type-hints-checker]\nignore-return = true\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("src/pyproject.toml"), "[project]\n").unwrap();
+
+        let found = ConfigFile::locate(None, &dir.path().join("src/app")).unwrap();
+        assert_eq!(
+            found.map(|config| config.path),
+            Some(std::path::absolute(dir.path().join("pyproject.toml")).unwrap())
+        );
+
+        std::fs::write(
+            dir.path().join("src").join(FILE_NAME),
+            "ignore-tests = true\n",
+        )
+        .unwrap();
+        let found = ConfigFile::locate(None, &dir.path().join("src/app")).unwrap();
+        assert_eq!(
+            found.map(|config| config.path),
+            Some(std::path::absolute(dir.path().join("src").join(FILE_NAME)).unwrap())
+        );
+
+        std::fs::write(dir.path().join("broken.toml"), "ignore-tests = \n").unwrap();
+        let error = ConfigFile::locate(Some(&dir.path().join("broken.toml")), dir.path());
+        assert!(error.unwrap_err().starts_with("could not parse"));
+        assert!(
+            ConfigFile::locate(Some(&dir.path().join("src/pyproject.toml")), dir.path()).is_err()
+        );
+    }
+
+    #[test]
+    fn assignments_test() {
+        let config = ConfigFile {
+            path: PathBuf::from(FILE_NAME),
+            settings: toml::from_str(
+                "ignore-tests = true\nmax_line_length = 500\nignore-dirs = [\"build\", \"vendored\"]\nignore-return = true\n",
+            )
+            .unwrap(),
+        };
+        let command = Cli::command();
+        let matches = command
+            .clone()
+            .try_get_matches_from(["pythcheck", "src", "--max-line-length", "80"])
+            .unwrap();
+
+        let assignments = config
+            .assignments(&command, &matches, &["ignore-return".to_string()])
+            .unwrap();
+        let assigned: Vec<(&str, &str)> = assignments
+            .iter()
+            .map(|assignment| (assignment.id.as_str(), assignment.value.as_str()))
+            .collect();
+        assert_eq!(
+            assigned,
+            [("ignore_dirs", "build,vendored"), ("ignore_tests", "true")]
+        );
+
+        let invalid = |settings: &str| {
+            let config = ConfigFile {
+                path: PathBuf::from(FILE_NAME),
+                settings: toml::from_str(settings).unwrap(),
+            };
+            config.assignments(&command, &matches, &[]).unwrap_err()
+        };
+        assert_eq!(
+            invalid("max-line-length = \"long\""),
+            "pyhints.toml: max-line-length expects a number, got \"long\" (invalid digit found in string)"
+        );
+        assert!(invalid("colour = \"always\"").contains("not an option"));
+        assert!(invalid("ignore-tests = [true]").contains("expects true or false"));
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    fmt,
+    str::FromStr,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+/// Time given to the files being checked at the deadline, whose parses stop at the
+/// deadline, before the results are rendered without them.
+const GRACE: Duration = Duration::from_secs(1);
+
+/// A `--timeout` for the whole run, such as `120s`, `2m` or `500ms`. A bare number is a
+/// number of seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timeout(pub Duration);
+
+impl FromStr for Timeout {
+    type Err = String;
+
+    fn from_str(timeout: &str) -> Result<Self, Self::Err> {
+        let trimmed = timeout.trim();
+        let split = trimmed
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(trimmed.len());
+        let (amount, unit) = trimmed.split_at(split);
+        let amount: u64 = amount
+            .parse()
+            .map_err(|_| format!("'{timeout}' is not a duration such as 120s or 2m"))?;
+
+        let duration = match unit {
+            "ms" => Duration::from_millis(amount),
+            "" | "s" => Duration::from_secs(amount),
+            "m" => Duration::from_secs(amount * 60),
+            "h" => Duration::from_secs(amount * 60 * 60),
+            _ => return Err(format!("'{unit}' is not a unit of time, use ms, s, m or h")),
+        };
+        if duration.is_zero() {
+            return Err("the timeout should be longer than 0s".to_string());
+        }
+
+        Ok(Timeout(duration))
+    }
+}
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.subsec_millis() != 0 {
+            write!(f, "{}ms", self.0.as_millis())
+        } else {
+            write!(f, "{}s", self.0.as_secs())
+        }
+    }
+}
+
+impl serde::Serialize for Timeout {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// The time by which the run should be over.
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    pub fn new(started: Instant, timeout: Timeout) -> Self {
+        Deadline(started + timeout.0)
+    }
+
+    pub fn expired(&self) -> bool {
+        Instant::now() >= self.0
+    }
+
+    /// Time left before the deadline, for bounding the parse of a file.
+    pub fn remaining(&self) -> Duration {
+        self.0.saturating_duration_since(Instant::now())
+    }
+
+    /// Receives the next message until shortly after the deadline, when the work still
+    /// going on, such as reading a file that hangs, is not waited for any longer.
+    pub fn receive<T>(&self, receiver: &mpsc::Receiver<T>) -> Option<T> {
+        let waited = (self.0 + GRACE).saturating_duration_since(Instant::now());
+        receiver.recv_timeout(waited).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeout_test() {
+        assert_eq!("120s".parse(), Ok(Timeout(Duration::from_secs(120))));
+        assert_eq!("2m".parse(), Ok(Timeout(Duration::from_secs(120))));
+        assert_eq!("90".parse(), Ok(Timeout(Duration::from_secs(90))));
+        assert_eq!("1h".parse(), Ok(Timeout(Duration::from_secs(3600))));
+        assert_eq!("500ms".parse(), Ok(Timeout(Duration::from_millis(500))));
+        assert!("0s".parse::<Timeout>().is_err());
+        assert!("2d".parse::<Timeout>().is_err());
+        assert!("s".parse::<Timeout>().is_err());
+
+        assert_eq!(Timeout(Duration::from_secs(120)).to_string(), "120s");
+        assert_eq!(Timeout(Duration::from_millis(1500)).to_string(), "1500ms");
+    }
+
+    #[test]
+    fn receive_test() {
+        let deadline = Deadline::new(Instant::now(), "10ms".parse().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        let started = Instant::now();
+        std::thread::spawn(move || {
+            let _ = sender.send(1);
+            std::thread::sleep(Duration::from_secs(60));
+            let _ = sender.send(2);
+        });
+
+        assert_eq!(deadline.receive(&receiver), Some(1));
+        assert_eq!(deadline.receive(&receiver), None);
+        assert!(deadline.expired());
+        assert!(started.elapsed() < Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,353 @@
+use std::path::{Component, Path, PathBuf};
+
+use tree_sitter::Point;
+
+use crate::snippets::Snippet;
+
+/// Info string word of a fenced Markdown block that leaves it unchecked.
+const OPT_OUT: &str = "no-check";
+
+/// Languages of the code blocks that are checked.
+const PYTHON_LANGUAGES: &[&str] = &["python", "py", "python3", "py3"];
+
+/// Documentation formats whose Python code blocks can be checked with `--docs`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocsFormat {
+    Markdown,
+    ReStructuredText,
+}
+
+impl DocsFormat {
+    /// The format of a documentation file, told by its extension.
+    pub fn of(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "md" | "markdown" => Some(DocsFormat::Markdown),
+            "rst" => Some(DocsFormat::ReStructuredText),
+            _ => None,
+        }
+    }
+
+    pub fn code_blocks(self, text: &str) -> Vec<Snippet> {
+        match self {
+            DocsFormat::Markdown => markdown_blocks(text),
+            DocsFormat::ReStructuredText => rst_blocks(text),
+        }
+    }
+}
+
+/// Files matching the `--docs` globs, such as `docs/**/*.md`, which are matched against
+/// paths as written, from the directory before the first wildcard down.
+pub fn find_docs(patterns: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    for pattern in patterns {
+        let matcher = globset::GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| format!("--docs '{pattern}' is not a valid glob: {}", e.kind()))?
+            .compile_matcher();
+
+        let base: PathBuf = Path::new(pattern)
+            .components()
+            .take_while(|component| {
+                !component
+                    .as_os_str()
+                    .to_string_lossy()
+                    .contains(['*', '?', '[', '{'])
+            })
+            .collect();
+        let base = if base.as_os_str().is_empty() {
+            PathBuf::from(Component::CurDir.as_os_str())
+        } else {
+            base
+        };
+
+        let mut matched: Vec<PathBuf> = walkdir::WalkDir::new(&base)
+            .into_iter()
+            .flatten()
+            .filter(|entry| !entry.file_type().is_dir())
+            .map(|entry| entry.into_path())
+            // Paths walked from `.` start with `./`, which patterns are written without.
+            .filter(|path| {
+                matcher.is_match(path) || matcher.is_match(path.strip_prefix(".").unwrap_or(path))
+            })
+            .collect();
+        if matched.is_empty() {
+            eprintln!("warning: --docs '{pattern}' matches no files.");
+        }
+        matched.sort();
+        files.extend(matched);
+    }
+    files.dedup();
+
+    Ok(files)
+}
+
+/// Fenced code blocks tagged as Python, such as ```` ```python ````, except the ones
+/// whose info string also has `no-check`.
+fn markdown_blocks(text: &str) -> Vec<Snippet> {
+    let mut blocks = Vec::new();
+    // The fence being in, its indentation and whether its block is checked.
+    let mut fence: Option<(&str, usize, Option<Snippet>)> = None;
+
+    for (row, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start_matches(' ');
+        let indent = line.len() - trimmed.len();
+
+        match &mut fence {
+            Some((opening, fence_indent, block)) => {
+                if indent < 4 && closes_fence(trimmed, opening) {
+                    blocks.extend(block.take());
+                    fence = None;
+                } else if let Some(block) = block {
+                    let stripped = indent.min(*fence_indent);
+                    block.push(
+                        &line[stripped..],
+                        Point {
+                            row,
+                            column: stripped,
+                        },
+                    );
+                }
+            }
+            None if indent < 4 => {
+                let Some(opening) = opening_fence(trimmed) else {
+                    continue;
+                };
+                let mut info = trimmed[opening.len()..].split_whitespace();
+                let checked =
+                    info.next().is_some_and(is_python) && info.all(|word| word != OPT_OUT);
+                fence = Some((opening, indent, checked.then(Snippet::default)));
+            }
+            None => {}
+        }
+    }
+
+    blocks
+}
+
+/// The fence opening a code block, three or more backticks or tildes.
+fn opening_fence(line: &str) -> Option<&str> {
+    let marker = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = line.len() - line.trim_start_matches(marker).len();
+    // Backticks cannot appear in the info string of a backtick fence.
+    let valid = length >= 3 && (marker == '~' || !line[length..].contains('`'));
+
+    valid.then(|| &line[..length])
+}
+
+fn closes_fence(line: &str, opening: &str) -> bool {
+    let marker = opening.chars().next().expect("Fences should not be empty.");
+    let length = line.len() - line.trim_start_matches(marker).len();
+
+    length >= opening.len() && line[length..].trim().is_empty()
+}
+
+fn is_python(language: &str) -> bool {
+    // Pandoc style attributes, such as `{.python}`.
+    let language = language.trim_start_matches('{').trim_start_matches('.');
+    let language = language.trim_end_matches('}');
+
+    PYTHON_LANGUAGES
+        .iter()
+        .any(|python| python.eq_ignore_ascii_case(language))
+}
+
+/// The bodies of `code-block` directives for Python, and of literal blocks (introduced
+/// by `::`) while the `highlight` language is Python, as it is by default in Sphinx.
+fn rst_blocks(text: &str) -> Vec<Snippet> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut blocks = Vec::new();
+    let mut highlights_python = true;
+
+    let mut row = 0;
+    while row < lines.len() {
+        let line = lines[row];
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        row += 1;
+
+        let checked = if let Some(directive) = trimmed.strip_prefix("..") {
+            match directive.trim_start().split_once("::") {
+                Some((name, language)) => match name.trim() {
+                    "code-block" | "code" | "sourcecode" => {
+                        let language = language.trim();
+                        // Without a language, the highlight language is used.
+                        if language.is_empty() {
+                            highlights_python
+                        } else {
+                            is_python(language)
+                        }
+                    }
+                    "highlight" => {
+                        highlights_python = is_python(language.trim());
+                        continue;
+                    }
+                    _ => continue,
+                },
+                None => continue,
+            }
+        } else if trimmed.ends_with("::") {
+            highlights_python
+        } else {
+            continue;
+        };
+
+        let mut block = Snippet::default();
+        let mut body = Vec::new();
+        while row < lines.len() {
+            let line = lines[row];
+            let body_indent = line.len() - line.trim_start().len();
+            if !line.trim().is_empty() && body_indent <= indent {
+                break;
+            }
+            body.push((row, line));
+            row += 1;
+        }
+        if !checked {
+            continue;
+        }
+
+        // Directive options, such as `:linenos:`, come right after the directive.
+        let options = body
+            .iter()
+            .take_while(|(_, line)| line.trim_start().starts_with(':'))
+            .count();
+        let body = &body[options..];
+        let Some(body_indent) = body
+            .iter()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(_, line)| line.len() - line.trim_start().len())
+            .min()
+        else {
+            continue;
+        };
+        let first = body
+            .iter()
+            .position(|(_, line)| !line.trim().is_empty())
+            .unwrap_or(0);
+        let last = body
+            .iter()
+            .rposition(|(_, line)| !line.trim().is_empty())
+            .unwrap_or(0);
+        for (row, line) in &body[first..=last] {
+            let column = body_indent.min(line.len());
+            block.push(&line[column..], Point { row: *row, column });
+        }
+        blocks.push(block);
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{snippets::check_snippets, MissingType, Rules};
+
+    /// Kinds and locations, starting at 1, of the findings in the blocks.
+    fn findings(blocks: Vec<Snippet>) -> Vec<(MissingType, usize, usize)> {
+        check_snippets(blocks, &Rules::default())
+            .positions
+            .into_iter()
+            .map(|position| {
+                (
+                    position.missing_type,
+                    position.start.row + 1,
+                    position.start.column + 1,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn markdown_blocks_test() {
+        let text = r#"# Guide
+
+```python
+def greet(name):
+    print(name)
+```
+
+```python no-check
+def skipped(x): ...
+```
+
+```bash
+def not_python(x): ...
+```
+
+- A list item:
+
+  ````py
+  async def fetch(url) -> bytes:
+      return b"""
+  ```
+  """
+  ````
+"#;
+        assert_eq!(
+            findings(DocsFormat::Markdown.code_blocks(text)),
+            [
+                (MissingType::Parameter("name".to_string()), 4, 11),
+                (MissingType::Return("greet".to_string()), 4, 1),
+                (MissingType::Parameter("url".to_string()), 19, 19),
+            ]
+        );
+    }
+
+    #[test]
+    fn rst_blocks_test() {
+        let text = "Guide
+=====
+
+.. code-block:: python
+   :linenos:
+
+   def greet(name):
+       print(name)
+
+An example::
+
+    def literal(x) -> None: ...
+
+.. code-block:: bash
+
+   def not_python(x): ...
+
+.. highlight:: console
+
+Not Python either::
+
+    $ def shell(x): ...
+";
+        assert_eq!(
+            findings(DocsFormat::ReStructuredText.code_blocks(text)),
+            [
+                (MissingType::Parameter("name".to_string()), 7, 14),
+                (MissingType::Return("greet".to_string()), 7, 4),
+                (MissingType::Parameter("x".to_string()), 12, 17),
+            ]
+        );
+    }
+
+    #[test]
+    fn find_docs_test() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in ["docs/guide.md", "docs/api/index.rst", "docs/notes.txt"] {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        let docs = dir.path().join("docs").display().to_string();
+
+        assert_eq!(
+            find_docs(&[format!("{docs}/**/*.md"), format!("{docs}/**/*.rst")]),
+            Ok(vec![
+                dir.path().join("docs/guide.md"),
+                dir.path().join("docs/api/index.rst")
+            ])
+        );
+        assert!(find_docs(&["docs/[".to_string()]).is_err());
+    }
+}
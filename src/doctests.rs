@@ -0,0 +1,216 @@
+use tree_sitter::{Node, Point, Tree};
+
+use crate::{
+    snippets::{check_snippets, Snippet, SnippetFindings},
+    Rules,
+};
+
+/// Checks the functions defined in the `>>>` examples of the file's docstrings as if
+/// they were part of the file.
+pub fn check_doctests(tree: &Tree, source_code: &[u8], rules: &Rules) -> SnippetFindings {
+    let mut docstrings = Vec::new();
+    collect_docstrings(tree.root_node(), &mut docstrings);
+
+    check_snippets(
+        docstrings
+            .into_iter()
+            .flat_map(|docstring| examples(docstring, source_code)),
+        rules,
+    )
+}
+
+/// Strings that are the first statement of the module, a class or a function, including
+/// the ones defined in the bodies of compound statements.
+fn collect_docstrings<'a>(node: Node<'a>, docstrings: &mut Vec<Node<'a>>) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        match child.kind() {
+            "function_definition" | "class_definition" => {
+                if let Some(body) = child.child_by_field_name("body") {
+                    if let Some(docstring) = docstring(body) {
+                        docstrings.push(docstring);
+                    }
+                    collect_docstrings(body, docstrings);
+                }
+            }
+            "decorated_definition"
+            | "block"
+            | "if_statement"
+            | "elif_clause"
+            | "else_clause"
+            | "try_statement"
+            | "except_clause"
+            | "except_group_clause"
+            | "finally_clause"
+            | "with_statement"
+            | "for_statement"
+            | "while_statement"
+            | "match_statement"
+            | "case_clause" => collect_docstrings(child, docstrings),
+            _ => {}
+        }
+    }
+
+    if node.kind() == "module" {
+        if let Some(docstring) = docstring(node) {
+            docstrings.push(docstring);
+        }
+    }
+}
+
+fn docstring(body: Node) -> Option<Node> {
+    let statement = body.named_child(0)?;
+    let string = statement.named_child(0)?;
+
+    (statement.kind() == "expression_statement" && string.kind() == "string").then_some(string)
+}
+
+/// Reassembles the examples of a docstring from their `>>>` and `...` lines, leaving
+/// out the expected output and the prose around them.
+fn examples(docstring: Node, source_code: &[u8]) -> Vec<Snippet> {
+    let Ok(text) = docstring.utf8_text(source_code) else {
+        return Vec::new();
+    };
+    let opening = text.find(['"', '\'']).unwrap_or(0);
+    let quotes = if text[opening..].starts_with("\"\"\"") || text[opening..].starts_with("'''") {
+        3
+    } else {
+        1
+    };
+    let contents = &text[opening + quotes..text.len().saturating_sub(quotes).max(opening + quotes)];
+
+    let mut examples = Vec::new();
+    let mut current: Option<Snippet> = None;
+    for (index, line) in contents.split('\n').enumerate() {
+        let start = Point {
+            row: docstring.start_position().row + index,
+            column: if index == 0 {
+                docstring.start_position().column + opening + quotes
+            } else {
+                0
+            },
+        };
+        let trimmed = line.trim_start();
+        let origin = |prompt: usize| Point {
+            row: start.row,
+            column: start.column + line.len() - trimmed.len() + prompt,
+        };
+
+        if let Some((code, prompt)) = after_prompt(trimmed, ">>>") {
+            examples.extend(current.take());
+            let mut example = Snippet::default();
+            example.push(code, origin(prompt));
+            current = Some(example);
+        } else if let (Some(example), Some((code, prompt))) =
+            (&mut current, after_prompt(trimmed, "..."))
+        {
+            example.push(code, origin(prompt));
+        } else {
+            examples.extend(current.take());
+        }
+    }
+    examples.extend(current);
+
+    examples
+}
+
+/// The code after a prompt, which is followed by a space unless the line is empty, and
+/// the length of the prompt.
+fn after_prompt<'a>(line: &'a str, prompt: &str) -> Option<(&'a str, usize)> {
+    let rest = line.strip_prefix(prompt)?;
+    if rest.trim_end().is_empty() {
+        Some(("", prompt.len()))
+    } else {
+        rest.strip_prefix(' ').map(|code| (code, prompt.len() + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_python_parser, get_tree_from_source, AnnotationCounts, MissingType};
+
+    #[test]
+    fn compound_statements_test() {
+        let source = r#"try:
+    def a():
+        """>>> def f(x): ..."""
+except ImportError:
+    def b():
+        """>>> def f(x): ..."""
+finally:
+    with open("f") as f:
+        for line in f:
+            while True:
+                class C:
+                    """>>> def f(x): ..."""
+if True:
+    pass
+elif False:
+    def d():
+        """>>> def f(x): ..."""
+"#;
+        let mut parser = create_python_parser();
+        let (tree, source_code) = get_tree_from_source(&mut parser, source.as_bytes().to_vec());
+        let doctests = check_doctests(&tree, &source_code, &Rules::default());
+
+        let mut rows: Vec<usize> = doctests
+            .positions
+            .iter()
+            .map(|position| position.start.row + 1)
+            .collect();
+        rows.dedup();
+        assert_eq!(rows, [3, 6, 12, 17]);
+    }
+
+    #[test]
+    fn check_doctests_test() {
+        let source = r#"def greet(name: str) -> str:
+    """Greets.
+
+    >>> def shout(text):
+    ...     return text.upper()
+    >>> shout("hi")
+    'HI'
+    >>> def broken(:
+    """
+    return name
+
+
+class Greeter:
+    '''
+        >>> def typed(x: int) -> int: return x
+    '''
+"#;
+        let mut parser = create_python_parser();
+        let (tree, source_code) = get_tree_from_source(&mut parser, source.as_bytes().to_vec());
+        let doctests = check_doctests(&tree, &source_code, &Rules::default());
+
+        let found: Vec<(&MissingType, usize, usize)> = doctests
+            .positions
+            .iter()
+            .map(|position| {
+                (
+                    &position.missing_type,
+                    position.start.row + 1,
+                    position.start.column + 1,
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (&MissingType::Parameter("text".to_string()), 4, 19),
+                (&MissingType::Return("shout".to_string()), 4, 9),
+            ]
+        );
+        assert_eq!(doctests.unparsable, [8]);
+        assert_eq!(
+            doctests.counts,
+            AnnotationCounts {
+                functions: 2,
+                slots: 4
+            }
+        );
+    }
+}
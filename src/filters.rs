@@ -0,0 +1,670 @@
+use std::{
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+};
+
+use walkdir::DirEntry;
+
+pub trait Filter {
+    fn should_be_processed(&self, entry: &DirEntry) -> bool;
+
+    /// Explanation of why an entry was excluded, as shown by `list-files --why`.
+    fn reason(&self, entry: &DirEntry) -> String;
+}
+
+/// Returns the first filter excluding the entry, if any.
+///
+/// Filters only judge entries below the scanned root: the root itself was explicitly
+/// asked for, so it is always processed, whatever its name (`.`, `..`, `.hidden`...).
+/// Since `filter_entry` prunes rejected directories, each filter only needs to look at
+/// the entry's own name for its ancestors below the root to have been judged as well.
+pub fn rejecting_filter<'a>(
+    filters: &'a [Box<dyn Filter + Sync>],
+    entry: &DirEntry,
+) -> Option<&'a (dyn Filter + Sync)> {
+    if entry.depth() == 0 {
+        return None;
+    }
+
+    filters
+        .iter()
+        .find(|filter| !filter.should_be_processed(entry))
+        .map(|filter| filter.as_ref())
+}
+
+pub struct NotHidden;
+
+impl Filter for NotHidden {
+    fn should_be_processed(&self, entry: &DirEntry) -> bool {
+        let dot_hidden = entry
+            .file_name()
+            .to_str()
+            .map(|s| s.starts_with('.'))
+            .unwrap_or(true);
+
+        !dot_hidden && !has_hidden_attribute(entry)
+    }
+
+    fn reason(&self, _entry: &DirEntry) -> String {
+        "hidden (--ignore-hidden)".to_string()
+    }
+}
+
+/// Checks the Windows `FILE_ATTRIBUTE_HIDDEN` attribute, which is how hidden-ness is
+/// expressed there regardless of the file name.
+#[cfg(windows)]
+fn has_hidden_attribute(entry: &DirEntry) -> bool {
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+
+    entry
+        .metadata()
+        .map(|metadata| metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(windows))]
+fn has_hidden_attribute(_entry: &DirEntry) -> bool {
+    false
+}
+
+pub struct NotTest;
+
+const TEST_DIRECTORIES: [&str; 3] = ["tests", "test", "testing"];
+
+impl Filter for NotTest {
+    fn should_be_processed(&self, entry: &DirEntry) -> bool {
+        let in_test_directory = relative_components(entry)
+            .iter()
+            .any(|component| TEST_DIRECTORIES.contains(component));
+
+        if in_test_directory {
+            return false;
+        }
+
+        if entry.file_type().is_dir() {
+            return true;
+        }
+
+        entry
+            .file_name()
+            .to_str()
+            .map(|s| !is_test_file(s))
+            .unwrap_or(false)
+    }
+
+    fn reason(&self, _entry: &DirEntry) -> String {
+        "test code (--ignore-tests)".to_string()
+    }
+}
+
+fn is_test_file(name: &str) -> bool {
+    let Some(stem) = name.strip_suffix(".py") else {
+        return false;
+    };
+
+    stem.starts_with("test_") || stem.ends_with("_test") || stem == "conftest"
+}
+
+/// Prunes directories whose name is exactly one of the given names.
+pub struct IgnoreDirs(pub Vec<String>);
+
+impl Filter for IgnoreDirs {
+    fn should_be_processed(&self, entry: &DirEntry) -> bool {
+        if !entry.file_type().is_dir() {
+            return true;
+        }
+
+        entry
+            .file_name()
+            .to_str()
+            .map(|s| !self.0.iter().any(|name| name == s))
+            .unwrap_or(true)
+    }
+
+    fn reason(&self, _entry: &DirEntry) -> String {
+        "ignored directory (--ignore-dirs)".to_string()
+    }
+}
+
+/// Directories of installed or generated Python that are not walked unless
+/// `--no-default-excludes` is given.
+pub const DEFAULT_EXCLUDES: [&str; 5] = [".venv", "venv", "__pycache__", ".tox", "site-packages"];
+
+/// Prunes the [`DEFAULT_EXCLUDES`] directories.
+pub struct DefaultExcludes;
+
+impl Filter for DefaultExcludes {
+    fn should_be_processed(&self, entry: &DirEntry) -> bool {
+        !entry.file_type().is_dir()
+            || entry
+                .file_name()
+                .to_str()
+                .is_none_or(|name| !DEFAULT_EXCLUDES.contains(&name))
+    }
+
+    fn reason(&self, _entry: &DirEntry) -> String {
+        "excluded by default (--no-default-excludes)".to_string()
+    }
+}
+
+/// Excludes entries matching any of a set of glob patterns.
+pub struct GlobExclude(pub Vec<GlobPattern>);
+
+/// Includes only the files matching any of a set of glob patterns. Directories are all
+/// walked, so that the files below them can still match.
+pub struct GlobInclude(pub Vec<GlobPattern>);
+
+/// A glob pattern along with where it came from.
+pub struct GlobPattern {
+    pattern: String,
+    matcher: globset::GlobMatcher,
+    anchor: Anchor,
+    source: String,
+}
+
+/// What the path matched against a pattern is relative to.
+enum Anchor {
+    /// Only the entry's name is matched.
+    Name,
+    Directory(PathBuf),
+    /// The directory being checked, whichever it is.
+    ScanRoot,
+}
+
+impl GlobPattern {
+    /// Patterns without an anchor are matched against entry names, while anchored ones
+    /// are matched against the entry's path relative to the anchor directory.
+    pub fn new(pattern: &str, anchor: Option<&Path>, source: String) -> Result<Self, String> {
+        let matcher = globset::Glob::new(pattern)
+            .map_err(|e| format!("invalid exclude pattern '{pattern}' from {source}: {e}"))?
+            .compile_matcher();
+
+        Ok(GlobPattern {
+            pattern: pattern.to_string(),
+            matcher,
+            anchor: anchor.map_or(Anchor::Name, |anchor| {
+                Anchor::Directory(absolute_path(anchor))
+            }),
+            source,
+        })
+    }
+
+    /// Patterns with a `/`, such as `src/**/generated`, are matched against the entry's
+    /// path relative to the checked directory, with `*` not crossing a `/`, while the
+    /// others, such as `*_pb2.py`, are matched against entry names.
+    pub fn relative_to_root(pattern: &str, source: String) -> Result<Self, String> {
+        let pattern = pattern.trim_start_matches("./");
+        let anchored = pattern.contains('/');
+        let matcher = globset::GlobBuilder::new(pattern.trim_end_matches('/'))
+            .literal_separator(anchored)
+            .build()
+            .map_err(|e| format!("invalid pattern '{pattern}' from {source}: {}", e.kind()))?
+            .compile_matcher();
+
+        Ok(GlobPattern {
+            pattern: pattern.to_string(),
+            matcher,
+            anchor: if anchored {
+                Anchor::ScanRoot
+            } else {
+                Anchor::Name
+            },
+            source,
+        })
+    }
+
+    fn matches(&self, entry: &DirEntry) -> bool {
+        match &self.anchor {
+            Anchor::Name => self.matcher.is_match(entry.file_name()),
+            Anchor::Directory(anchor) => absolute_path(entry.path())
+                .strip_prefix(anchor)
+                .map(|relative| self.matcher.is_match(relative))
+                .unwrap_or(false),
+            Anchor::ScanRoot => {
+                // The walk's depth counts the components below the checked directory.
+                let components: Vec<Component> = entry.path().components().collect();
+                let relative: PathBuf = components
+                    [components.len().saturating_sub(entry.depth())..]
+                    .iter()
+                    .collect();
+                self.matcher.is_match(relative)
+            }
+        }
+    }
+}
+
+impl Filter for GlobExclude {
+    fn should_be_processed(&self, entry: &DirEntry) -> bool {
+        !self.0.iter().any(|pattern| pattern.matches(entry))
+    }
+
+    fn reason(&self, entry: &DirEntry) -> String {
+        self.0
+            .iter()
+            .find(|pattern| pattern.matches(entry))
+            .map(|pattern| format!("matches '{}' from {}", pattern.pattern, pattern.source))
+            .unwrap_or_default()
+    }
+}
+
+impl Filter for GlobInclude {
+    fn should_be_processed(&self, entry: &DirEntry) -> bool {
+        entry.file_type().is_dir() || self.0.iter().any(|pattern| pattern.matches(entry))
+    }
+
+    fn reason(&self, _entry: &DirEntry) -> String {
+        "does not match any --include pattern".to_string()
+    }
+}
+
+/// Excludes files outside of packages, i.e. in directories without an `__init__.py`.
+/// With `namespace_packages`, directories nested in a package count as packages even
+/// without one.
+pub struct PackagesOnly {
+    namespace_packages: bool,
+    /// Package-ness of the directories leading to the entry being walked, indexed by
+    /// depth, so that each directory is only looked at once.
+    ancestors: Mutex<Vec<(PathBuf, bool)>>,
+}
+
+impl PackagesOnly {
+    pub fn new(namespace_packages: bool) -> Self {
+        PackagesOnly {
+            namespace_packages,
+            ancestors: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn is_package(&self, directory: &Path, parent_is_package: bool) -> bool {
+        directory.join("__init__.py").is_file() || (self.namespace_packages && parent_is_package)
+    }
+
+    /// Whether the entry's parent is a package, looking it up in the walked directories
+    /// and falling back to looking at the directory itself, e.g. for the scanned root.
+    fn parent_is_package(ancestors: &[(PathBuf, bool)], entry: &DirEntry) -> Option<bool> {
+        let parent = entry.path().parent()?;
+
+        ancestors
+            .get(entry.depth().checked_sub(1)?)
+            .filter(|(directory, _)| directory == parent)
+            .map(|(_, is_package)| *is_package)
+    }
+}
+
+impl Filter for PackagesOnly {
+    fn should_be_processed(&self, entry: &DirEntry) -> bool {
+        let mut ancestors = self
+            .ancestors
+            .lock()
+            .expect("Should be able to get a lock on the walked packages.");
+
+        let parent_is_package = Self::parent_is_package(&ancestors, entry).unwrap_or_else(|| {
+            entry
+                .path()
+                .parent()
+                .is_some_and(|parent| parent.join("__init__.py").is_file())
+        });
+
+        if !entry.file_type().is_dir() {
+            return parent_is_package;
+        }
+
+        let is_package = self.is_package(entry.path(), parent_is_package);
+        ancestors.truncate(entry.depth());
+        while ancestors.len() < entry.depth() {
+            let parent = entry.path().parent().unwrap_or(entry.path()).to_path_buf();
+            ancestors.push((parent, parent_is_package));
+        }
+        ancestors.push((entry.path().to_path_buf(), is_package));
+
+        true
+    }
+
+    fn reason(&self, _entry: &DirEntry) -> String {
+        "not in a package (--packages-only)".to_string()
+    }
+}
+
+/// Makes the path absolute, resolving `.` and `..` lexically so that paths given with
+/// them can still be compared with anchors.
+fn absolute_path(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Components of the entry's path below the scanned root, excluding the entry itself
+/// when it is a file.
+fn relative_components(entry: &DirEntry) -> Vec<&str> {
+    let mut components: Vec<&str> = entry
+        .path()
+        .components()
+        .rev()
+        .take(entry.depth())
+        .filter_map(|component| component.as_os_str().to_str())
+        .collect();
+    components.reverse();
+
+    if !entry.file_type().is_dir() {
+        components.pop();
+    }
+
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{check_directory, default_options, fixture_dir};
+
+    #[test]
+    fn hidden_dot_test() {
+        let dir = fixture_dir();
+        std::fs::create_dir(dir.path().join(".venv")).unwrap();
+        std::fs::write(dir.path().join(".venv/lib.py"), "def f(x): ...").unwrap();
+        std::fs::write(dir.path().join("app.py"), "def f(x): ...").unwrap();
+
+        let filters: Vec<Box<dyn Filter + Sync>> = vec![Box::new(NotHidden)];
+        let (_, summary) = check_directory(dir.path(), &filters, &default_options());
+        assert_eq!(summary.files_checked, 1);
+    }
+
+    #[test]
+    fn hidden_root_test() {
+        let filters: Vec<Box<dyn Filter + Sync>> = vec![Box::new(NotHidden)];
+
+        for root in [".", "./src", "..", "./src/..", ".git"] {
+            let root_entry = walkdir::WalkDir::new(root)
+                .max_depth(0)
+                .into_iter()
+                .next()
+                .unwrap()
+                .unwrap();
+            assert!(rejecting_filter(&filters, &root_entry).is_none(), "{root}");
+        }
+    }
+
+    #[test]
+    fn hidden_below_root_test() {
+        let dir = fixture_dir();
+        std::fs::create_dir_all(dir.path().join(".hidden_dir/.cache")).unwrap();
+        std::fs::create_dir_all(dir.path().join("pkg/.cache")).unwrap();
+        std::fs::create_dir_all(dir.path().join("other")).unwrap();
+        for file in [
+            ".hidden_dir/module.py",
+            ".hidden_dir/.cache/module.py",
+            "pkg/module.py",
+            "pkg/.hidden_module.py",
+            "pkg/.cache/module.py",
+        ] {
+            std::fs::write(dir.path().join(file), "def f(x): ...").unwrap();
+        }
+
+        let filters: Vec<Box<dyn Filter + Sync>> = vec![Box::new(NotHidden)];
+
+        let (_, summary) = check_directory(dir.path(), &filters, &default_options());
+        assert_eq!(summary.files_checked, 1);
+
+        let (_, summary) = check_directory(
+            &dir.path().join(".hidden_dir"),
+            &filters,
+            &default_options(),
+        );
+        assert_eq!(summary.files_checked, 1);
+
+        let (_, summary) = check_directory(
+            &dir.path().join("other/../pkg"),
+            &filters,
+            &default_options(),
+        );
+        assert_eq!(summary.files_checked, 1);
+    }
+
+    #[test]
+    fn not_test_test() {
+        let dir = fixture_dir();
+        for directory in [
+            "tests",
+            "test",
+            "testing",
+            "pkg/tests",
+            "pkg/testing/unit",
+            "src",
+        ] {
+            std::fs::create_dir_all(dir.path().join(directory)).unwrap();
+        }
+        for file in [
+            "app.py",
+            "conftest.py",
+            "test_app.py",
+            "app_test.py",
+            "testimony.py",
+            "tests/helpers.py",
+            "test/helpers.py",
+            "testing/helpers.py",
+            "pkg/tests/helpers.py",
+            "pkg/testing/unit/helpers.py",
+            "pkg/contest.py",
+            "src/module.py",
+            "src/conftest.py",
+            "src/test_module.py",
+            "src/module_test.py",
+        ] {
+            std::fs::write(dir.path().join(file), "def f(x): ...").unwrap();
+        }
+
+        let filters: Vec<Box<dyn Filter + Sync>> = vec![Box::new(NotTest)];
+        let (message, summary) = check_directory(dir.path(), &filters, &default_options());
+
+        let mut checked: Vec<&str> = message
+            .lines()
+            .filter_map(|line| line.strip_prefix("File: "))
+            .map(|path| {
+                path.strip_prefix(dir.path().to_str().unwrap())
+                    .unwrap()
+                    .trim_start_matches('/')
+            })
+            .collect();
+        checked.sort();
+
+        assert_eq!(
+            checked,
+            ["app.py", "pkg/contest.py", "src/module.py", "testimony.py"]
+        );
+        assert_eq!(summary.files_checked, 4);
+
+        let (_, summary) = check_directory(&dir.path().join("tests"), &filters, &default_options());
+        assert_eq!(summary.files_checked, 1);
+    }
+
+    #[test]
+    fn ignore_dirs_test() {
+        let dir = fixture_dir();
+        for directory in [
+            "app/migrations",
+            "vendored/lib",
+            "Migrations",
+            ".migrations",
+        ] {
+            std::fs::create_dir_all(dir.path().join(directory)).unwrap();
+        }
+        for file in [
+            "app/models.py",
+            "app/migrations/0001_initial.py",
+            "vendored/lib/module.py",
+            "Migrations/module.py",
+            ".migrations/module.py",
+            "migrations.py",
+        ] {
+            std::fs::write(dir.path().join(file), "def f(x): ...").unwrap();
+        }
+
+        let ignore_dirs = IgnoreDirs(vec!["migrations".to_string(), "vendored".to_string()]);
+        let filters: Vec<Box<dyn Filter + Sync>> = vec![Box::new(ignore_dirs)];
+        let (_, summary) = check_directory(dir.path(), &filters, &default_options());
+        assert_eq!(summary.files_checked, 4);
+
+        let ignore_dirs = IgnoreDirs(vec!["migrations".to_string()]);
+        let filters: Vec<Box<dyn Filter + Sync>> = vec![Box::new(NotHidden), Box::new(ignore_dirs)];
+        let (_, summary) = check_directory(dir.path(), &filters, &default_options());
+        assert_eq!(summary.files_checked, 4);
+    }
+
+    #[test]
+    fn default_excludes_test() {
+        let dir = fixture_dir();
+        for directory in [".venv/lib", "pkg/__pycache__", "site-packages", "venvs"] {
+            std::fs::create_dir_all(dir.path().join(directory)).unwrap();
+        }
+        for file in [
+            ".venv/lib/module.py",
+            "pkg/__pycache__/module.py",
+            "pkg/app.py",
+            "site-packages/module.py",
+            "venvs/module.py",
+        ] {
+            std::fs::write(dir.path().join(file), "def f(x): ...").unwrap();
+        }
+
+        let filters: Vec<Box<dyn Filter + Sync>> = vec![Box::new(DefaultExcludes)];
+        let (_, summary) = check_directory(dir.path(), &filters, &default_options());
+        assert_eq!(summary.files_checked, 2);
+        let (_, summary) = check_directory(&dir.path().join(".venv"), &filters, &default_options());
+        assert_eq!(summary.files_checked, 1);
+    }
+
+    #[test]
+    fn glob_exclude_test() {
+        let dir = fixture_dir();
+        std::fs::create_dir_all(dir.path().join("src/generated")).unwrap();
+        std::fs::create_dir_all(dir.path().join("build")).unwrap();
+        for file in [
+            "src/app.py",
+            "src/app_pb2.py",
+            "src/generated/module.py",
+            "build/module.py",
+        ] {
+            std::fs::write(dir.path().join(file), "def f(x): ...").unwrap();
+        }
+
+        let patterns = vec![
+            GlobPattern::new("build", None, "test".to_string()).unwrap(),
+            GlobPattern::new("*_pb2.py", None, "test".to_string()).unwrap(),
+            GlobPattern::new("src/generated", Some(dir.path()), "test".to_string()).unwrap(),
+        ];
+        let filters: Vec<Box<dyn Filter + Sync>> = vec![Box::new(GlobExclude(patterns))];
+        let (_, summary) = check_directory(
+            &dir.path().join("src/../src/.."),
+            &filters,
+            &default_options(),
+        );
+        assert_eq!(summary.files_checked, 1);
+    }
+
+    #[test]
+    fn glob_include_test() {
+        let dir = fixture_dir();
+        std::fs::create_dir_all(dir.path().join("app/src/core/migrations")).unwrap();
+        std::fs::create_dir_all(dir.path().join("app/third_party/lib")).unwrap();
+        for file in [
+            "app/setup.py",
+            "app/src/main.py",
+            "app/src/core/models.py",
+            "app/src/core/models_pb2.py",
+            "app/src/core/migrations/0001.py",
+            "app/third_party/lib/vendored.py",
+        ] {
+            std::fs::write(dir.path().join(file), "def f(x): ...").unwrap();
+        }
+        let patterns = |patterns: &[&str]| -> Vec<GlobPattern> {
+            patterns
+                .iter()
+                .map(|pattern| GlobPattern::relative_to_root(pattern, "test".to_string()))
+                .collect::<Result<_, _>>()
+                .unwrap()
+        };
+
+        let filters: Vec<Box<dyn Filter + Sync>> = vec![
+            Box::new(GlobExclude(patterns(&[
+                "migrations",
+                "*_pb2.py",
+                "third_party/",
+            ]))),
+            Box::new(GlobInclude(patterns(&["src/**"]))),
+        ];
+        let (_, summary) = check_directory(&dir.path().join("app"), &filters, &default_options());
+        assert_eq!(summary.files_checked, 2);
+
+        // `*` does not cross directories in patterns relative to the checked directory.
+        let filters: Vec<Box<dyn Filter + Sync>> =
+            vec![Box::new(GlobInclude(patterns(&["./src/*.py"])))];
+        let (_, summary) = check_directory(&dir.path().join("app"), &filters, &default_options());
+        assert_eq!(summary.files_checked, 1);
+
+        assert!(GlobPattern::relative_to_root("src/[", "test".to_string()).is_err());
+    }
+
+    #[test]
+    fn packages_only_test() {
+        let dir = fixture_dir();
+        for directory in ["pkg/sub", "pkg/namespace", "scripts", "src/app"] {
+            std::fs::create_dir_all(dir.path().join(directory)).unwrap();
+        }
+        for file in [
+            "setup.py",
+            "pkg/__init__.py",
+            "pkg/module.py",
+            "pkg/sub/__init__.py",
+            "pkg/sub/module.py",
+            "pkg/namespace/module.py",
+            "scripts/one_off.py",
+            "src/app/__init__.py",
+        ] {
+            std::fs::write(dir.path().join(file), "def f(x): ...").unwrap();
+        }
+
+        let filters: Vec<Box<dyn Filter + Sync>> = vec![Box::new(PackagesOnly::new(false))];
+        let (_, summary) = check_directory(dir.path(), &filters, &default_options());
+        assert_eq!(summary.files_checked, 5);
+
+        let filters: Vec<Box<dyn Filter + Sync>> = vec![Box::new(PackagesOnly::new(true))];
+        let (_, summary) = check_directory(dir.path(), &filters, &default_options());
+        assert_eq!(summary.files_checked, 6);
+
+        let (_, summary) = check_directory(&dir.path().join("pkg"), &filters, &default_options());
+        assert_eq!(summary.files_checked, 5);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn hidden_attribute_test() {
+        let dir = fixture_dir();
+        std::fs::create_dir(dir.path().join("cache")).unwrap();
+        std::fs::write(dir.path().join("cache/lib.py"), "def f(x): ...").unwrap();
+        std::fs::write(dir.path().join("app.py"), "def f(x): ...").unwrap();
+
+        let status = std::process::Command::new("attrib")
+            .arg("+h")
+            .arg(dir.path().join("cache"))
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let filters: Vec<Box<dyn Filter + Sync>> = vec![Box::new(NotHidden)];
+        let (_, summary) = check_directory(dir.path(), &filters, &default_options());
+        assert_eq!(summary.files_checked, 1);
+    }
+}
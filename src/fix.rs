@@ -0,0 +1,480 @@
+use std::{fmt::Write, path::PathBuf};
+
+use rayon::prelude::*;
+
+use crate::{
+    create_python_parser, find_missing_types_positions, paths::display_path, MissingType, Position,
+    Rules,
+};
+
+/// Lines of unchanged source shown around each change of a diff.
+const CONTEXT_LINES: usize = 3;
+
+/// The import added to files that annotate parameters with `Any` without importing it.
+const ANY_IMPORT: &str = "from typing import Any";
+
+/// Source rewritten by [`fix_source`].
+#[derive(Debug, PartialEq)]
+pub struct Fixed {
+    pub source: String,
+    /// The line before which [`ANY_IMPORT`] was inserted, if it was. Every other line
+    /// stays on the line it was on, so that the diff can pair them up.
+    import_row: Option<usize>,
+}
+
+/// Replacement of the bytes `start..end` of a source.
+struct Edit {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+/// Fixes the files with placeholder annotations, or with `dry_run`, prints the diff
+/// that doing so would make instead. Files that cannot be read or parsed are left
+/// alone, with a note. Returns how many files were, or would be, fixed.
+pub fn fix_files(files: &[PathBuf], rules: &Rules, dry_run: bool) -> usize {
+    let fixes: Vec<_> = files
+        .par_iter()
+        .filter(|file| crate::docs::DocsFormat::of(file).is_none())
+        .map(|file| (file, fix_file(file, rules)))
+        .collect();
+
+    let mut fixed_files = 0;
+    for (file, fix) in fixes {
+        let (original, fixed) = match fix {
+            Ok(Some(fix)) => fix,
+            Ok(None) => continue,
+            Err(reason) => {
+                eprintln!("note: not fixing '{}', as {reason}.", file.display());
+                continue;
+            }
+        };
+
+        if dry_run {
+            print!(
+                "{}",
+                unified_diff(&display_path(file, false), &original, &fixed)
+            );
+        } else if let Err(e) = std::fs::write(file, &fixed.source) {
+            eprintln!("warning: could not fix '{}': {e}.", file.display());
+            continue;
+        }
+        fixed_files += 1;
+    }
+
+    fixed_files
+}
+
+fn fix_file(file: &std::path::Path, rules: &Rules) -> Result<Option<(String, Fixed)>, String> {
+    let contents = std::fs::read(file).map_err(|e| format!("it could not be read: {e}"))?;
+    let source = String::from_utf8(contents).map_err(|_| "it is not valid UTF-8".to_string())?;
+
+    let tree = create_python_parser()
+        .parse(&source, None)
+        .expect("Parsing without a timeout should not fail.");
+    if tree.root_node().has_error() {
+        return Err("it does not parse".to_string());
+    }
+
+    let (positions, _) = find_missing_types_positions(source.as_bytes(), tree.clone(), rules);
+
+    Ok(fix_source(&source, &tree, &positions).map(|fixed| (source, fixed)))
+}
+
+/// Annotates unannotated parameters with `Any`, importing it when needed, and functions
+/// without a return type with `-> None` when no `return` in them returns a value and they
+/// do not `yield`. Other findings are left alone. Returns `None` when nothing changes.
+pub fn fix_source(source: &str, tree: &tree_sitter::Tree, positions: &[Position]) -> Option<Fixed> {
+    let root = tree.root_node();
+    let mut edits = Vec::new();
+
+    for position in positions {
+        let Some(node) = root.descendant_for_point_range(position.start, position.end) else {
+            continue;
+        };
+
+        match &position.missing_type {
+            MissingType::Parameter(_) => {
+                let Some(parameter) = enclosing(node, |node| {
+                    node.start_position() == position.start && node.end_position() == position.end
+                }) else {
+                    continue;
+                };
+                edits.push(parameter_edit(parameter, source));
+            }
+            MissingType::Return(_) => {
+                let Some(function) = enclosing(node, |node| node.kind() == "function_definition")
+                else {
+                    continue;
+                };
+                let (Some(parameters), Some(body)) = (
+                    function.child_by_field_name("parameters"),
+                    function.child_by_field_name("body"),
+                ) else {
+                    continue;
+                };
+                if !returns_value(body) {
+                    edits.push(Edit {
+                        start: parameters.end_byte(),
+                        end: parameters.end_byte(),
+                        text: " -> None".to_string(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if edits.is_empty() {
+        return None;
+    }
+
+    let mut import_row = None;
+    let annotates_any = edits.iter().any(|edit| edit.text.starts_with(": Any"));
+    if annotates_any && !imports_any(root, source) {
+        let (start, row) = import_location(root, source);
+        edits.push(Edit {
+            start,
+            end: start,
+            text: format!("{ANY_IMPORT}{}", line_ending(source)),
+        });
+        import_row = Some(row);
+    }
+
+    // Applied from the bottom of the file up, so that the offsets of the edits still to
+    // apply remain valid.
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.start));
+    let mut fixed = source.to_string();
+    for edit in edits {
+        fixed.replace_range(edit.start..edit.end, &edit.text);
+    }
+
+    Some(Fixed {
+        source: fixed,
+        import_row,
+    })
+}
+
+/// The node or its closest ancestor satisfying the predicate.
+fn enclosing<'tree>(
+    node: tree_sitter::Node<'tree>,
+    predicate: impl Fn(&tree_sitter::Node<'tree>) -> bool,
+) -> Option<tree_sitter::Node<'tree>> {
+    let mut current = Some(node);
+    while let Some(node) = current {
+        if predicate(&node) {
+            return Some(node);
+        }
+        current = node.parent();
+    }
+
+    None
+}
+
+/// Annotates the parameter with `Any`, such as `*args: Any` or `x: Any = 3`, spacing the
+/// default as PEP 8 does for annotated parameters unless it is on another line.
+fn parameter_edit(parameter: tree_sitter::Node, source: &str) -> Edit {
+    let name = parameter.child_by_field_name("name");
+    let value = parameter.child_by_field_name("value");
+
+    match (parameter.kind(), name, value) {
+        ("default_parameter", Some(name), Some(value))
+            if !source[name.end_byte()..value.start_byte()].contains('\n') =>
+        {
+            Edit {
+                start: name.end_byte(),
+                end: value.start_byte(),
+                text: ": Any = ".to_string(),
+            }
+        }
+        (_, name, _) => {
+            let end = name.unwrap_or(parameter).end_byte();
+            Edit {
+                start: end,
+                end,
+                text: ": Any".to_string(),
+            }
+        }
+    }
+}
+
+/// Whether a `return` in the body, outside of nested functions and classes, returns a
+/// value, or the body yields, in which case `-> None` would be wrong.
+fn returns_value(body: tree_sitter::Node) -> bool {
+    let mut cursor = body.walk();
+    let children: Vec<_> = body.named_children(&mut cursor).collect();
+
+    children.into_iter().any(|child| match child.kind() {
+        "function_definition" | "class_definition" | "lambda" => false,
+        "return_statement" => child.named_child_count() > 0,
+        "yield" => true,
+        _ => returns_value(child),
+    })
+}
+
+/// Whether the module imports a name `Any` at its top level, from `typing` or elsewhere.
+fn imports_any(root: tree_sitter::Node, source: &str) -> bool {
+    let mut cursor = root.walk();
+    let statements: Vec<_> = root.named_children(&mut cursor).collect();
+
+    statements
+        .into_iter()
+        .filter(|statement| statement.kind() == "import_from_statement")
+        .any(|statement| {
+            let mut cursor = statement.walk();
+            let has_wildcard = statement
+                .named_children(&mut cursor)
+                .any(|child| child.kind() == "wildcard_import");
+            let module = statement
+                .child_by_field_name("module_name")
+                .map(|module| &source[module.byte_range()]);
+            let names: Vec<_> = statement
+                .children_by_field_name("name", &mut cursor)
+                .collect();
+
+            (has_wildcard && module == Some("typing"))
+                || names.into_iter().any(|name| {
+                    let bound = name.child_by_field_name("alias").unwrap_or(name);
+                    &source[bound.byte_range()] == "Any"
+                })
+        })
+}
+
+/// Where the import goes: at the start of the line of the first statement that is
+/// neither the module's docstring nor a `__future__` import. Returns the byte offset and
+/// the row.
+fn import_location(root: tree_sitter::Node, source: &str) -> (usize, usize) {
+    let mut cursor = root.walk();
+    let statements: Vec<_> = root
+        .named_children(&mut cursor)
+        .filter(|node| node.kind() != "comment")
+        .collect();
+
+    let is_docstring = |node: &tree_sitter::Node| {
+        node.kind() == "expression_statement"
+            && node.named_child_count() == 1
+            && node
+                .named_child(0)
+                .is_some_and(|child| child.kind() == "string")
+    };
+    let skipped = statements
+        .iter()
+        .enumerate()
+        .take_while(|(index, node)| {
+            (*index == 0 && is_docstring(node)) || node.kind() == "future_import_statement"
+        })
+        .count();
+    let Some(first) = statements.get(skipped) else {
+        let end = source.len();
+        return (end, source.lines().count());
+    };
+
+    let start = source[..first.start_byte()]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+
+    (start, first.start_position().row)
+}
+
+/// The line ending used by the source, so that added lines match it.
+fn line_ending(source: &str) -> &'static str {
+    match source.find('\n') {
+        Some(newline) if source[..newline].ends_with('\r') => "\r\n",
+        _ => "\n",
+    }
+}
+
+/// A line of the original and the fixed source, lined up.
+enum Line<'a> {
+    Same(&'a str),
+    Changed(&'a str, &'a str),
+    Inserted(&'a str),
+}
+
+/// Renders the changes from the original to the fixed source as a unified diff.
+pub fn unified_diff(path: &str, original: &str, fixed: &Fixed) -> String {
+    let mut fixed_lines = fixed.source.split_inclusive('\n');
+    let mut lines = Vec::new();
+    for (row, original_line) in original.split_inclusive('\n').enumerate() {
+        if fixed.import_row == Some(row) {
+            lines.push(Line::Inserted(fixed_lines.next().unwrap_or_default()));
+        }
+        match fixed_lines.next() {
+            Some(line) if line == original_line => lines.push(Line::Same(line)),
+            Some(line) => lines.push(Line::Changed(original_line, line)),
+            None => break,
+        }
+    }
+    lines.extend(fixed_lines.map(Line::Inserted));
+
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Same(_)))
+        .map(|(index, _)| index)
+        .collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    // Changes close enough for their context to touch share a hunk.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for index in changed {
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + CONTEXT_LINES + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut diff = format!("--- {path}\n+++ {path}\n");
+    let (mut original_row, mut fixed_row) = (0, 0);
+    let mut cursor = 0;
+    for (start, end) in hunks {
+        for line in &lines[cursor..start] {
+            let (original_count, fixed_count) = line_counts(line);
+            original_row += original_count;
+            fixed_row += fixed_count;
+        }
+
+        let (original_len, fixed_len) = lines[start..end]
+            .iter()
+            .map(line_counts)
+            .fold((0, 0), |(a, b), (c, d)| (a + c, b + d));
+        let _ = writeln!(
+            diff,
+            "@@ -{},{original_len} +{},{fixed_len} @@",
+            original_row + 1,
+            fixed_row + 1
+        );
+        for line in &lines[start..end] {
+            match line {
+                Line::Same(line) => push_line(&mut diff, ' ', line),
+                Line::Changed(original, fixed) => {
+                    push_line(&mut diff, '-', original);
+                    push_line(&mut diff, '+', fixed);
+                }
+                Line::Inserted(line) => push_line(&mut diff, '+', line),
+            }
+        }
+
+        original_row += original_len;
+        fixed_row += fixed_len;
+        cursor = end;
+    }
+
+    diff
+}
+
+fn line_counts(line: &Line) -> (usize, usize) {
+    match line {
+        Line::Same(_) | Line::Changed(..) => (1, 1),
+        Line::Inserted(_) => (0, 1),
+    }
+}
+
+fn push_line(diff: &mut String, prefix: char, line: &str) {
+    diff.push(prefix);
+    diff.push_str(line);
+    if !line.ends_with('\n') {
+        diff.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::default_options;
+
+    fn fix(source: &str) -> Option<Fixed> {
+        let tree = create_python_parser().parse(source, None).unwrap();
+        let rules = default_options().rules;
+        let (positions, _) = find_missing_types_positions(source.as_bytes(), tree.clone(), &rules);
+
+        fix_source(source, &tree, &positions)
+    }
+
+    #[test]
+    fn fix_source_test() {
+        let fixed = fix(concat!(
+            "\"\"\"Docs.\"\"\"\r\n",
+            "from __future__ import annotations\r\n",
+            "import os\r\n",
+            "\r\n",
+            "def f(x, y=3, *args, z: int = 1, **kwargs):  \r\n",
+            "    def g():\r\n",
+            "        return 1\r\n",
+            "    return\r\n",
+            "\r\n",
+            "class A:\r\n",
+            "    def h(self, w) -> int:\r\n",
+            "        return 1\r\n",
+            "\r\n",
+            "def counter():\r\n",
+            "    yield 1\r\n",
+        ))
+        .unwrap();
+
+        assert_eq!(
+            fixed.source,
+            concat!(
+                "\"\"\"Docs.\"\"\"\r\n",
+                "from __future__ import annotations\r\n",
+                "from typing import Any\r\n",
+                "import os\r\n",
+                "\r\n",
+                "def f(x: Any, y: Any = 3, *args: Any, z: int = 1, **kwargs: Any) -> None:  \r\n",
+                "    def g():\r\n",
+                "        return 1\r\n",
+                "    return\r\n",
+                "\r\n",
+                "class A:\r\n",
+                "    def h(self, w: Any) -> int:\r\n",
+                "        return 1\r\n",
+                "\r\n",
+                "def counter():\r\n",
+                "    yield 1\r\n",
+            )
+        );
+        assert_eq!(fixed.import_row, Some(2));
+
+        let fixed = fix("from typing import Any as Any\n\ndef f(x):\n    return x\n").unwrap();
+        assert_eq!(
+            fixed.source,
+            "from typing import Any as Any\n\ndef f(x: Any):\n    return x\n"
+        );
+        assert_eq!(fix("def f() -> None: ...\n"), None);
+    }
+
+    #[test]
+    fn unified_diff_test() {
+        let original = "import os\n\n\n\n\n\n\ndef f(x):\n    pass\n\ndef g(y) -> None: pass";
+        let fixed = fix(original).unwrap();
+
+        assert_eq!(
+            unified_diff("app.py", original, &fixed),
+            concat!(
+                "--- app.py\n",
+                "+++ app.py\n",
+                "@@ -1,3 +1,4 @@\n",
+                "+from typing import Any\n",
+                " import os\n",
+                " \n",
+                " \n",
+                "@@ -5,7 +6,7 @@\n",
+                " \n",
+                " \n",
+                " \n",
+                "-def f(x):\n",
+                "+def f(x: Any) -> None:\n",
+                "     pass\n",
+                " \n",
+                "-def g(y) -> None: pass\n",
+                "\\ No newline at end of file\n",
+                "+def g(y: Any) -> None: pass\n",
+                "\\ No newline at end of file\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use std::path::Path;
+
+use crate::{
+    json_report::{fingerprint, kind_and_name},
+    paths::display_path,
+    report::get_finding_message,
+    Position, Severity,
+};
+
+/// An issue of a GitLab Code Quality report, as written by `--format gitlab`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CodeQualityIssue {
+    pub description: String,
+    pub check_name: String,
+    /// Hash of the path, function, kind and name, with which GitLab tells new issues
+    /// from the ones already on the target branch.
+    pub fingerprint: String,
+    pub severity: String,
+    pub location: Location,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Location {
+    pub path: String,
+    pub lines: Lines,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Lines {
+    pub begin: usize,
+}
+
+impl CodeQualityIssue {
+    pub fn new(path: &str, position: &Position) -> Self {
+        let (kind, name) = kind_and_name(&position.missing_type);
+        let function = position
+            .function
+            .as_ref()
+            .map(|function| function.qualified_name.as_str());
+
+        CodeQualityIssue {
+            description: get_finding_message(position),
+            check_name: position.missing_type.code().to_string(),
+            fingerprint: fingerprint(path, function, kind, &name),
+            severity: match position.missing_type.severity() {
+                Severity::Warning => "minor",
+                Severity::Error => "major",
+            }
+            .to_string(),
+            location: Location {
+                path: path.to_string(),
+                lines: Lines {
+                    begin: position.start.row + 1,
+                },
+            },
+        }
+    }
+}
+
+/// Renders one compact issue per line, which the walk concatenates across files before
+/// [`render`] gathers them into the report.
+pub fn get_gitlab_records(path: &Path, positions: &[Position]) -> String {
+    let path = display_path(path, false);
+
+    positions
+        .iter()
+        .map(|position| record_line(&CodeQualityIssue::new(&path, position)))
+        .collect()
+}
+
+/// Renders the report, a JSON array of the issues sorted by path and line so that it does
+/// not depend on the order in which files were checked.
+pub fn render(records: &str) -> String {
+    let mut issues = parse_records(records);
+    issues.sort_by(|a, b| {
+        (&a.location.path, a.location.lines.begin, &a.check_name).cmp(&(
+            &b.location.path,
+            b.location.lines.begin,
+            &b.check_name,
+        ))
+    });
+
+    crate::json_report::render(&issues)
+}
+
+#[cfg(feature = "formats-json")]
+fn record_line(issue: &CodeQualityIssue) -> String {
+    serde_json::to_string(issue).expect("Issues should be serializable.") + "\n"
+}
+
+#[cfg(not(feature = "formats-json"))]
+fn record_line(_issue: &CodeQualityIssue) -> String {
+    unreachable!("--format gitlab should have been rejected at startup.");
+}
+
+#[cfg(feature = "formats-json")]
+fn parse_records(records: &str) -> Vec<CodeQualityIssue> {
+    records
+        .lines()
+        .map(|record| serde_json::from_str(record).expect("Records should be valid JSON."))
+        .collect()
+}
+
+#[cfg(not(feature = "formats-json"))]
+fn parse_records(_records: &str) -> Vec<CodeQualityIssue> {
+    unreachable!("--format gitlab should have been rejected at startup.");
+}
+
+#[cfg(all(test, feature = "formats-json"))]
+mod tests {
+    use super::*;
+    use crate::tests::{default_options, fixture_dir};
+    use crate::{check_files, report::OutputFormat, CheckOptions};
+
+    #[test]
+    fn gitlab_report_test() {
+        let dir = fixture_dir();
+        let file = dir.path().join("app.py");
+        std::fs::write(&file, "def f(x, y: int):\n    return 1\n").unwrap();
+
+        let options = CheckOptions {
+            format: OutputFormat::Gitlab,
+            ..default_options()
+        };
+        let (records, _) = check_files(vec![file.clone()], &options);
+        let issues: Vec<CodeQualityIssue> = serde_json::from_str(&render(&records)).unwrap();
+
+        let path = display_path(&file, false);
+        assert_eq!(
+            issues,
+            [
+                CodeQualityIssue {
+                    description: "Parameter 'x' is missing a type hint.".to_string(),
+                    check_name: "THC001".to_string(),
+                    fingerprint: fingerprint(&path, Some("f"), "missing_parameter", "x"),
+                    severity: "minor".to_string(),
+                    location: Location {
+                        path: path.clone(),
+                        lines: Lines { begin: 1 },
+                    },
+                },
+                CodeQualityIssue {
+                    description: "Function 'f' is missing a return type.".to_string(),
+                    check_name: "THC002".to_string(),
+                    fingerprint: fingerprint(&path, Some("f"), "missing_return", "f"),
+                    severity: "minor".to_string(),
+                    location: Location {
+                        path: path.clone(),
+                        lines: Lines { begin: 1 },
+                    },
+                },
+            ]
+        );
+
+        std::fs::write(&file, "\n\ndef f(x, y: int):\n    return 1\n").unwrap();
+        let (records, _) = check_files(vec![file], &options);
+        let moved: Vec<CodeQualityIssue> = serde_json::from_str(&render(&records)).unwrap();
+        assert_eq!(
+            moved
+                .iter()
+                .map(|issue| &issue.fingerprint)
+                .collect::<Vec<_>>(),
+            issues
+                .iter()
+                .map(|issue| &issue.fingerprint)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(render(""), "[]\n");
+    }
+}
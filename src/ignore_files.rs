@@ -0,0 +1,401 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use walkdir::DirEntry;
+
+use crate::{filters::Filter, paths::normalized};
+
+/// Name of the checker's own exclusion files, discovered in the scanned directories.
+pub const THCIGNORE: &str = ".thcignore";
+
+/// Name of git's exclusion files, discovered up to the root of the repository.
+pub const GITIGNORE: &str = ".gitignore";
+
+/// A pattern of an ignore file, along with the line it was read from.
+struct IgnorePattern {
+    pattern: String,
+    line: usize,
+    matcher: globset::GlobMatcher,
+    negated: bool,
+    directories_only: bool,
+}
+
+impl IgnorePattern {
+    /// Parses a line with gitignore syntax, returning `None` for blank lines and
+    /// comments. Patterns with a `/` other than a trailing one are relative to the
+    /// file's directory, the others match names at any depth below it.
+    fn parse(line: &str, number: usize) -> Option<Result<Self, String>> {
+        let trimmed = line.trim_end();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            return None;
+        }
+
+        let (negated, glob) = match trimmed.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed.strip_prefix('\\').unwrap_or(trimmed)),
+        };
+        let (directories_only, glob) = match glob.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, glob),
+        };
+        let glob = if glob.contains('/') {
+            glob.trim_start_matches('/').to_string()
+        } else {
+            format!("**/{glob}")
+        };
+
+        Some(
+            globset::GlobBuilder::new(&glob)
+                .literal_separator(true)
+                .build()
+                .map(|glob| IgnorePattern {
+                    pattern: trimmed.to_string(),
+                    line: number,
+                    matcher: glob.compile_matcher(),
+                    negated,
+                    directories_only,
+                })
+                .map_err(|e| e.kind().to_string()),
+        )
+    }
+}
+
+/// The patterns of one ignore file.
+struct IgnoreFile {
+    path: PathBuf,
+    patterns: Vec<IgnorePattern>,
+}
+
+impl IgnoreFile {
+    /// Reads the file in `directory`, if any. Unreadable files and invalid patterns are
+    /// warned about and skipped, as filters cannot fail the walk.
+    fn read(directory: &Path, file_name: &str) -> Option<Self> {
+        let path = directory.join(file_name);
+        if !path.is_file() {
+            return None;
+        }
+
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| eprintln!("warning: could not read {}: {e}", path.display()))
+            .ok()?;
+        let patterns = contents
+            .lines()
+            .enumerate()
+            .filter_map(|(index, line)| {
+                IgnorePattern::parse(line, index + 1)?
+                    .map_err(|e| {
+                        eprintln!(
+                            "warning: skipping '{}' at {}:{}: {e}.",
+                            line.trim_end(),
+                            path.display(),
+                            index + 1
+                        )
+                    })
+                    .ok()
+            })
+            .collect();
+
+        Some(IgnoreFile { path, patterns })
+    }
+
+    /// The last pattern matching the entry, which decides whether it is excluded.
+    /// Files above the scanned directory are read from absolute directories, against
+    /// which the entry is matched once made absolute too.
+    fn decisive_pattern(&self, entry: &DirEntry) -> Option<&IgnorePattern> {
+        let directory = self.path.parent()?;
+        let relative = match entry.path().strip_prefix(directory) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => normalized(entry.path())
+                .strip_prefix(directory)
+                .ok()?
+                .to_path_buf(),
+        };
+        let is_dir = entry.file_type().is_dir();
+
+        self.patterns.iter().rev().find(|pattern| {
+            (is_dir || !pattern.directories_only) && pattern.matcher.is_match(&relative)
+        })
+    }
+}
+
+/// What is known about a directory visited by the walk.
+#[derive(Clone)]
+struct Directory {
+    ignore_file: Option<Arc<IgnoreFile>>,
+    /// Whether the directory is the root of a git repository.
+    is_repository: bool,
+}
+
+/// Excludes entries matching ignore files with gitignore syntax: the `.thcignore` files
+/// of the scanned directory and of the directories below it, or the `.gitignore` files
+/// of the git repository the entries are in. The last matching pattern wins, `!`
+/// re-includes what earlier patterns excluded, and files in deeper directories take
+/// precedence over the ones above them.
+pub struct IgnoreFiles {
+    file_name: &'static str,
+    /// Whether the files of the directories above the scanned one apply as well, up to
+    /// the root of the repository, outside of which none apply.
+    up_to_repository: bool,
+    /// Directories visited so far, so that each ignore file is only read once.
+    directories: Mutex<HashMap<PathBuf, Directory>>,
+}
+
+impl IgnoreFiles {
+    pub fn thcignore() -> Self {
+        IgnoreFiles {
+            file_name: THCIGNORE,
+            up_to_repository: false,
+            directories: Mutex::default(),
+        }
+    }
+
+    pub fn gitignore() -> Self {
+        IgnoreFiles {
+            file_name: GITIGNORE,
+            up_to_repository: true,
+            directories: Mutex::default(),
+        }
+    }
+
+    /// The pattern deciding the entry's fate, along with the file it is from.
+    fn decisive_pattern<R>(
+        &self,
+        entry: &DirEntry,
+        decide: impl FnOnce(Option<(&IgnoreFile, &IgnorePattern)>) -> R,
+    ) -> R {
+        let ignore_files: Vec<Arc<IgnoreFile>> = {
+            let mut visited = self
+                .directories
+                .lock()
+                .expect("Should be able to get a lock on the read ignore files.");
+            let mut visit = |directory: &Path| {
+                visited
+                    .entry(directory.to_path_buf())
+                    .or_insert_with(|| Directory {
+                        ignore_file: IgnoreFile::read(directory, self.file_name).map(Arc::new),
+                        is_repository: self.up_to_repository && directory.join(".git").exists(),
+                    })
+                    .clone()
+            };
+
+            let mut directories: Vec<PathBuf> = entry
+                .path()
+                .ancestors()
+                .skip(1)
+                .take(entry.depth())
+                .map(Path::to_path_buf)
+                .collect();
+            if !self.up_to_repository {
+                directories
+                    .iter()
+                    .filter_map(|directory| visit(directory).ignore_file)
+                    .collect()
+            } else {
+                if let Some(root) = entry.path().ancestors().nth(entry.depth()) {
+                    directories.extend(normalized(root).ancestors().skip(1).map(Path::to_path_buf));
+                }
+
+                let mut ignore_files = Vec::new();
+                let mut in_repository = false;
+                for directory in &directories {
+                    let directory = visit(directory);
+                    ignore_files.extend(directory.ignore_file);
+                    if directory.is_repository {
+                        in_repository = true;
+                        break;
+                    }
+                }
+                if !in_repository {
+                    ignore_files.clear();
+                }
+                ignore_files
+            }
+        };
+
+        decide(ignore_files.iter().find_map(|file| {
+            file.decisive_pattern(entry)
+                .map(|pattern| (file.as_ref(), pattern))
+        }))
+    }
+}
+
+impl Filter for IgnoreFiles {
+    fn should_be_processed(&self, entry: &DirEntry) -> bool {
+        self.decisive_pattern(entry, |decisive| {
+            decisive.is_none_or(|(_, pattern)| pattern.negated)
+        })
+    }
+
+    fn reason(&self, entry: &DirEntry) -> String {
+        self.decisive_pattern(entry, |decisive| {
+            decisive
+                .map(|(file, pattern)| {
+                    format!(
+                        "matches '{}' from {}:{}",
+                        pattern.pattern,
+                        file.path.display(),
+                        pattern.line
+                    )
+                })
+                .unwrap_or_default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collect_files, tests::fixture_dir};
+
+    #[test]
+    fn ignore_files_test() {
+        let dir = fixture_dir();
+        for directory in ["build", "src/generated", "src/vendored", "docs/build"] {
+            std::fs::create_dir_all(dir.path().join(directory)).unwrap();
+        }
+        for file in [
+            "app.py",
+            "app_pb2.py",
+            "keep_pb2.py",
+            "build/module.py",
+            "docs/build/conf.py",
+            "src/generated/module.py",
+            "src/generated/handwritten.py",
+            "src/vendored/lib.py",
+        ] {
+            std::fs::write(dir.path().join(file), "def f(x): ...").unwrap();
+        }
+        std::fs::write(
+            dir.path().join(THCIGNORE),
+            "# Generated code\n*_pb2.py\n!keep_pb2.py\n\n/build/\nsrc/vendored\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("src/generated").join(THCIGNORE),
+            "*.py\n!handwritten.py\n",
+        )
+        .unwrap();
+
+        let filters: Vec<Box<dyn Filter + Sync>> = vec![Box::new(IgnoreFiles::thcignore())];
+        let mut excluded = Vec::new();
+        let mut files: Vec<String> = collect_files(
+            &[dir.path().to_path_buf()],
+            &filters,
+            &["py".to_string()],
+            |entry, filter| {
+                excluded.push((
+                    entry.path().strip_prefix(dir.path()).unwrap().to_path_buf(),
+                    filter.reason(entry),
+                ))
+            },
+        )
+        .0
+        .iter()
+        .map(|file| {
+            let relative = file.strip_prefix(dir.path()).unwrap();
+            relative.to_str().unwrap().to_string()
+        })
+        .collect();
+        files.sort();
+        excluded.sort();
+
+        assert_eq!(
+            files,
+            [
+                "app.py",
+                "docs/build/conf.py",
+                "keep_pb2.py",
+                "src/generated/handwritten.py"
+            ]
+        );
+        let root_file = dir.path().join(THCIGNORE);
+        assert_eq!(
+            excluded,
+            [
+                (
+                    PathBuf::from("app_pb2.py"),
+                    format!("matches '*_pb2.py' from {}:2", root_file.display())
+                ),
+                (
+                    PathBuf::from("build"),
+                    format!("matches '/build/' from {}:5", root_file.display())
+                ),
+                (
+                    PathBuf::from("src/generated/module.py"),
+                    format!(
+                        "matches '*.py' from {}:1",
+                        dir.path().join("src/generated").join(THCIGNORE).display()
+                    )
+                ),
+                (
+                    PathBuf::from("src/vendored"),
+                    format!("matches 'src/vendored' from {}:6", root_file.display())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn gitignore_test() {
+        let dir = fixture_dir();
+        for directory in ["repo/.git", "repo/src/pkg", "repo/build"] {
+            std::fs::create_dir_all(dir.path().join(directory)).unwrap();
+        }
+        for file in [
+            "repo/build/module.py",
+            "repo/src/app.py",
+            "repo/src/settings_local.py",
+            "repo/src/pkg/module.py",
+            "repo/src/pkg/schema_gen.py",
+        ] {
+            std::fs::write(dir.path().join(file), "def f(x): ...").unwrap();
+        }
+        std::fs::write(dir.path().join(GITIGNORE), "*.py\n").unwrap();
+        std::fs::write(
+            dir.path().join("repo").join(GITIGNORE),
+            "/build/\n*_local.py\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("repo/src/pkg").join(GITIGNORE),
+            "*_gen.py\n",
+        )
+        .unwrap();
+
+        let checked = |path: &Path| {
+            let filters: Vec<Box<dyn Filter + Sync>> = vec![Box::new(IgnoreFiles::gitignore())];
+            let mut files: Vec<String> = collect_files(
+                &[path.to_path_buf()],
+                &filters,
+                &["py".to_string()],
+                |_, _| {},
+            )
+            .0
+            .iter()
+            .map(|file| {
+                let relative = file.strip_prefix(dir.path()).unwrap();
+                relative.to_str().unwrap().to_string()
+            })
+            .collect();
+            files.sort();
+            files
+        };
+
+        // The files above the repository do not apply, the ones above the checked
+        // directory within it do.
+        assert_eq!(
+            checked(&dir.path().join("repo")),
+            ["repo/src/app.py", "repo/src/pkg/module.py"]
+        );
+        assert_eq!(
+            checked(&dir.path().join("repo/src")),
+            ["repo/src/app.py", "repo/src/pkg/module.py"]
+        );
+
+        std::fs::remove_dir(dir.path().join("repo/.git")).unwrap();
+        assert_eq!(checked(&dir.path().join("repo")).len(), 5);
+    }
+}
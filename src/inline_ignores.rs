@@ -0,0 +1,64 @@
+/// Comment suppressing the findings of a function when it ends its `def` line, or of the
+/// whole file when it makes up the file's first non-blank line.
+pub const MARKER: &str = "hints: ignore";
+
+/// Whether the findings of the whole file are suppressed.
+pub fn ignores_file(source_code: &[u8]) -> bool {
+    source_code
+        .split(|byte| *byte == b'\n')
+        .map(|line| line.trim_ascii())
+        .find(|line| !line.is_empty())
+        .is_some_and(|line| line.starts_with(b"#") && has_marker(line))
+}
+
+/// Whether the findings of the function are suppressed by a comment on its `def` line.
+/// Those of the functions nested in it are not.
+pub fn ignores_function(function: tree_sitter::Node, source_code: &[u8]) -> bool {
+    let start = function.start_byte();
+    let line_start = source_code[..start]
+        .iter()
+        .rposition(|byte| *byte == b'\n')
+        .map_or(0, |newline| newline + 1);
+    let line_end = source_code[start..]
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .map_or(source_code.len(), |newline| start + newline);
+
+    has_marker(&source_code[line_start..line_end])
+}
+
+/// Whether a comment on the line starts with the marker, such as `# hints: ignore` or
+/// `# noqa  # hints: ignore`.
+fn has_marker(line: &[u8]) -> bool {
+    line.split(|byte| *byte == b'#')
+        .skip(1)
+        .any(|comment| comment.trim_ascii().starts_with(MARKER.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_python_parser, find_missing_types_positions, get_tree_from_source};
+
+    #[test]
+    fn inline_ignores_test() {
+        let source = b"def legacy(x):  # hints: ignore\n    def nested(y):\n        pass\n\n\
+                       def other(z):  # noqa  # hints: ignore[dynamic]\n    pass\n\n\
+                       def checked(w):  # hints: not ignored\n    pass\n";
+        let mut parser = create_python_parser();
+        let (tree, source_code) = get_tree_from_source(&mut parser, source.to_vec());
+        let (positions, counts) =
+            find_missing_types_positions(&source_code, tree, &Default::default());
+
+        let functions: Vec<&str> = positions
+            .iter()
+            .filter_map(|position| Some(position.function.as_ref()?.name.as_str()))
+            .collect();
+        assert_eq!(functions, ["nested", "nested", "checked", "checked"]);
+        assert_eq!(counts.functions, 2);
+
+        assert!(ignores_file(b"\n  # hints: ignore\ndef f(x): ...\n"));
+        assert!(!ignores_file(b"import os\n# hints: ignore\n"));
+        assert!(!ignores_file(b"def f(x):  # hints: ignore\n    pass\n"));
+    }
+}
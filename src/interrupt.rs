@@ -0,0 +1,43 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Exit status of a run stopped by Ctrl-C, as shells report a SIGINT.
+pub const EXIT_STATUS: i32 = 130;
+
+/// Ctrl-C presses received so far.
+pub struct Interrupts(AtomicUsize);
+
+impl Interrupts {
+    pub const fn new() -> Self {
+        Interrupts(AtomicUsize::new(0))
+    }
+
+    /// Records a press, returning whether it is not the first one, which exits right away.
+    pub fn press(&self) -> bool {
+        self.0.fetch_add(1, Ordering::SeqCst) > 0
+    }
+
+    pub fn interrupted(&self) -> bool {
+        self.0.load(Ordering::SeqCst) > 0
+    }
+}
+
+/// The presses received by the process, which runs stop checking new files at.
+pub static INTERRUPTS: Interrupts = Interrupts::new();
+
+/// Makes the first Ctrl-C stop the checking of new files, so that the findings so far
+/// can still be rendered, and the second one exit right away.
+pub fn install() {
+    let installed = ctrlc::set_handler(|| {
+        if INTERRUPTS.press() {
+            std::process::exit(EXIT_STATUS);
+        }
+    });
+
+    if let Err(e) = installed {
+        eprintln!("warning: could not handle Ctrl-C, interrupting will lose the results: {e}");
+    }
+}
+
+pub fn interrupted() -> bool {
+    INTERRUPTS.interrupted()
+}
@@ -0,0 +1,180 @@
+use crate::{compat, stubs::qualified_name};
+
+/// Version of the `inventory --format json` document, bumped on incompatible changes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// What is known about a function definition, whether or not it is fully annotated.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct FunctionRecord {
+    pub qualified_name: String,
+    pub file: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Parameters with a type hint, not counting the implicit first parameter of methods.
+    pub annotated_parameters: usize,
+    pub unannotated_parameters: usize,
+    pub return_annotated: bool,
+    pub is_method: bool,
+    pub is_async: bool,
+    /// Whether the function is defined inside another function.
+    pub is_nested: bool,
+    /// Decorators without their `@`, such as `staticmethod` or `app.route("/")`.
+    pub decorators: Vec<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct Inventory {
+    pub schema_version: u32,
+    pub functions: Vec<FunctionRecord>,
+}
+
+/// Lists the functions of a file in definition order.
+pub fn functions(file: &str, source_code: &[u8], tree: &tree_sitter::Tree) -> Vec<FunctionRecord> {
+    let walk = tree.walk();
+
+    tree_sitter_traversal::traverse(walk, tree_sitter_traversal::Order::Pre)
+        .filter(|node| node.kind() == "function_definition")
+        .map(|node| function_record(file, node, source_code))
+        .collect()
+}
+
+fn function_record(file: &str, function: tree_sitter::Node, source_code: &[u8]) -> FunctionRecord {
+    let (mut annotated_parameters, mut unannotated_parameters) = (0, 0);
+    if let Some(parameters) = function.child_by_field_name("parameters") {
+        let skips_first = compat::binds_first_parameter(function, source_code);
+        let mut cursor = parameters.walk();
+        for (index, parameter) in parameters.named_children(&mut cursor).enumerate() {
+            if index == 0 && skips_first {
+                continue;
+            }
+
+            match parameter.kind() {
+                "typed_parameter" | "typed_default_parameter" => annotated_parameters += 1,
+                "identifier"
+                | "default_parameter"
+                | "list_splat_pattern"
+                | "dictionary_splat_pattern" => unannotated_parameters += 1,
+                _ => {}
+            }
+        }
+    }
+
+    let decorators = match function.parent() {
+        Some(parent) if parent.kind() == "decorated_definition" => {
+            let mut cursor = parent.walk();
+            parent
+                .children(&mut cursor)
+                .filter(|child| child.kind() == "decorator")
+                .filter_map(|decorator| decorator.utf8_text(source_code).ok())
+                .map(|text| text.trim_start_matches('@').trim().to_string())
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+
+    let mut ancestor = function.parent();
+    let mut is_nested = false;
+    while let Some(node) = ancestor {
+        if node.kind() == "function_definition" {
+            is_nested = true;
+            break;
+        }
+        ancestor = node.parent();
+    }
+
+    FunctionRecord {
+        qualified_name: qualified_name(function, source_code),
+        file: file.to_string(),
+        start_line: function.start_position().row + 1,
+        end_line: function.end_position().row + 1,
+        annotated_parameters,
+        unannotated_parameters,
+        return_annotated: function.child_by_field_name("return_type").is_some(),
+        is_method: compat::is_method(function),
+        is_async: function
+            .child(0)
+            .is_some_and(|child| child.kind() == "async"),
+        is_nested,
+        decorators,
+    }
+}
+
+/// Renders one line per function, e.g.
+/// `src/app.py:3-5: Greeter.greet: 1/2 parameters annotated, return annotated`.
+pub fn render_text(functions: &[FunctionRecord]) -> String {
+    functions
+        .iter()
+        .map(|function| {
+            format!(
+                "{}:{}-{}: {}: {}/{} parameters annotated, return {}\n",
+                function.file,
+                function.start_line,
+                function.end_line,
+                function.qualified_name,
+                function.annotated_parameters,
+                function.annotated_parameters + function.unannotated_parameters,
+                if function.return_annotated {
+                    "annotated"
+                } else {
+                    "not annotated"
+                }
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_python_parser, get_tree_from_source};
+
+    #[test]
+    fn functions_test() {
+        let source = "\
+class Greeter:
+    @staticmethod
+    def make(name):
+        pass
+
+    async def greet(self, name: str, *args, **kwargs) -> None:
+        def inner(x: int) -> int:
+            return x
+";
+        let mut parser = create_python_parser();
+        let (tree, source_code) = get_tree_from_source(&mut parser, source.as_bytes().to_vec());
+        let functions = functions("app.py", &source_code, &tree);
+
+        assert_eq!(
+            functions[0],
+            FunctionRecord {
+                qualified_name: "Greeter.make".to_string(),
+                file: "app.py".to_string(),
+                start_line: 3,
+                end_line: 4,
+                annotated_parameters: 0,
+                unannotated_parameters: 1,
+                return_annotated: false,
+                is_method: true,
+                is_async: false,
+                is_nested: false,
+                decorators: vec!["staticmethod".to_string()],
+            }
+        );
+        assert_eq!(functions[1].qualified_name, "Greeter.greet");
+        assert_eq!(
+            (
+                functions[1].annotated_parameters,
+                functions[1].unannotated_parameters
+            ),
+            (1, 2)
+        );
+        assert!(functions[1].is_async && functions[1].return_annotated);
+        assert_eq!(functions[2].qualified_name, "Greeter.greet.inner");
+        assert!(functions[2].is_nested && !functions[2].is_method);
+
+        assert_eq!(
+            render_text(&functions[1..2]),
+            "app.py:6-8: Greeter.greet: 1/3 parameters annotated, return annotated\n"
+        );
+    }
+}
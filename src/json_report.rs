@@ -0,0 +1,318 @@
+use std::path::Path;
+
+use crate::{
+    config::stable_hash, links::Links, paths::display_path, FileStats, MissingType, Position,
+    Summary,
+};
+
+/// Version of the `--format json` document, bumped on incompatible changes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A finding as written by `--format json`. Lines and columns start at 1.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FindingRecord {
+    pub path: String,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    pub code: String,
+    pub kind: String,
+    /// The parameter, function, variable or annotation the finding is about.
+    pub name: String,
+    /// Qualified name of the function the finding is about, if any.
+    pub function: Option<String>,
+    pub message: String,
+    /// Annotation that would fix the finding, such as `Self` with --suggest-self or the
+    /// replacement of a deprecated alias.
+    #[serde(default)]
+    pub suggestion: Option<String>,
+    /// Link to the finding's location, with --link-template.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Hash of the path, function, kind and name, which stays the same when unrelated
+    /// lines are added or removed.
+    pub fingerprint: String,
+}
+
+impl FindingRecord {
+    pub fn new(path: &str, position: &Position) -> Self {
+        let (kind, name) = kind_and_name(&position.missing_type);
+        let function = position
+            .function
+            .as_ref()
+            .map(|function| function.qualified_name.clone());
+
+        FindingRecord {
+            path: path.to_string(),
+            line: position.start.row + 1,
+            column: position.start.column + 1,
+            end_line: position.end.row + 1,
+            end_column: position.end.column + 1,
+            code: position.missing_type.code().to_string(),
+            kind: kind.to_string(),
+            fingerprint: fingerprint(path, function.as_deref(), kind, &name),
+            name,
+            function,
+            message: crate::report::get_short_message(&position.missing_type),
+            suggestion: position.suggestion.clone(),
+            url: None,
+        }
+    }
+}
+
+pub fn fingerprint(path: &str, function: Option<&str>, kind: &str, name: &str) -> String {
+    let key = [path, function.unwrap_or_default(), kind, name].join("\0");
+
+    format!("{:016x}", stable_hash(key.as_bytes()))
+}
+
+pub fn kind_and_name(missing_type: &MissingType) -> (&'static str, String) {
+    match missing_type {
+        MissingType::Parameter(name) => ("missing_parameter", name.clone()),
+        MissingType::Return(name) => ("missing_return", name.clone()),
+        MissingType::EmptyCollection(name) => ("empty_collection", name.clone()),
+        MissingType::BareGeneric(name) => ("bare_generic", name.clone()),
+        MissingType::DeprecatedTyping { alias, .. } => ("deprecated_typing", alias.clone()),
+        MissingType::QuotedAnnotation(contents) => ("quoted_annotation", contents.clone()),
+        MissingType::AnnotationStyle { found, .. } => ("annotation_style", found.clone()),
+        MissingType::Lambda(name) => ("lambda", name.clone()),
+        MissingType::PartiallyAnnotated(name) => ("partially_annotated", name.clone()),
+        MissingType::ExplicitAny { name, .. } => ("explicit_any", name.clone()),
+        MissingType::NewerSyntax { syntax, .. } => ("newer_syntax", syntax.to_string()),
+        MissingType::SyntaxError { text, .. } => ("syntax_error", text.clone()),
+    }
+}
+
+/// Annotation counts of a checked file, from which coverage can be recomputed.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FileRecord {
+    pub path: String,
+    pub functions: usize,
+    pub slots: usize,
+    pub missing_parameters: usize,
+    pub missing_returns: usize,
+    pub findings: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Totals {
+    pub files_checked: usize,
+    pub findings: usize,
+    pub coverage: Option<f64>,
+    #[serde(default)]
+    pub functions: usize,
+    #[serde(default)]
+    pub missing_parameters: usize,
+    #[serde(default)]
+    pub missing_returns: usize,
+    #[serde(default)]
+    pub files_with_findings: usize,
+}
+
+impl Totals {
+    pub fn new(files: &[FileStats], findings: usize) -> Self {
+        let sum = |count: fn(&FileStats) -> usize| files.iter().map(count).sum();
+
+        Totals {
+            files_checked: files.len(),
+            findings,
+            coverage: crate::coverage(files.iter()),
+            functions: sum(|file| file.counts.functions),
+            missing_parameters: sum(|file| file.missing_parameters),
+            missing_returns: sum(|file| file.missing_returns),
+            files_with_findings: sum(|file| usize::from(file.findings > 0)),
+        }
+    }
+}
+
+/// The document written by `--format json`, with findings and files sorted by path so
+/// that it does not depend on the order in which files were checked.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct JsonReport {
+    pub schema_version: u32,
+    pub version: String,
+    pub options_fingerprint: String,
+    pub findings: Vec<FindingRecord>,
+    pub files: Vec<FileRecord>,
+    pub summary: Totals,
+    /// Whether the run timed out or was interrupted before every file was checked.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+}
+
+impl JsonReport {
+    pub fn new(
+        mut findings: Vec<FindingRecord>,
+        summary: &Summary,
+        options_fingerprint: String,
+    ) -> Self {
+        findings.sort_by(|a, b| {
+            (&a.path, a.line, a.column, &a.code).cmp(&(&b.path, b.line, b.column, &b.code))
+        });
+
+        let mut files: Vec<FileRecord> = summary
+            .files
+            .iter()
+            .map(|file| FileRecord {
+                path: display_path(&file.path, false),
+                functions: file.counts.functions,
+                slots: file.counts.slots,
+                missing_parameters: file.missing_parameters,
+                missing_returns: file.missing_returns,
+                findings: file.findings,
+            })
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        JsonReport {
+            schema_version: SCHEMA_VERSION,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            options_fingerprint,
+            findings,
+            files,
+            summary: Totals::new(&summary.files, summary.findings),
+            partial: summary.unchecked > 0,
+        }
+    }
+}
+
+/// Renders one compact record per line, which the walk concatenates across files before
+/// they are gathered into a [`JsonReport`].
+pub fn get_json_records(path: &Path, positions: &[Position], links: Option<&Links>) -> String {
+    let display_path = display_path(path, false);
+
+    positions
+        .iter()
+        .map(|position| {
+            let record = FindingRecord {
+                url: links.map(|links| links.url(path, position.start.row + 1)),
+                ..FindingRecord::new(&display_path, position)
+            };
+            record_line(&record)
+        })
+        .collect()
+}
+
+#[cfg(feature = "formats-json")]
+fn record_line(record: &FindingRecord) -> String {
+    serde_json::to_string(record).expect("Findings should be serializable.") + "\n"
+}
+
+#[cfg(not(feature = "formats-json"))]
+fn record_line(_record: &FindingRecord) -> String {
+    unreachable!("--format json should have been rejected at startup.");
+}
+
+#[cfg(feature = "formats-json")]
+pub fn parse_records(records: &str) -> Vec<FindingRecord> {
+    records
+        .lines()
+        .map(|record| serde_json::from_str(record).expect("Records should be valid JSON."))
+        .collect()
+}
+
+#[cfg(not(feature = "formats-json"))]
+pub fn parse_records(_records: &str) -> Vec<FindingRecord> {
+    unreachable!("--format json should have been rejected at startup.");
+}
+
+/// Pretty-prints a JSON document, such as a report or an inventory.
+#[cfg(feature = "formats-json")]
+pub fn render<T: serde::Serialize>(document: &T) -> String {
+    let mut rendered =
+        serde_json::to_string_pretty(document).expect("Reports should be serializable.");
+    rendered.push('\n');
+
+    rendered
+}
+
+#[cfg(not(feature = "formats-json"))]
+pub fn render<T: serde::Serialize>(_document: &T) -> String {
+    unreachable!("JSON output should have been rejected at startup.");
+}
+
+#[cfg(all(test, feature = "formats-json"))]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::tests::{default_options, fixture_dir};
+    use crate::{check_files, report::OutputFormat, CheckOptions};
+
+    #[test]
+    fn json_report_test() {
+        let dir = fixture_dir();
+        let file = dir.path().join("app.py");
+        std::fs::write(
+            &file,
+            "class A:\n    def f(self, x) -> int:\n        return 1\n",
+        )
+        .unwrap();
+
+        let options = CheckOptions {
+            format: OutputFormat::Json,
+            ..default_options()
+        };
+        let (records, summary) = check_files(vec![file.clone()], &options);
+        let report = JsonReport::new(parse_records(&records), &summary, "fp".to_string());
+
+        let path = crate::paths::display_path(&file, false);
+        assert_eq!(
+            report.findings,
+            [FindingRecord {
+                path: path.clone(),
+                line: 2,
+                column: 17,
+                end_line: 2,
+                end_column: 18,
+                code: "THC001".to_string(),
+                kind: "missing_parameter".to_string(),
+                name: "x".to_string(),
+                function: Some("A.f".to_string()),
+                message: "Parameter 'x' is missing a type hint.".to_string(),
+                suggestion: None,
+                url: None,
+                fingerprint: fingerprint(&path, Some("A.f"), "missing_parameter", "x"),
+            }]
+        );
+        assert_eq!(report.summary.coverage, Some(0.5));
+
+        std::fs::write(&file, "def g(x: Optional[int]) -> List[int]: ...\n").unwrap();
+        let rules_options = CheckOptions {
+            rules: crate::Rules {
+                flag_deprecated_typing: true,
+                annotation_style: Some(crate::rules::AnnotationStyle::Union),
+                python_version: crate::python_version::PythonVersion::new(3, 10),
+                ..Default::default()
+            },
+            ..options.clone()
+        };
+        let (records, _) = check_files(vec![file.clone()], &rules_options);
+        let suggestions: Vec<(String, Option<String>)> = parse_records(&records)
+            .into_iter()
+            .map(|finding| (finding.kind, finding.suggestion))
+            .collect();
+        assert_eq!(
+            suggestions,
+            [
+                ("annotation_style", Some("int | None")),
+                ("deprecated_typing", Some("int | None")),
+                ("deprecated_typing", Some("list[int]")),
+            ]
+            .map(|(kind, suggestion)| (kind.to_string(), suggestion.map(str::to_string)))
+        );
+
+        let rendered = render(&report);
+        let parsed: JsonReport = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(parsed, report);
+
+        let (records, summary) = check_files(Vec::<PathBuf>::new(), &options);
+        let empty = render(&JsonReport::new(
+            parse_records(&records),
+            &summary,
+            "fp".to_string(),
+        ));
+        assert!(empty.contains("\"findings\": []"));
+    }
+}
@@ -0,0 +1,191 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+};
+
+/// Placeholders a `--link-template` can use.
+const PLACEHOLDERS: &[&str] = &["path", "line", "rev"];
+
+/// A URL with `{path}`, `{line}` and `{rev}` placeholders, such as
+/// `https://github.com/org/repo/blob/{rev}/{path}#L{line}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkTemplate(String);
+
+impl LinkTemplate {
+    fn uses(&self, placeholder: &str) -> bool {
+        self.0.contains(&format!("{{{placeholder}}}"))
+    }
+}
+
+impl FromStr for LinkTemplate {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut rest = template;
+        while let Some(start) = rest.find(['{', '}']) {
+            if rest[start..].starts_with('}') {
+                return Err(format!("'{template}' has a '}}' without a matching '{{'"));
+            }
+
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("'{template}' has a '{{' without a matching '}}'"))?;
+            let placeholder = &rest[start + 1..start + end];
+            if !PLACEHOLDERS.contains(&placeholder) {
+                return Err(format!(
+                    "'{{{placeholder}}}' is not a placeholder, use {{path}}, {{line}} or {{rev}}"
+                ));
+            }
+            rest = &rest[start + end + 1..];
+        }
+
+        let template = LinkTemplate(template.to_string());
+        if !template.uses("path") {
+            return Err(format!("'{}' does not use {{path}}", template.0));
+        }
+
+        Ok(template)
+    }
+}
+
+impl fmt::Display for LinkTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl serde::Serialize for LinkTemplate {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Turns locations into links, with paths relative to the repository they are in.
+#[derive(Debug, Clone)]
+pub struct Links {
+    template: LinkTemplate,
+    rev: String,
+    /// Top of the git work tree, if the checked path is in one.
+    top_level: Option<PathBuf>,
+}
+
+impl Links {
+    /// Detects the repository of `path` and, unless given, its `HEAD` revision.
+    pub fn new(template: LinkTemplate, rev: Option<String>, path: &Path) -> Result<Self, String> {
+        let directory = if path.is_dir() {
+            path
+        } else {
+            path.parent().unwrap_or(Path::new("."))
+        };
+        let git = |arguments: &[&str]| -> Option<String> {
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(if directory.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    directory
+                })
+                .args(arguments)
+                .output()
+                .ok()?;
+            output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        };
+
+        let rev = match rev {
+            Some(rev) => rev,
+            None if template.uses("rev") => git(&["rev-parse", "HEAD"]).ok_or_else(|| {
+                format!(
+                    "could not read the git revision of '{}' for {{rev}}, pass --link-rev",
+                    path.display()
+                )
+            })?,
+            None => String::new(),
+        };
+
+        Ok(Links {
+            template,
+            rev,
+            top_level: git(&["rev-parse", "--show-toplevel"]).map(PathBuf::from),
+        })
+    }
+
+    pub fn url(&self, path: &Path, line: usize) -> String {
+        let relative = self
+            .top_level
+            .as_ref()
+            .and_then(|top_level| {
+                let absolute = std::path::absolute(path).ok()?;
+                absolute.strip_prefix(top_level).ok().map(Path::to_path_buf)
+            })
+            .unwrap_or_else(|| path.to_path_buf());
+
+        self.template
+            .0
+            .replace("{path}", &encode_path(&relative))
+            .replace("{line}", &line.to_string())
+            .replace("{rev}", &self.rev)
+    }
+}
+
+/// Percent-encodes each segment of the path, keeping `/` between them.
+fn encode_path(path: &Path) -> String {
+    let path = crate::paths::display_path(path, false);
+
+    path.trim_start_matches("./")
+        .split('/')
+        .map(|segment| {
+            segment
+                .bytes()
+                .map(|byte| match byte {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                        char::from(byte).to_string()
+                    }
+                    _ => format!("%{byte:02X}"),
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_test() {
+        assert!("https://example.com/{rev}/{path}#L{line}"
+            .parse::<LinkTemplate>()
+            .is_ok());
+        assert_eq!(
+            "https://example.com/{file}".parse::<LinkTemplate>(),
+            Err("'{file}' is not a placeholder, use {path}, {line} or {rev}".to_string())
+        );
+        assert!("https://example.com/{path".parse::<LinkTemplate>().is_err());
+        assert!("https://example.com/path}".parse::<LinkTemplate>().is_err());
+        assert!("https://example.com/#L{line}"
+            .parse::<LinkTemplate>()
+            .is_err());
+    }
+
+    #[test]
+    fn url_test() {
+        let links = Links {
+            template: "https://example.com/blob/{rev}/{path}#L{line}"
+                .parse()
+                .unwrap(),
+            rev: "abc123".to_string(),
+            top_level: None,
+        };
+
+        assert_eq!(
+            links.url(Path::new("./src/my app/ü.py"), 42),
+            "https://example.com/blob/abc123/src/my%20app/%C3%BC.py#L42"
+        );
+    }
+}
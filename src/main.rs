@@ -1,13 +1,61 @@
 use std::{
+    collections::HashSet,
+    io::{Read, Write},
     path::{Path, PathBuf},
-    sync::{Arc, Mutex},
+    sync::mpsc,
 };
 
-use clap::Parser;
-use rayon::prelude::{ParallelBridge, ParallelIterator};
-use walkdir::DirEntry;
+use clap::{CommandFactory, FromArgMatches, ValueEnum};
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+
+use compat::Compat;
+use filters::{
+    rejecting_filter, DefaultExcludes, Filter, GlobExclude, GlobInclude, GlobPattern, IgnoreDirs,
+    NotHidden, NotTest, PackagesOnly,
+};
+use ignore_files::IgnoreFiles;
+use paths::{display_path, openable_path};
+use progress::{Progress, ProgressMode};
+use python_version::PythonVersion;
+use report::{render_file, resolve_format, OutputFormat};
+use rules::{AnnotationStyle, CollectionScope, Strictness};
+use tool_excludes::{find_tool_excludes, Tool};
+
+mod annotate;
+mod compare;
+mod compat;
+mod config;
+mod config_file;
+mod deadline;
+mod docs;
+mod doctests;
+mod filters;
+mod fix;
+mod gitlab_report;
+mod ignore_files;
+mod inline_ignores;
+mod interrupt;
+mod inventory;
+mod json_report;
+mod links;
+mod merge;
+mod metrics;
+mod modules;
+mod overrides;
+mod paths;
+mod progress;
+mod python_version;
+mod ratchet;
+mod report;
+mod rules;
+mod run_summary;
+mod sample;
+mod shard;
+mod snippets;
+mod stubs;
+mod tool_excludes;
+mod typed_packages;
 
-const PARAMETERS_KIND: u16 = 147;
 const _TYPED_PARAMETER: u16 = 206;
 const _TYPED_DEFAULT_PARAMETER: u16 = 183;
 const IDENTIFIER: u16 = 1;
@@ -15,33 +63,592 @@ const DEFAULT_PARAMETER: u16 = 182;
 
 /// Checks missing type hints in function definitions for Python files.
 #[derive(clap::Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    args: Args,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Lists the Python files that would be checked.
+    ListFiles {
+        #[command(flatten)]
+        args: Box<Args>,
+
+        /// Also lists excluded files and directories along with the reason.
+        #[arg(long, default_value_t = false)]
+        why: bool,
+    },
+    /// Lists every function along with its annotations, whether or not it is reported,
+    /// as text or as a JSON document with a stable schema (--format json).
+    Inventory {
+        #[command(flatten)]
+        args: Box<Args>,
+    },
+    /// Compares two --format json reports, such as one from the target branch and one
+    /// from a pull request, and fails when the second has findings the first does not.
+    Compare {
+        /// Report to compare against, e.g. from the target branch.
+        old: PathBuf,
+
+        /// Report of the run being gated.
+        new: PathBuf,
+
+        /// Only lists new findings, leaving out the fixed ones.
+        #[arg(long, default_value_t = false)]
+        new_only: bool,
+
+        /// File renamed between the two runs, as in the reports. Can be repeated.
+        #[arg(long = "renames", value_name = "FROM=TO")]
+        renames: Vec<compare::Rename>,
+
+        /// Reads renames from `git diff --name-status` output in this file. `-` reads it
+        /// from stdin.
+        #[arg(long, value_name = "PATH")]
+        renames_from: Option<PathBuf>,
+
+        /// Output format for the listed findings.
+        #[arg(long, value_enum, default_value_t = CompareFormat::Text)]
+        format: CompareFormat,
+    },
+    /// Combines --format json reports of runs over parts of a tree, such as CI shards,
+    /// into the report of a single run.
+    Merge {
+        /// Reports to merge, which must have been written with the same options.
+        #[arg(required = true)]
+        reports: Vec<PathBuf>,
+
+        /// Writes the merged report to this file instead of stdout.
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+
+        /// Output format for the merged report.
+        #[arg(long, value_enum, default_value_t = MergeFormat::Json)]
+        format: MergeFormat,
+    },
+    /// Describes which functions and parameters are checked, and how a compatibility
+    /// mode differs from the checker it follows.
+    Explain {
+        #[arg(long, value_enum)]
+        compat: Option<Compat>,
+    },
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum CompareFormat {
+    /// `path:line:column: code message` lines.
+    Text,
+    /// The findings as in the compared reports.
+    Json,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum MergeFormat {
+    /// A --format json report.
+    Json,
+    /// Findings grouped by file, as checking the whole tree at once prints them.
+    Text,
+}
+
+#[derive(clap::Args, Debug, serde::Serialize)]
 struct Args {
-    /// File or directory to check
-    path: String,
+    /// Files and directories to check. A file under one of the directories, or given
+    /// twice, is only checked once.
+    #[arg(
+        value_name = "PATH",
+        required_unless_present_any = ["print_config", "files_from", "stdin_filenames", "annotate_source"]
+    )]
+    #[serde(skip)]
+    paths: Vec<PathBuf>,
+
+    /// Also checks the files and directories listed in this file, one per line. `-`
+    /// reads the list from stdin.
+    #[arg(long, value_name = "PATH", env = "THC_FILES_FROM")]
+    #[serde(skip)]
+    files_from: Option<PathBuf>,
+
+    /// Also checks the files and directories read from stdin, one per line, as
+    /// `--files-from -` does.
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with = "files_from",
+        env = "THC_STDIN_FILENAMES"
+    )]
+    #[serde(skip)]
+    stdin_filenames: bool,
 
     /// Ignores hidden subdirectories and files.
-    #[arg(alias = "ih", long, default_value_t = false)]
+    #[arg(alias = "ih", long, default_value_t = false, env = "THC_IGNORE_HIDDEN")]
     ignore_hidden: bool,
 
-    /// Ignores tests subdirectories and files.
-    #[arg(alias = "it", long, default_value_t = false)]
+    /// Ignores tests subdirectories and files (tests/, test/, testing/, test_*.py, *_test.py and conftest.py).
+    #[arg(alias = "it", long, default_value_t = false, env = "THC_IGNORE_TESTS")]
     ignore_tests: bool,
 
+    /// Ignores directories with this exact name. Can be repeated.
+    #[arg(
+        long,
+        value_name = "NAME",
+        value_delimiter = ',',
+        env = "THC_IGNORE_DIRS"
+    )]
+    ignore_dirs: Vec<String>,
+
+    /// Excludes files and directories matching this glob, without walking the excluded
+    /// directories. Patterns with a `/`, such as `src/**/generated`, are relative to the
+    /// checked directory, and the others match names. Can be repeated.
+    #[arg(long, value_name = "GLOB", value_delimiter = ',', env = "THC_EXCLUDE")]
+    exclude: Vec<String>,
+
+    /// Only checks the files matching this glob, or any of them if repeated, in the same
+    /// way as --exclude, which takes precedence.
+    #[arg(long, value_name = "GLOB", value_delimiter = ',', env = "THC_INCLUDE")]
+    include: Vec<String>,
+
+    /// Fails the run when a file does not parse. Such files are reported with a syntax
+    /// error finding either way, and their functions outside of the broken regions are
+    /// checked.
+    #[arg(long, default_value_t = false, env = "THC_FAIL_ON_PARSE_ERROR")]
+    fail_on_parse_error: bool,
+
+    /// Does not read the `.thcignore` files of the checked directories.
+    #[arg(long, default_value_t = false, env = "THC_NO_THCIGNORE")]
+    no_thcignore: bool,
+
+    /// Does not read the `.gitignore` files of the git repository the checked directories
+    /// are in, which otherwise exclude what git ignores.
+    #[arg(long, default_value_t = false, env = "THC_NO_GITIGNORE")]
+    no_gitignore: bool,
+
+    /// Walks the directories excluded by default: `.venv`, `venv`, `__pycache__`, `.tox`
+    /// and `site-packages`.
+    #[arg(long, default_value_t = false, env = "THC_NO_DEFAULT_EXCLUDES")]
+    no_default_excludes: bool,
+
+    /// Only checks files inside packages, i.e. directories with an `__init__.py`.
+    #[arg(long, default_value_t = false, env = "THC_PACKAGES_ONLY")]
+    packages_only: bool,
+
+    /// Also counts directories nested in a package as packages under --packages-only,
+    /// even without an `__init__.py` (PEP 420 namespace packages).
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "packages_only",
+        env = "THC_NAMESPACE_PACKAGES"
+    )]
+    namespace_packages: bool,
+
+    /// Also excludes what the given tool's configuration excludes. Can be repeated.
+    #[arg(
+        long,
+        value_name = "TOOL",
+        value_delimiter = ',',
+        env = "THC_RESPECT_TOOL_EXCLUDES"
+    )]
+    respect_tool_excludes: Vec<Tool>,
+
+    /// File extension to check when walking directories, matched case-insensitively.
+    /// Can be repeated.
+    #[arg(
+        long = "extension",
+        value_name = "EXT",
+        default_values_t = ["py".to_string()],
+        value_delimiter = ',',
+        env = "THC_EXTENSIONS"
+    )]
+    extensions: Vec<String>,
+
     /// Ignores absence of return type hints.
-    #[arg(alias = "ir", long, default_value_t = false)]
+    #[arg(alias = "ir", long, default_value_t = false, env = "THC_IGNORE_RETURN")]
     ignore_return: bool,
+
+    /// Functions with this exact name do not need a return type, such as entry points.
+    /// Has no effect with --compat mypy, which exempts no function by name. Can be
+    /// repeated.
+    #[arg(
+        long,
+        value_name = "NAME",
+        value_delimiter = ',',
+        default_value = "main",
+        env = "THC_IGNORE_NAMES"
+    )]
+    ignore_names: Vec<String>,
+
+    /// Also exempts functions with this exact name from return types, on top of
+    /// --ignore-names and the dunders exempt by default, such as `setUp`. Can be
+    /// repeated.
+    #[arg(
+        long,
+        value_name = "NAME",
+        value_delimiter = ',',
+        env = "THC_IGNORE_RETURN_FOR"
+    )]
+    ignore_return_for: Vec<String>,
+
+    /// Requires a return type on `__init__` and the other dunders that usually return
+    /// `None`, such as `__exit__` or `__setattr__`, which are exempt by default.
+    #[arg(long, default_value_t = false, env = "THC_REQUIRE_INIT_RETURN")]
+    require_init_return: bool,
+
+    /// Checks functions the way the given checker does (see `pythcheck explain`).
+    #[arg(long, value_enum, env = "THC_COMPAT")]
+    compat: Option<Compat>,
+
+    /// Reports unannotated assignments of empty collections, whose element types are
+    /// inferred as Any. SCOPE is top-level (module and class bodies) or all.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "SCOPE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "top-level",
+        env = "THC_FLAG_EMPTY_COLLECTIONS"
+    )]
+    flag_empty_collections: Option<CollectionScope>,
+
+    /// Reports generics such as `list`, `dict` or `Callable` annotated without type
+    /// arguments.
+    #[arg(long, default_value_t = false, env = "THC_FLAG_BARE_GENERICS")]
+    flag_bare_generics: bool,
+
+    /// Generic that --flag-bare-generics accepts without type arguments, such as `tuple`.
+    /// Can be repeated.
+    #[arg(
+        long,
+        value_name = "NAME",
+        value_delimiter = ',',
+        env = "THC_ALLOW_BARE_GENERICS"
+    )]
+    allow_bare_generics: Vec<String>,
+
+    /// Reports `typing` aliases such as `List` or `Optional` that the target Python
+    /// version can spell as `list` or `X | None`.
+    #[arg(long, default_value_t = false, env = "THC_FLAG_DEPRECATED_TYPING")]
+    flag_deprecated_typing: bool,
+
+    /// Reports string annotations in modules that import `annotations` from `__future__`
+    /// (or in any module from Python 3.14), where the quotes are unnecessary.
+    #[arg(long, default_value_t = false, env = "THC_FLAG_QUOTED_ANNOTATIONS")]
+    flag_quoted_annotations: bool,
+
+    /// Reports optional annotations not spelled in the given style: `X | None` (union)
+    /// or `Optional[X]` (optional).
+    #[arg(long, value_enum, env = "THC_ANNOTATION_STYLE")]
+    annotation_style: Option<AnnotationStyle>,
+
+    /// Suggests `-> Self` (or the quoted class name before Python 3.11) for methods
+    /// missing a return type whose every value-returning statement is `return self`.
+    #[arg(long, default_value_t = false, env = "THC_SUGGEST_SELF")]
+    suggest_self: bool,
+
+    /// Checks the `self` and `cls` parameters of methods like any other parameter, for
+    /// codebases annotating them with `Self` or `type[Self]`.
+    #[arg(long, default_value_t = false, env = "THC_STRICT_SELF")]
+    strict_self: bool,
+
+    /// Reports lambdas bound to a name, such as `handler = lambda request: ...`, as they
+    /// cannot be annotated. Defining them with `def` allows type hints.
+    #[arg(long, default_value_t = false, env = "THC_CHECK_LAMBDAS")]
+    check_lambdas: bool,
+
+    /// Also reports functions annotating some of their parameters but not the others, as
+    /// one finding each (consistent), and on top of that, parameters and return types
+    /// annotated as `Any` (strict).
+    #[arg(long, value_enum, default_value_t = Strictness::Basic, env = "THC_STRICTNESS")]
+    strictness: Strictness,
+
+    /// Leaves out findings about functions that are fully annotated in the stub next to
+    /// their module, such as `foo.pyi` for `foo.py`, matched by qualified name.
+    #[arg(long, default_value_t = false, env = "THC_PREFER_STUBS")]
+    prefer_stubs: bool,
+
+    /// Also checks the functions defined in the `>>>` examples of docstrings, reporting
+    /// their findings at the lines of the examples.
+    #[arg(long, default_value_t = false, env = "THC_CHECK_DOCTESTS")]
+    check_doctests: bool,
+
+    /// Reports the findings of functions whose `def` line ends with a `# hints: ignore`
+    /// comment, and of files starting with one, for audits.
+    #[arg(long, default_value_t = false, env = "THC_NO_INLINE_IGNORES")]
+    no_inline_ignores: bool,
+
+    /// Also checks the Python code blocks of the Markdown and reStructuredText files
+    /// matching this glob, such as `docs/**/*.md`. Can be repeated.
+    #[arg(long, value_name = "GLOB", value_delimiter = ',', env = "THC_DOCS")]
+    docs: Vec<String>,
+
+    /// Checks files in packages marked with a PEP 561 `py.typed` file with every rule
+    /// enabled and return types required, and the other files as configured.
+    #[arg(long, default_value_t = false, env = "THC_STRICT_TYPED_PACKAGES")]
+    strict_typed_packages: bool,
+
+    /// Python version the checked code targets [default: the oldest version allowed by
+    /// requires-python in pyproject.toml, or 3.10]
+    #[arg(long, value_name = "X.Y", env = "THC_PYTHON_VERSION")]
+    python_version: Option<PythonVersion>,
+
+    /// Warns about files with lines longer than this (likely minified code).
+    #[arg(long, default_value_t = 1000, env = "THC_MAX_LINE_LENGTH")]
+    max_line_length: usize,
+
+    /// Skips files with lines longer than --max-line-length instead of only warning.
+    #[arg(long, default_value_t = false, env = "THC_SKIP_MINIFIED")]
+    skip_minified: bool,
+
+    /// Checks files that are not valid UTF-8, such as Latin-1 sources, by replacing the
+    /// invalid bytes, instead of skipping them as files that could not be checked.
+    #[arg(long, default_value_t = false, env = "THC_LOSSY_UTF8")]
+    lossy_utf8: bool,
+
+    /// Output format for findings [default: text, or the matching format when running
+    /// in a recognized CI environment]
+    #[arg(long, value_enum, env = "THC_FORMAT")]
+    format: Option<OutputFormat>,
+
+    /// Renders one line per function listing all of its missing hints.
+    #[arg(long, default_value_t = false, env = "THC_AGGREGATE")]
+    aggregate: bool,
+
+    /// Indents the findings listed under each file by N spaces.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 4,
+        conflicts_with_all = ["aggregate", "no_group_by_file"],
+        env = "THC_INDENT"
+    )]
+    indent: usize,
+
+    /// Prints the files in alphabetical order once they are all checked, rather than
+    /// each as soon as it is done. Findings within a file are in order either way.
+    #[arg(long, default_value_t = false, env = "THC_SORTED")]
+    sorted: bool,
+
+    /// Prints how many findings each file has, most first, instead of the findings.
+    #[arg(long, default_value_t = false, env = "THC_STATISTICS")]
+    statistics: bool,
+
+    /// Adds placeholder annotations before checking: `: Any` to unannotated parameters,
+    /// importing it if needed, and `-> None` to functions that return no value. Files
+    /// that do not parse are left alone.
+    #[arg(long, default_value_t = false)]
+    #[serde(skip)]
+    fix: bool,
+
+    /// Prints the changes --fix would make as a unified diff, without writing them or
+    /// checking the files.
+    #[arg(long, default_value_t = false, conflicts_with = "fix")]
+    #[serde(skip)]
+    fix_dry_run: bool,
+
+    /// Renders one `path:line:column: message` line per finding instead of listing the
+    /// findings under a header for each file.
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["aggregate", "module_names"],
+        env = "THC_NO_GROUP_BY_FILE"
+    )]
+    no_group_by_file: bool,
+
+    /// Separates --files-from paths, --aggregate lines and listed files with NUL instead
+    /// of newlines, as `find -print0` and `git diff -z` do.
+    #[arg(short = '0', long, default_value_t = false, env = "THC_NULL")]
+    null: bool,
+
+    /// Shows the dotted module name of each file next to its path, and fully dotted
+    /// function names with --aggregate. Names derived from the path of files in namespace
+    /// packages or outside of any package are marked with `~`.
+    #[arg(long, default_value_t = false, env = "THC_MODULE_NAMES")]
+    module_names: bool,
+
+    /// Directory that module names are relative to, such as `src`. Can be repeated
+    /// [default: the top of each file's package]
+    #[arg(
+        long = "src-root",
+        value_name = "DIR",
+        value_delimiter = ',',
+        env = "THC_SRC_ROOTS"
+    )]
+    src_roots: Vec<PathBuf>,
+
+    /// Links each finding of --format json to this URL, with `{path}` (relative to the
+    /// git repository), `{line}` and `{rev}` placeholders, e.g.
+    /// `https://github.com/org/repo/blob/{rev}/{path}#L{line}`. Ignored by text output.
+    #[arg(long, value_name = "TEMPLATE", env = "THC_LINK_TEMPLATE")]
+    link_template: Option<links::LinkTemplate>,
+
+    /// Revision for the `{rev}` placeholder of --link-template [default: git HEAD]
+    #[arg(
+        long,
+        value_name = "REV",
+        requires = "link_template",
+        env = "THC_LINK_REV"
+    )]
+    link_rev: Option<String>,
+
+    /// Prints this file with line numbers and, under each line with findings, markers
+    /// spanning them along with their message, instead of checking PATH.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["paths", "files_from", "stdin_filenames", "format", "aggregate"],
+        env = "THC_ANNOTATE_SOURCE"
+    )]
+    #[serde(skip)]
+    annotate_source: Option<PathBuf>,
+
+    /// Keeps native path separators in human-readable output instead of forward slashes.
+    #[arg(long, default_value_t = false, env = "THC_NATIVE_PATHS")]
+    native_paths: bool,
+
+    /// Reports progress on stderr while checking.
+    #[arg(long, value_enum, default_value_t = ProgressMode::None, env = "THC_PROGRESS")]
+    progress: ProgressMode,
+
+    /// Writes Prometheus metrics about the run to this file, for node_exporter's
+    /// textfile collector.
+    #[arg(long, value_name = "PATH", env = "THC_METRICS_OUT")]
+    metrics_out: Option<PathBuf>,
+
+    /// Adds metrics broken down by top-level directory to --metrics-out.
+    #[arg(long, default_value_t = false, env = "THC_METRICS_BY_DIRECTORY")]
+    metrics_by_directory: bool,
+
+    /// Writes a JSON summary of the run (findings, coverage, files checked, duration and
+    /// exit status) to this file, whatever the output format. It is not written when
+    /// the run fails to check paths or times out, exiting with 2.
+    #[arg(long, value_name = "PATH", env = "THC_SUMMARY_JSON")]
+    summary_json: Option<PathBuf>,
+
+    /// Fails when the run has more findings than the budget stored in this file, in
+    /// total or for a top-level directory it lists.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["shard", "sample"],
+        env = "THC_RATCHET"
+    )]
+    ratchet: Option<PathBuf>,
+
+    /// Lowers the --ratchet budget to the run's counts when it is within budget, or
+    /// creates the file if it does not exist.
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "ratchet",
+        env = "THC_RATCHET_UPDATE"
+    )]
+    ratchet_update: bool,
+
+    /// Stops checking files once the run has lasted this long, e.g. 120s or 2m, and
+    /// reports the findings so far as partial results, exiting with status 2.
+    #[arg(long, value_name = "DURATION", env = "THC_TIMEOUT")]
+    timeout: Option<deadline::Timeout>,
+
+    /// Exits with the status the partial results would have on their own after a
+    /// --timeout, instead of 2.
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "timeout",
+        env = "THC_TIMEOUT_SOFT"
+    )]
+    timeout_soft: bool,
+
+    /// Only checks the files assigned to shard I of N, e.g. 2/4, to split a run across
+    /// parallel jobs. Every file belongs to exactly one shard.
+    #[arg(long, value_name = "I/N", env = "THC_SHARD")]
+    shard: Option<shard::Shard>,
+
+    /// Only checks this many of the walked files, or this percentage of them, picked at
+    /// random for a quick estimate. Exit statuses do not fail the run unless
+    /// --gate-sample is given.
+    #[arg(long, value_name = "N|P%", env = "THC_SAMPLE")]
+    sample: Option<sample::SampleSize>,
+
+    /// Seed picking the --sample files, to reproduce a sampled run [default: random]
+    #[arg(long, requires = "sample", env = "THC_SEED")]
+    seed: Option<u64>,
+
+    /// Lets findings and --error-on-empty fail a sampled run, even though its results are
+    /// estimates.
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "sample",
+        env = "THC_GATE_SAMPLE"
+    )]
+    gate_sample: bool,
+
+    /// Lists skipped files in the summary.
+    #[arg(short, long, default_value_t = false, env = "THC_VERBOSE")]
+    verbose: bool,
+
+    /// Only fails the run when there are more findings than this, rather than when
+    /// there are any, so that a codebase can adopt the checker gradually.
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with = "ratchet",
+        env = "THC_MAX_VIOLATIONS"
+    )]
+    max_violations: Option<usize>,
+
+    /// Fails when a directory scan finds no Python files to check.
+    #[arg(long, default_value_t = false, env = "THC_ERROR_ON_EMPTY")]
+    error_on_empty: bool,
+
+    /// Reads the settings from this file instead of the `pyhints.toml` or `pyproject.toml`
+    /// found in the checked directory or its ancestors.
+    #[arg(long, value_name = "PATH", env = "THC_CONFIG")]
+    #[serde(skip)]
+    config: Option<PathBuf>,
+
+    /// Prints the effective configuration for the given file or directory (the checked
+    /// path by default) along with where each value comes from, then exits.
+    #[arg(long, value_name = "PATH", num_args = 0..=1, require_equals = true)]
+    #[serde(skip)]
+    print_config: Option<Option<PathBuf>>,
+
+    /// Sets the option named KEY, such as `max-line-length=500`, or appends to it with
+    /// `KEY+=VALUE`, such as `ignore-dirs+=migrations`. Overrides environment variables
+    /// but not the option's own flag. Can be repeated.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    #[serde(skip)]
+    set: Vec<overrides::Override>,
 }
 
 pub fn get_tree_from_file(
     parser: &mut tree_sitter::Parser,
     path: &str,
+) -> std::io::Result<(tree_sitter::Tree, Vec<u8>)> {
+    let contents = std::fs::read(path)?;
+
+    Ok(get_tree_from_source(parser, contents))
+}
+
+pub fn get_tree_from_source(
+    parser: &mut tree_sitter::Parser,
+    contents: Vec<u8>,
 ) -> (tree_sitter::Tree, Vec<u8>) {
-    let contents =
-        std::fs::read_to_string(path).unwrap_or_else(|_| panic!("File in {path} should exist."));
-    let contents_to_return = contents.as_bytes().to_vec();
+    let tree = parser.parse(&contents, None).unwrap();
 
-    (parser.parse(contents, None).unwrap(), contents_to_return)
+    (tree, contents)
 }
 
 pub fn create_python_parser() -> tree_sitter::Parser {
@@ -56,261 +663,2704 @@ pub fn create_python_parser() -> tree_sitter::Parser {
 #[derive(Debug)]
 struct Position {
     start: tree_sitter::Point,
-    _end: tree_sitter::Point,
+    end: tree_sitter::Point,
     missing_type: MissingType,
+    /// The function the finding is about, if any.
+    function: Option<Function>,
+    /// Annotation that would fix the finding, when it can be told from the code.
+    suggestion: Option<String>,
 }
 
-#[derive(Debug)]
+/// The function definition a finding belongs to.
+#[derive(Debug, Clone)]
+struct Function {
+    /// Index of the function in definition order within its file, shared by all of its
+    /// findings so they can be aggregated.
+    group_id: usize,
+    name: String,
+    /// Dotted name within the module, such as `Greeter.greet`.
+    qualified_name: String,
+    start: tree_sitter::Point,
+}
+
+#[derive(Debug, PartialEq)]
 enum MissingType {
     Return(String),
     Parameter(String),
+    /// Target of an unannotated assignment of an empty collection.
+    EmptyCollection(String),
+    /// Spelling of a generic annotation used without type arguments.
+    BareGeneric(String),
+    /// A `typing` alias with a builtin or `|` replacement in the target Python version,
+    /// which is only known when `Optional` and `Union` are subscripted.
+    DeprecatedTyping {
+        alias: String,
+        replacement: Option<String>,
+    },
+    /// Contents of a string annotation in a module whose annotations are deferred.
+    QuotedAnnotation(String),
+    /// An optional annotation spelled against the preferred style, with its rewrite.
+    AnnotationStyle {
+        found: String,
+        replacement: String,
+    },
+    /// Name a lambda is bound to, which cannot carry type hints.
+    Lambda(String),
+    /// Name of a function annotating some of its parameters but not the others.
+    PartiallyAnnotated(String),
+    /// Name of a parameter annotated as `Any`, or of a function annotated to return it.
+    ExplicitAny {
+        name: String,
+        return_type: bool,
+    },
+    /// Code that does not parse: the kind of the token the parser expected when `missing`,
+    /// or else the start of the unparsable text.
+    SyntaxError {
+        text: String,
+        missing: bool,
+    },
+    /// Syntax that does not parse in the target Python version.
+    NewerSyntax {
+        syntax: &'static str,
+        since: PythonVersion,
+    },
+}
+
+impl MissingType {
+    /// Identifies the rule behind the finding.
+    fn code(&self) -> &'static str {
+        match self {
+            MissingType::Parameter(_) => "THC001",
+            MissingType::Return(_) => "THC002",
+            MissingType::EmptyCollection(_) => "THC101",
+            MissingType::BareGeneric(_) => "THC102",
+            MissingType::DeprecatedTyping { .. } => "THC103",
+            MissingType::QuotedAnnotation(_) => "THC104",
+            MissingType::AnnotationStyle { .. } => "THC105",
+            MissingType::Lambda(_) => "THC106",
+            MissingType::PartiallyAnnotated(_) => "THC107",
+            MissingType::ExplicitAny { .. } => "THC108",
+            MissingType::NewerSyntax { .. } => "THC201",
+            MissingType::SyntaxError { .. } => "THC202",
+        }
+    }
+
+    /// How CI formats report the finding. Syntax the target version cannot parse breaks
+    /// the code, so it is an error, while everything else is a warning.
+    fn severity(&self) -> Severity {
+        match self {
+            MissingType::NewerSyntax { .. } | MissingType::SyntaxError { .. } => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// Name shared by the GitHub and Azure DevOps commands.
+    fn name(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// How many functions a file defines and how many annotations they could carry, so that
+/// findings can be put in proportion.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct AnnotationCounts {
+    functions: usize,
+    /// Parameters and return types that are checked, whether annotated or not.
+    slots: usize,
+}
+
+/// Which findings are reported for each function definition, and which opt-in rules
+/// are enabled.
+#[derive(Debug, Default, Clone)]
+struct Rules {
+    ignore_return: bool,
+    /// Functions that do not need a return type, by name.
+    ignored_names: Vec<String>,
+    /// Matches another checker's definition of an untyped function instead of the defaults.
+    compat: Option<Compat>,
+    empty_collections: Option<CollectionScope>,
+    flag_bare_generics: bool,
+    allowed_bare_generics: Vec<String>,
+    flag_deprecated_typing: bool,
+    flag_quoted_annotations: bool,
+    annotation_style: Option<AnnotationStyle>,
+    /// Suggests `Self` for methods missing a return type that only ever return `self`.
+    suggest_self: bool,
+    /// Reports findings even where a `# hints: ignore` comment suppresses them.
+    no_inline_ignores: bool,
+    /// Checks the first parameter of methods, such as `self`, like any other.
+    strict_self: bool,
+    /// Reports lambdas bound to a name, which cannot be annotated.
+    check_lambdas: bool,
+    strictness: Strictness,
+    python_version: PythonVersion,
+}
+
+impl Rules {
+    /// The rules applied to files under typed packages: every rule is enabled and return
+    /// types are required, whatever was configured.
+    fn strict(&self) -> Rules {
+        Rules {
+            ignore_return: false,
+            empty_collections: Some(CollectionScope::All),
+            flag_bare_generics: true,
+            flag_deprecated_typing: true,
+            flag_quoted_annotations: true,
+            check_lambdas: true,
+            strictness: Strictness::Strict,
+            ..self.clone()
+        }
+    }
 }
 
 fn find_missing_types_positions(
     source_code: &[u8],
     tree: tree_sitter::Tree,
-    ignore_return: bool,
-) -> Vec<Position> {
+    rules: &Rules,
+) -> (Vec<Position>, AnnotationCounts) {
     let walk = tree.walk();
     let mut results = Vec::new();
+    let mut counts = AnnotationCounts::default();
+    let mypy = rules.compat == Some(Compat::Mypy);
+    let flags_quoted_annotations = rules.flag_quoted_annotations
+        && rules::annotations_are_deferred(tree.root_node(), source_code, rules.python_version);
+
+    if !rules.no_inline_ignores && inline_ignores::ignores_file(source_code) {
+        return (results, counts);
+    }
+
+    // Files that do not parse are reported once, at the first error, while what lies
+    // outside of the broken regions is still checked.
+    let syntax_error = rules::first_syntax_error(tree.root_node());
+    if let Some(error) = syntax_error {
+        let (text, missing) = rules::syntax_error_text(error, source_code);
+        results.push(Position {
+            start: error.start_position(),
+            end: error.end_position(),
+            missing_type: MissingType::SyntaxError { text, missing },
+            function: None,
+            suggestion: None,
+        });
+    }
+
+    let mut group_id = 0;
 
     for node in tree_sitter_traversal::traverse(walk, tree_sitter_traversal::Order::Pre) {
+        if syntax_error.is_some() && rules::in_error_region(node) {
+            continue;
+        }
+
+        if rules.python_version < python_version::TYPE_PARAMETER_SYNTAX {
+            if let Some((syntax_node, syntax)) = rules::type_parameter_syntax(node) {
+                results.push(Position {
+                    start: syntax_node.start_position(),
+                    end: syntax_node.end_position(),
+                    missing_type: MissingType::NewerSyntax {
+                        syntax,
+                        since: python_version::TYPE_PARAMETER_SYNTAX,
+                    },
+                    function: None,
+                    suggestion: None,
+                });
+            }
+        }
+
         if node.kind() == "function_definition" {
-            let mut cursor = node.walk();
-
-            let mut has_return_type = false;
-            for child in node.children(&mut cursor) {
-                // println!(
-                //     "Kind: {:?}, Text: {:?}, Id: {:?}",
-                //     child.kind(),
-                //     child.utf8_text(source_code),
-                //     child.kind_id()
-                // );
-
-                if child.kind() == "type" {
-                    has_return_type = true;
-                }
+            if !rules.no_inline_ignores && inline_ignores::ignores_function(node, source_code) {
+                continue;
+            }
 
-                if child.kind_id() == PARAMETERS_KIND {
-                    let mut cursor = child.walk();
-                    for inner_child in child.children(&mut cursor) {
-                        if matches!(inner_child.kind_id(), IDENTIFIER | DEFAULT_PARAMETER) {
-                            let utf8_text = inner_child.utf8_text(source_code);
+            let function_name = node
+                .child_by_field_name("name")
+                .and_then(|identifier| identifier.utf8_text(source_code).ok())
+                .expect("Function should have name.")
+                .to_string();
 
-                            if let Ok("self") = utf8_text {
-                                continue;
-                            }
+            let function = Function {
+                group_id,
+                name: function_name,
+                qualified_name: stubs::qualified_name(node, source_code),
+                start: node.start_position(),
+            };
+            group_id += 1;
+            counts.functions += 1;
 
-                            let start = inner_child.start_position();
-                            let end = inner_child.end_position();
+            // mypy skips the bound parameter of methods whatever its name, the defaults
+            // only when it follows the naming convention.
+            let first_parameter = node
+                .child_by_field_name("parameters")
+                .and_then(|parameters| parameters.named_child(0))
+                .and_then(|parameter| parameter.utf8_text(source_code).ok());
+            let skips_first_parameter = !rules.strict_self
+                && if mypy {
+                    compat::binds_first_parameter(node, source_code)
+                } else {
+                    compat::bound_parameter_name(node, source_code)
+                        .is_some_and(|name| first_parameter == Some(name))
+                };
+            let mut has_annotated_parameter = false;
+            let mut has_unannotated_parameter = false;
+            let has_return_type = node.child_by_field_name("return_type").is_some();
+            if let Some(parameters) = node.child_by_field_name("parameters") {
+                let mut cursor = parameters.walk();
+                for (index, inner_child) in parameters.named_children(&mut cursor).enumerate() {
+                    if matches!(
+                        inner_child.kind(),
+                        "typed_parameter" | "typed_default_parameter"
+                    ) {
+                        counts.slots += 1;
+                        has_annotated_parameter = true;
 
+                        if let Some((name, annotation)) = (rules.strictness >= Strictness::Strict)
+                            .then(|| rules::any_parameter(inner_child, source_code))
+                            .flatten()
+                        {
                             results.push(Position {
-                                start,
-                                _end: end,
-                                missing_type: MissingType::Parameter(
-                                    utf8_text.expect("Parameter should have name").to_string(),
-                                ),
+                                start: annotation.start_position(),
+                                end: annotation.end_position(),
+                                missing_type: MissingType::ExplicitAny {
+                                    name: name.to_string(),
+                                    return_type: false,
+                                },
+                                function: Some(function.clone()),
+                                suggestion: None,
                             });
                         }
                     }
+
+                    // The bare `*` and `/` separators are not parameters, while
+                    // `*args` and `**kwargs` are reported along with their stars.
+                    let is_unannotated =
+                        matches!(inner_child.kind_id(), IDENTIFIER | DEFAULT_PARAMETER)
+                            || matches!(
+                                inner_child.kind(),
+                                "list_splat_pattern" | "dictionary_splat_pattern"
+                            );
+
+                    if is_unannotated {
+                        let utf8_text = inner_child.utf8_text(source_code);
+
+                        if index == 0 && skips_first_parameter {
+                            continue;
+                        }
+
+                        counts.slots += 1;
+                        has_unannotated_parameter = true;
+
+                        let start = inner_child.start_position();
+                        let end = inner_child.end_position();
+
+                        results.push(Position {
+                            start,
+                            end,
+                            missing_type: MissingType::Parameter(
+                                utf8_text.expect("Parameter should have name").to_string(),
+                            ),
+                            function: Some(function.clone()),
+                            suggestion: None,
+                        });
+                    }
                 }
             }
-            if !has_return_type & !ignore_return {
-                let identifier = node.child(1).expect("Function should have name.");
-                let mut function_name = identifier
-                    .utf8_text(source_code)
-                    .expect("Function should have name.")
-                    .to_string();
-
-                if function_name == "def" {
-                    let identifier = node.child(2).expect("Function should have name.");
-                    function_name = identifier
-                        .utf8_text(source_code)
-                        .expect("Function should have name.")
-                        .to_string();
-                }
 
-                if function_name == "main" {
+            if rules.strictness >= Strictness::Consistent
+                && has_annotated_parameter
+                && has_unannotated_parameter
+            {
+                results.push(Position {
+                    start: node.start_position(),
+                    end: node.end_position(),
+                    missing_type: MissingType::PartiallyAnnotated(function.name.clone()),
+                    function: Some(function.clone()),
+                    suggestion: None,
+                });
+            }
+
+            if let Some(return_type) =
+                node.child_by_field_name("return_type")
+                    .filter(|return_type| {
+                        rules.strictness >= Strictness::Strict
+                            && rules::is_any(*return_type, source_code)
+                    })
+            {
+                results.push(Position {
+                    start: return_type.start_position(),
+                    end: return_type.end_position(),
+                    missing_type: MissingType::ExplicitAny {
+                        name: function.name.clone(),
+                        return_type: true,
+                    },
+                    function: Some(function.clone()),
+                    suggestion: None,
+                });
+            }
+
+            let exempt_from_return = if mypy {
+                compat::returns_none_implicitly(&function.name, has_annotated_parameter)
+            } else {
+                rules.ignored_names.contains(&function.name)
+            };
+
+            if !rules.ignore_return && (has_return_type || !exempt_from_return) {
+                counts.slots += 1;
+            }
+
+            if !has_return_type & !rules.ignore_return {
+                if exempt_from_return {
                     continue;
                 }
 
+                let suggestion = rules.suggest_self.then(|| {
+                    rules::self_return_suggestion(node, source_code, rules.python_version)
+                });
                 results.push(Position {
                     start: node.start_position(),
-                    _end: node.end_position(),
-                    missing_type: MissingType::Return(function_name),
+                    end: node.end_position(),
+                    missing_type: MissingType::Return(function.name.clone()),
+                    function: Some(function),
+                    suggestion: suggestion.flatten(),
                 });
             }
-        }
-    }
-    results
-}
+        } else if node.kind() == "assignment" {
+            if rules.check_lambdas {
+                if let Some(name) = rules::lambda_binding(node, source_code) {
+                    results.push(Position {
+                        start: node.start_position(),
+                        end: node.end_position(),
+                        missing_type: MissingType::Lambda(name.to_string()),
+                        function: None,
+                        suggestion: None,
+                    });
+                }
+            }
 
-fn get_message_from_positions(positions: &[Position]) -> String {
-    let mut message = String::new();
+            let Some(scope) = rules.empty_collections else {
+                continue;
+            };
 
-    for position in positions {
-        match &position.missing_type {
-            MissingType::Return(name) => {
-                message += &format!(
-                    "Function '{name}' in line {} and column {} is missing a return type.\n",
-                    position.start.row + 1,
-                    position.start.column + 1
-                )
+            if let Some(target) = rules::empty_collection_target(node, source_code, scope) {
+                results.push(Position {
+                    start: node.start_position(),
+                    end: node.end_position(),
+                    missing_type: MissingType::EmptyCollection(target.to_string()),
+                    function: None,
+                    suggestion: None,
+                });
             }
-            MissingType::Parameter(name) => {
-                message += &format!(
-                    "Parameter '{name}' in line {} and column {} is missing a type hint.\n",
-                    position.start.row + 1,
-                    position.start.column + 1
-                )
+        } else {
+            if node.kind() == "type" && rules.flag_bare_generics {
+                if let Some(generic) =
+                    rules::bare_generic(node, source_code, &rules.allowed_bare_generics)
+                {
+                    results.push(Position {
+                        start: node.start_position(),
+                        end: node.end_position(),
+                        missing_type: MissingType::BareGeneric(generic.to_string()),
+                        function: None,
+                        suggestion: None,
+                    });
+                }
+            }
+
+            if node.kind() == "type" && flags_quoted_annotations {
+                if let Some(contents) = rules::quoted_annotation(node, source_code) {
+                    results.push(Position {
+                        start: node.start_position(),
+                        end: node.end_position(),
+                        missing_type: MissingType::QuotedAnnotation(contents.to_string()),
+                        function: None,
+                        suggestion: None,
+                    });
+                }
+            }
+
+            if let Some(style) = rules.annotation_style {
+                if let Some(violation) = rules::style_violation(node, source_code, style) {
+                    results.push(Position {
+                        start: node.start_position(),
+                        end: node.end_position(),
+                        missing_type: MissingType::AnnotationStyle {
+                            found: violation.found.to_string(),
+                            replacement: violation.replacement.clone(),
+                        },
+                        function: None,
+                        suggestion: Some(violation.replacement),
+                    });
+                }
+            }
+
+            if rules.flag_deprecated_typing {
+                if let Some(deprecated) =
+                    rules::deprecated_alias(node, source_code, rules.python_version)
+                {
+                    results.push(Position {
+                        start: node.start_position(),
+                        end: node.end_position(),
+                        missing_type: MissingType::DeprecatedTyping {
+                            alias: deprecated.alias.to_string(),
+                            replacement: deprecated.replacement.clone(),
+                        },
+                        function: None,
+                        suggestion: deprecated.replacement,
+                    });
+                }
             }
         }
     }
-
-    message
+    (results, counts)
 }
 
 fn main() {
-    let args = Args::parse();
-    let path = args.path;
-    let ignore_hidden = args.ignore_hidden;
-    let ignore_tests = args.ignore_tests;
-    let ignore_return = args.ignore_return;
+    let mut command = Cli::command();
+    let mut matches = command.clone().get_matches();
+    let overridden;
+    (command, overridden) = apply_overrides(command, &matches);
+    if !overridden.is_empty() {
+        matches = command.clone().get_matches();
+    }
+    let configured;
+    (command, configured) = apply_config_file(command, &matches, &overridden);
+    if !configured.is_empty() {
+        matches = command.clone().get_matches();
+    }
+    let sources: Vec<(String, String)> = overridden
+        .into_iter()
+        .map(|key| (key, "cli:--set".to_string()))
+        .chain(configured)
+        .collect();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
-    let path = PathBuf::from(&path);
+    if let Some(print_config) = &cli.args.print_config {
+        let path = print_config
+            .clone()
+            .or_else(|| cli.args.paths.first().cloned())
+            .unwrap_or_else(|| PathBuf::from("."));
+        let (python_version, python_version_source) = cli
+            .args
+            .python_version(&path)
+            .unwrap_or_else(|e| exit_with_error(e));
+        let detected = [config::ConfigEntry {
+            key: "python-version".to_string(),
+            value: python_version.to_string().into(),
+            source: python_version_source.map_or("default".to_string(), |source| {
+                format!("requires-python in {}", source.display())
+            }),
+        }];
+        let entries = config::effective_config(
+            &cli.args,
+            &matches,
+            &command,
+            &detected,
+            &sources,
+            |variable| std::env::var(variable).ok(),
+        );
+        print!("{}", config::render_config(&path, &entries));
+        return;
+    }
 
-    if path.is_dir() {
-        let message = Arc::new(Mutex::from(String::new()));
+    match cli.command {
+        Some(Command::ListFiles { args, why }) => list_files(&args, why),
+        Some(Command::Inventory { args }) => inventory(&args),
+        Some(Command::Compare {
+            old,
+            new,
+            new_only,
+            renames,
+            renames_from,
+            format,
+        }) => compare(&old, &new, new_only, renames, renames_from, format),
+        Some(Command::Merge {
+            reports,
+            output,
+            format,
+        }) => merge(&reports, output.as_deref(), format),
+        Some(Command::Explain { compat }) => print!("{}", compat::explain(compat)),
+        None => match &cli.args.annotate_source {
+            Some(file) => annotate_source(&cli.args, file),
+            None => check(&cli.args),
+        },
+    }
+}
 
-        let walkdir = walkdir::WalkDir::new(path);
+/// Makes the options configured in the configuration file, but neither given nor
+/// `overridden` by --set, default to their configured value, returning the options along
+/// with the file's path.
+fn apply_config_file(
+    command: clap::Command,
+    matches: &clap::ArgMatches,
+    overridden: &[String],
+) -> (clap::Command, Vec<(String, String)>) {
+    let subcommand = matches.subcommand_name();
+    let matches = match matches.subcommand() {
+        Some((_, matches)) => matches,
+        None => matches,
+    };
+    let (Ok(explicit), Ok(paths)) = (
+        matches.try_get_one::<PathBuf>("config"),
+        matches.try_get_many::<PathBuf>("paths"),
+    ) else {
+        return (command, Vec::new());
+    };
+    // The configuration is the one --print-config prints, when given a path.
+    let start = matches
+        .try_get_one::<PathBuf>("print_config")
+        .ok()
+        .flatten()
+        .or_else(|| paths.and_then(|mut paths| paths.next()))
+        .map_or(Path::new("."), PathBuf::as_path);
 
-        let mut filters: Vec<Box<dyn Filter + Sync>> = Vec::new();
-        if ignore_hidden {
-            filters.push(Box::new(NotHidden));
-        }
-        if ignore_tests {
-            filters.push(Box::new(NotTest));
-        }
+    let Some(config_file) = config_file::ConfigFile::locate(explicit.map(PathBuf::as_path), start)
+        .unwrap_or_else(|e| exit_with_error(e))
+    else {
+        return (command, Vec::new());
+    };
+    let assignments = config_file
+        .assignments(&command, matches, overridden)
+        .unwrap_or_else(|e| exit_with_error(e));
+    let source = format!("config:{}", config_file.path.display());
+    let command = overrides::apply(command, subcommand, &assignments, false);
 
-        walkdir
-            .into_iter()
-            .filter_entry(|x| filters.iter().all(|filter| filter.should_be_processed(x)))
-            .flatten()
-            .par_bridge()
-            .for_each(|entry| add_to_message_from_file(entry, Arc::clone(&message), ignore_return));
+    let configured = assignments
+        .into_iter()
+        .map(|assignment| (assignment.key, source.clone()))
+        .collect();
+    (command, configured)
+}
 
-        let message = message
-            .as_ref()
-            .lock()
-            .expect("Should be able to retrieve message at end of parallelization.");
+/// Makes the options given to --set default to their value, and no longer read their
+/// environment variable, so that parsing the arguments again applies them below the
+/// flags given on the command line, and returns the names of these options.
+fn apply_overrides(
+    command: clap::Command,
+    matches: &clap::ArgMatches,
+) -> (clap::Command, Vec<String>) {
+    let subcommand = matches.subcommand_name();
+    let matches = match matches.subcommand() {
+        Some((_, matches)) => matches,
+        None => matches,
+    };
+    let overrides: Vec<overrides::Override> = matches
+        .try_get_many::<overrides::Override>("set")
+        .ok()
+        .flatten()
+        .map(|overrides| overrides.cloned().collect())
+        .unwrap_or_default();
 
-        if message.is_empty() {
-            println!("✨ All good!");
-        } else {
-            print!("{}", message);
-        }
-    } else {
-        let message = get_message_from_file(path.as_path(), ignore_return);
+    let assignments = overrides::assignments(&overrides, &command, matches)
+        .unwrap_or_else(|e| exit_with_error(e));
+    let command = overrides::apply(command, subcommand, &assignments, true);
+
+    let overridden = assignments
+        .into_iter()
+        .map(|assignment| assignment.key)
+        .collect();
+    (command, overridden)
+}
+
+impl Args {
+    /// The first checked path, from which the configuration and the project root are
+    /// found, or the current directory when only checking listed paths.
+    fn path(&self) -> PathBuf {
+        self.paths
+            .first()
+            .cloned()
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
 
-        if message.is_empty() {
-            println!("✨ All good!");
+    /// The list of paths to check given by --files-from or --stdin-filenames, if any.
+    fn path_list(&self) -> Option<&Path> {
+        if self.stdin_filenames {
+            Some(Path::new("-"))
         } else {
-            print!("{}", message);
+            self.files_from.as_deref()
         }
     }
-}
 
-trait Filter {
-    fn should_be_processed(&self, entry: &DirEntry) -> bool;
-}
+    /// The files and directories to check: the paths, followed by the listed ones.
+    fn targets(&self) -> Result<Vec<PathBuf>, String> {
+        let mut targets = self.paths.clone();
+        let Some(files_from) = self.path_list() else {
+            return Ok(targets);
+        };
+
+        let from_stdin = files_from.as_os_str() == "-";
+        let source = if from_stdin {
+            "stdin".to_string()
+        } else {
+            files_from.display().to_string()
+        };
 
-struct NotHidden;
+        let contents = if from_stdin {
+            let mut contents = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut contents)
+                .map(|_| contents)
+        } else {
+            std::fs::read(files_from)
+        }
+        .map_err(|e| format!("could not read {source}: {e}"))?;
+
+        let listed =
+            paths::read_path_list(&contents, self.null).map_err(|e| format!("{e} in {source}"))?;
+        if let Some(missing) = listed.iter().find(|target| !target.exists()) {
+            return Err(format!(
+                "'{}' listed in {source} does not exist{}",
+                missing.display(),
+                if self.null {
+                    ""
+                } else {
+                    " (pass -0 for NUL-separated lists)"
+                }
+            ));
+        }
+        targets.extend(listed);
 
-impl Filter for NotHidden {
-    fn should_be_processed(&self, entry: &DirEntry) -> bool {
-        entry
-            .file_name()
-            .to_str()
-            .map(|s| !s.starts_with('.') || s == ".")
-            .unwrap_or(false)
+        Ok(targets)
     }
-}
 
-struct NotTest;
+    fn filters(&self) -> Result<Vec<Box<dyn Filter + Sync>>, String> {
+        let mut filters: Vec<Box<dyn Filter + Sync>> = Vec::new();
+        if self.ignore_hidden {
+            filters.push(Box::new(NotHidden));
+        }
+        if self.ignore_tests {
+            filters.push(Box::new(NotTest));
+        }
+        if !self.ignore_dirs.is_empty() {
+            filters.push(Box::new(IgnoreDirs(self.ignore_dirs.clone())));
+        }
+        if !self.no_default_excludes {
+            filters.push(Box::new(DefaultExcludes));
+        }
+        if !self.no_thcignore {
+            filters.push(Box::new(IgnoreFiles::thcignore()));
+        }
+        if !self.no_gitignore {
+            filters.push(Box::new(IgnoreFiles::gitignore()));
+        }
+        if self.packages_only {
+            filters.push(Box::new(PackagesOnly::new(self.namespace_packages)));
+        }
+        if !self.exclude.is_empty() {
+            let patterns = self
+                .exclude
+                .iter()
+                .map(|pattern| GlobPattern::relative_to_root(pattern, "--exclude".to_string()))
+                .collect::<Result<_, _>>()?;
+            filters.push(Box::new(GlobExclude(patterns)));
+        }
+        if !self.include.is_empty() {
+            let patterns = self
+                .include
+                .iter()
+                .map(|pattern| GlobPattern::relative_to_root(pattern, "--include".to_string()))
+                .collect::<Result<_, _>>()?;
+            filters.push(Box::new(GlobInclude(patterns)));
+        }
+
+        for tool in &self.respect_tool_excludes {
+            match find_tool_excludes(*tool, &self.path())? {
+                Some(excludes) => {
+                    if self.verbose {
+                        eprintln!(
+                            "note: imported {} exclude pattern(s) from {}.",
+                            excludes.patterns.len(),
+                            excludes.source.display()
+                        );
+                    }
+                    filters.push(Box::new(GlobExclude(excludes.patterns)));
+                }
+                None => {
+                    eprintln!("warning: no {tool:?} configuration found to import excludes from.")
+                }
+            }
+        }
 
-impl Filter for NotTest {
-    fn should_be_processed(&self, entry: &DirEntry) -> bool {
-        entry
-            .file_name()
-            .to_str()
-            .map(|s| !s.starts_with("test_") && s != "tests")
-            .unwrap_or(false)
+        Ok(filters)
     }
-}
 
-fn add_to_message_from_file(
-    entry: walkdir::DirEntry,
-    message: Arc<Mutex<String>>,
-    ignore_return: bool,
-) {
-    if !entry.metadata().expect("Should have metadata.").is_dir()
-        && entry
-            .file_name()
-            .to_str()
-            .expect("Should be valid path name.")
-            .ends_with(".py")
-    {
-        let messages_from_file = get_message_from_file(entry.path(), ignore_return);
-        if messages_from_file.is_empty() {
-            return;
+    /// The targeted Python version, along with the `pyproject.toml` it was read from
+    /// when not given explicitly.
+    fn python_version(&self, path: &Path) -> Result<(PythonVersion, Option<PathBuf>), String> {
+        if let Some(version) = self.python_version {
+            return Ok((version, None));
         }
 
-        let mut message = message
-            .lock()
-            .expect("Should be able to get a lock on the message.");
+        Ok(match python_version::find_requires_python(path)? {
+            Some((version, source)) => (version, Some(source)),
+            None => (PythonVersion::default(), None),
+        })
+    }
 
-        *message += format!(
-            "File: {}\n",
-            entry.path().to_str().expect("Should be valid path name.")
-        )
-        .as_str();
+    /// Functions that do not need a return type: those given to --ignore-names and
+    /// --ignore-return-for, and the usual dunders unless --require-init-return is given.
+    fn ignored_names(&self) -> Vec<String> {
+        let dunders = compat::DUNDERS_WITHOUT_RETURN
+            .iter()
+            .filter(|_| !self.require_init_return)
+            .map(|name| name.to_string());
 
-        let messages_from_file = messages_from_file.split('\n');
+        self.ignore_names
+            .iter()
+            .chain(&self.ignore_return_for)
+            .cloned()
+            .chain(dunders)
+            .collect()
+    }
 
-        for line in messages_from_file {
-            *message += &("    ".to_string() + line + "\n")
+    fn check_options(&self, format: OutputFormat, python_version: PythonVersion) -> CheckOptions {
+        CheckOptions {
+            format,
+            rules: Rules {
+                ignore_return: self.ignore_return,
+                ignored_names: self.ignored_names(),
+                compat: self.compat,
+                empty_collections: self.flag_empty_collections,
+                flag_bare_generics: self.flag_bare_generics,
+                allowed_bare_generics: self.allow_bare_generics.clone(),
+                flag_deprecated_typing: self.flag_deprecated_typing,
+                flag_quoted_annotations: self.flag_quoted_annotations,
+                annotation_style: self.annotation_style,
+                suggest_self: self.suggest_self,
+                no_inline_ignores: self.no_inline_ignores,
+                strict_self: self.strict_self,
+                check_lambdas: self.check_lambdas,
+                strictness: self.strictness,
+                python_version,
+            },
+            max_line_length: self.max_line_length,
+            skip_minified: self.skip_minified,
+            extensions: self.extensions.clone(),
+            native_paths: self.native_paths,
+            aggregate: self.aggregate,
+            group_by_file: !self.no_group_by_file,
+            indent: self.indent,
+            sorted: self.sorted,
+            null: self.null,
+            module_names: self.module_names.then(|| self.src_roots.clone()),
+            prefer_stubs: self.prefer_stubs,
+            check_doctests: self.check_doctests,
+            lossy_utf8: self.lossy_utf8,
+            typed_files: None,
+            links: None,
+            deadline: None,
+            interrupts: &interrupt::INTERRUPTS,
+            progress: self.progress,
         }
     }
 }
 
-fn get_message_from_file(file: &Path, ignore_return: bool) -> String {
-    let mut parser = create_python_parser();
-
-    let (tree, source_code) = get_tree_from_file(
-        &mut parser,
-        file.to_str().expect("Should be valid path name."),
-    );
-    let positions = find_missing_types_positions(&source_code, tree, ignore_return);
+/// Prints an operational error and exits with the corresponding status code.
+fn exit_with_error(error: String) -> ! {
+    eprintln!("error: {error}");
+    std::process::exit(2);
+}
 
-    get_message_from_positions(&positions)
+/// Errors when an option needs functionality this binary was built without.
+fn require_feature(enabled: bool, feature: &str, usage: &str) -> Result<(), String> {
+    if enabled {
+        Ok(())
+    } else {
+        Err(format!(
+            "{usage} is not available as pythcheck was built without the '{feature}' feature."
+        ))
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    #[test]
-    fn tree_from_test_file() {
-        let mut parser = create_python_parser();
-        get_tree_from_file(&mut parser, "test_file.py");
+impl Args {
+    /// Rejects options that cannot work in this build, before doing any work.
+    fn validate(&self) -> Result<(), String> {
+        if self.progress == ProgressMode::Json {
+            require_feature(
+                cfg!(feature = "formats-json"),
+                "formats-json",
+                "--progress json",
+            )?;
+        }
+        if let Some(format @ (OutputFormat::Json | OutputFormat::Gitlab)) = self.format {
+            require_feature(
+                cfg!(feature = "formats-json"),
+                "formats-json",
+                &format!(
+                    "--format {}",
+                    format
+                        .to_possible_value()
+                        .expect("Formats should not be skipped.")
+                        .get_name()
+                ),
+            )?;
+        }
+        if self.summary_json.is_some() {
+            require_feature(
+                cfg!(feature = "formats-json"),
+                "formats-json",
+                "--summary-json",
+            )?;
+        }
+
+        Ok(())
     }
+}
 
-    #[test]
-    fn find_args_test() {
-        let mut parser = create_python_parser();
-        let (tree, source_code) = get_tree_from_file(&mut parser, "test_file.py");
-        println!(
+fn check(args: &Args) {
+    args.validate().unwrap_or_else(|e| exit_with_error(e));
+
+    let started = std::time::Instant::now();
+    let path = args.path();
+
+    let (format, detected_from) =
+        resolve_format(args.format, |variable| std::env::var(variable).ok());
+    if let Some(variable) = detected_from {
+        eprintln!(
+            "note: {variable} is set, using --format {} (pass --format text to disable).",
+            format
+                .to_possible_value()
+                .expect("Formats should not be skipped.")
+                .get_name()
+        );
+    }
+    let (python_version, _) = args
+        .python_version(&path)
+        .unwrap_or_else(|e| exit_with_error(e));
+    if args.annotation_style == Some(AnnotationStyle::Union)
+        && python_version < PythonVersion::new(3, 10)
+    {
+        exit_with_error(format!(
+            "--annotation-style union suggests `X | None`, which needs Python 3.10 or later, but Python {python_version} is targeted."
+        ));
+    }
+    if args.null && format != OutputFormat::Text {
+        exit_with_error(format!(
+            "--null only applies to text records and cannot be combined with --format {}.",
+            format
+                .to_possible_value()
+                .expect("Formats should not be skipped.")
+                .get_name()
+        ));
+    }
+    if args.statistics && format != OutputFormat::Text {
+        exit_with_error(format!(
+            "--statistics only applies to text output and cannot be combined with --format {}.",
+            format
+                .to_possible_value()
+                .expect("Formats should not be skipped.")
+                .get_name()
+        ));
+    }
+    let mut options = args.check_options(format, python_version);
+    if let Some(template) = &args.link_template {
+        let links = links::Links::new(template.clone(), args.link_rev.clone(), &path)
+            .unwrap_or_else(|e| exit_with_error(e));
+        options.links = Some(links);
+    }
+    options.deadline = args
+        .timeout
+        .map(|timeout| deadline::Deadline::new(started, timeout));
+    let filters = args.filters().unwrap_or_else(|e| exit_with_error(e));
+    let targets = args.targets().unwrap_or_else(|e| exit_with_error(e));
+
+    // Whether a filter pruned a directory or a Python file, which the warning about
+    // finding no files then points out.
+    let mut excluded_any = false;
+    let (mut files, walk_errors) =
+        collect_files(&targets, &filters, &options.extensions, |entry, _| {
+            excluded_any |=
+                entry.file_type().is_dir() || is_python_file(entry.path(), &options.extensions);
+        });
+    files.extend(docs::find_docs(&args.docs).unwrap_or_else(|e| exit_with_error(e)));
+    if let Some(shard) = args.shard {
+        files = shard.select(files, &targets);
+    }
+    let (files, sampling) = match args.sample {
+        Some(size) => {
+            let total_files = files.len();
+            let seed = args.seed.unwrap_or_else(sample::random_seed);
+            let files = sample::sample(files, size, seed);
+            let sampling = sample::Sampling {
+                sampled_files: files.len(),
+                total_files,
+                seed,
+            };
+            (files, Some(sampling))
+        }
+        None => (files, None),
+    };
+    if args.fix_dry_run {
+        let fixable = fix::fix_files(&files, &options.rules, true);
+        eprintln!("Would fix {fixable} file(s).");
+        std::process::exit(i32::from(fixable > 0));
+    }
+    if args.fix {
+        let fixed = fix::fix_files(&files, &options.rules, false);
+        eprintln!("Fixed {fixed} file(s).");
+    }
+    if args.strict_typed_packages {
+        options.typed_files = Some(typed_packages::typed_files(&files));
+    }
+    let total_files = files.len();
+    interrupt::install();
+
+    // Directories and lists may legitimately contain no Python files, unlike file paths.
+    // Anything else, such as a mistyped path, is searched without finding any either.
+    let searched = args.path_list().is_some() || targets.iter().any(|target| !target.is_file());
+    print!("{}", report::render_run_start(options.format));
+    let mut summary = if options.format == OutputFormat::Json {
+        let (records, summary) = check_files(files, &options);
+        let report = json_report::JsonReport::new(
+            json_report::parse_records(&records),
+            &summary,
+            config::options_fingerprint(args),
+        );
+        print!("{}", json_report::render(&report));
+        summary
+    } else if options.format == OutputFormat::Gitlab {
+        let (records, summary) = check_files(files, &options);
+        print!("{}", gitlab_report::render(&records));
+        summary
+    } else if args.statistics {
+        let summary = stream_files(files, &options, |_| {});
+        print!(
+            "{}",
+            report::render_statistics(&summary.files, options.native_paths)
+        );
+        summary
+    } else {
+        let mut stdout = std::io::stdout();
+        stream_files(files, &options, |block| {
+            let _ = stdout.write_all(block.as_bytes());
+            let _ = stdout.flush();
+        })
+    };
+    summary.could_not_check.extend(walk_errors);
+    if !matches!(options.format, OutputFormat::Json | OutputFormat::Gitlab)
+        && summary.findings == 0
+        && !args.null
+        && summary.unchecked == 0
+        && summary.could_not_check.is_empty()
+        && (summary.files_checked > 0 || !searched)
+    {
+        println!("✨ All good!");
+    }
+
+    eprint!("{}", summary.render(args.verbose));
+    if summary.files_checked > 0 {
+        eprint!("{}", summary.render_totals());
+    }
+    if let Some(sampling) = &sampling {
+        eprint!("{}", sampling.render(&summary));
+    }
+
+    if let Some(metrics_out) = &args.metrics_out {
+        let metrics = metrics::render_metrics(
+            &summary,
+            &path,
+            started.elapsed(),
+            args.metrics_by_directory,
+        );
+        metrics::write_atomically(metrics_out, &metrics).unwrap_or_else(|e| {
+            exit_with_error(format!(
+                "could not write metrics to {}: {e}",
+                metrics_out.display()
+            ))
+        });
+    }
+
+    let is_empty = searched && summary.files_checked == 0;
+    // Sampled results are estimates, which only fail the run when asked to.
+    let gated = sampling.is_none() || args.gate_sample;
+    // Syntax errors are reported, but only fail the run with --fail-on-parse-error.
+    let budgeted_findings = summary.findings - summary.syntax_errors.len();
+    let fails_on_parse_error = args.fail_on_parse_error && !summary.syntax_errors.is_empty();
+    // With --ratchet, the ratchet file budgets the findings instead.
+    let over_budget =
+        args.ratchet.is_none() && budgeted_findings > args.max_violations.unwrap_or(0);
+    let mut exit_status = if !summary.could_not_check.is_empty()
+        || (gated && is_empty && args.error_on_empty)
+    {
+        2
+    } else if gated && (over_budget || fails_on_parse_error) {
+        if let (true, Some(max_violations)) = (over_budget, args.max_violations) {
+            eprintln!(
+                    "{budgeted_findings} finding(s), more than the {max_violations} allowed by --max-violations."
+                );
+        }
+        if fails_on_parse_error {
+            eprintln!(
+                "{} file(s) do not parse (--fail-on-parse-error).",
+                summary.syntax_errors.len()
+            );
+        }
+        1
+    } else {
+        0
+    };
+    if interrupt::interrupted() && summary.unchecked > 0 {
+        eprintln!(
+            "Interrupted after {} of ~{total_files} file(s), the results are partial.",
+            total_files - summary.unchecked
+        );
+        exit_status = interrupt::EXIT_STATUS;
+    } else if summary.unchecked > 0 {
+        eprintln!(
+            "warning: the run timed out after {}, {} file(s) were not checked, so the results are partial.",
+            args.timeout.expect("Only runs with a timeout should leave files unchecked."),
+            summary.unchecked
+        );
+        if !args.timeout_soft {
+            exit_status = 2;
+        }
+    }
+    if let Some(ratchet) = &args.ratchet {
+        if summary.unchecked > 0 {
+            eprintln!("note: skipping --ratchet, as the results are partial.");
+        } else if !apply_ratchet(ratchet, args.ratchet_update, &summary, &path) && exit_status == 0
+        {
+            exit_status = 1;
+        }
+    }
+
+    if let (Some(_), 2) = (&args.summary_json, exit_status) {
+        eprintln!("note: not writing --summary-json, as the run could not be completed.");
+    } else if let Some(summary_json) = &args.summary_json {
+        let run_summary = run_summary::RunSummary {
+            sample: sampling,
+            ..run_summary::RunSummary::new(
+                &summary,
+                config::options_fingerprint(args),
+                exit_status,
+                started.elapsed(),
+            )
+        };
+        metrics::write_atomically(summary_json, &run_summary.render()).unwrap_or_else(|e| {
+            exit_with_error(format!(
+                "could not write the summary to {}: {e}",
+                summary_json.display()
+            ))
+        });
+    }
+
+    if is_empty {
+        let location = match (args.path_list(), args.paths.as_slice()) {
+            (Some(list), _) if list.as_os_str() == "-" => {
+                "in the paths read from stdin".to_string()
+            }
+            (Some(list), _) => format!("in the paths listed in '{}'", list.display()),
+            (None, [path]) => format!("under '{}'", path.display()),
+            (None, paths) => format!("under the {} given paths", paths.len()),
+        };
+        eprintln!(
+            "warning: no Python files found {location}{}.",
+            if excluded_any {
+                " (they may all have been excluded by the ignore flags, ignore files or default excludes)"
+            } else {
+                ""
+            }
+        );
+    }
+
+    print!(
+        "{}",
+        report::render_run_end(options.format, summary.findings, exit_status)
+    );
+
+    if exit_status != 0 {
+        std::process::exit(exit_status);
+    }
+}
+
+/// Checks the run against the ratchet file, updating it when asked to, and returns
+/// whether the run is within budget.
+fn apply_ratchet(path: &Path, update: bool, summary: &Summary, root: &Path) -> bool {
+    let write = |ratchet: &ratchet::Ratchet| {
+        metrics::write_atomically(path, &ratchet.render())
+            .unwrap_or_else(|e| exit_with_error(format!("could not write {}: {e}", path.display())))
+    };
+
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && update => {
+            let ratchet = ratchet::Ratchet::from_summary(summary, root);
+            write(&ratchet);
+            eprintln!(
+                "Ratchet: created {} with a budget of {} finding(s).",
+                path.display(),
+                ratchet.total
+            );
+            return true;
+        }
+        Err(e) => exit_with_error(format!(
+            "could not read {}: {e}{}",
+            path.display(),
+            if e.kind() == std::io::ErrorKind::NotFound {
+                " (pass --ratchet-update to create it)"
+            } else {
+                ""
+            }
+        )),
+    };
+    let ratchet = ratchet::Ratchet::parse(&contents)
+        .unwrap_or_else(|e| exit_with_error(format!("could not parse {}: {e}", path.display())));
+
+    let check = ratchet.check(summary, root);
+    eprint!("{}", check.render());
+    if !check.passed() {
+        return false;
+    }
+
+    let lowered = check.lowered();
+    if update && lowered != ratchet {
+        write(&lowered);
+        eprintln!(
+            "Ratchet: lowered the budget in {} to {} finding(s).",
+            path.display(),
+            lowered.total
+        );
+    }
+
+    true
+}
+
+fn annotate_source(args: &Args, file: &Path) {
+    args.validate().unwrap_or_else(|e| exit_with_error(e));
+    if file.is_dir() {
+        exit_with_error(format!(
+            "--annotate-source prints a single file, but '{}' is a directory.",
+            file.display()
+        ));
+    }
+
+    let (python_version, _) = args
+        .python_version(file)
+        .unwrap_or_else(|e| exit_with_error(e));
+    let options = args.check_options(OutputFormat::Text, python_version);
+    let checked = match check_file(file, &options) {
+        FileOutcome::Checked(checked)
+        | FileOutcome::Minified {
+            checked: Some(checked),
+            ..
+        } => checked,
+        FileOutcome::Binary => exit_with_error(format!("'{}' is not a text file.", file.display())),
+        FileOutcome::Changed => {
+            exit_with_error(format!("'{}' changed while it was read.", file.display()))
+        }
+        FileOutcome::Minified { checked: None, .. } => exit_with_error(format!(
+            "'{}' has lines longer than --max-line-length and --skip-minified is given.",
+            file.display()
+        )),
+        FileOutcome::Stopped => unreachable!("--annotate-source runs have no deadline."),
+        FileOutcome::Unreadable(e) => {
+            exit_with_error(format!("could not read {}: {e}", file.display()))
+        }
+        FileOutcome::BrokenLink => {
+            exit_with_error(format!("'{}' is a broken symbolic link.", file.display()))
+        }
+        FileOutcome::NotUtf8(e) => exit_with_error(format!(
+            "'{}' is not valid UTF-8 ({e}), pass --lossy-utf8 to annotate it anyway.",
+            file.display()
+        )),
+    };
+
+    let source = std::fs::read(file)
+        .unwrap_or_else(|e| exit_with_error(format!("could not read {}: {e}", file.display())));
+    print!(
+        "{}",
+        annotate::annotate_source(&String::from_utf8_lossy(&source), &checked.positions)
+    );
+}
+
+fn list_files(args: &Args, why: bool) {
+    let filters = args.filters().unwrap_or_else(|e| exit_with_error(e));
+    let targets = args.targets().unwrap_or_else(|e| exit_with_error(e));
+    let terminator = if args.null { '\0' } else { '\n' };
+
+    let mut excluded = Vec::new();
+    let (mut files, _) = collect_files(&targets, &filters, &args.extensions, |entry, filter| {
+        excluded.push((entry.path().to_path_buf(), filter.reason(entry)))
+    });
+    files.sort();
+
+    for file in files {
+        print!("{}{terminator}", file.display());
+    }
+
+    if why {
+        excluded.sort();
+        for (path, reason) in excluded {
+            print!("{} [excluded: {reason}]{terminator}", path.display());
+        }
+    }
+}
+
+fn inventory(args: &Args) {
+    args.validate().unwrap_or_else(|e| exit_with_error(e));
+    let format = args.format.unwrap_or_default();
+    if !matches!(format, OutputFormat::Text | OutputFormat::Json) {
+        exit_with_error("inventory is only available with --format text or json.".to_string());
+    }
+
+    let filters = args.filters().unwrap_or_else(|e| exit_with_error(e));
+    let targets = args.targets().unwrap_or_else(|e| exit_with_error(e));
+    let (mut files, _) = collect_files(&targets, &filters, &args.extensions, |_, _| {});
+    files.sort();
+
+    let mut parser = create_python_parser();
+    let mut functions = Vec::new();
+    for file in files {
+        let contents = match read_unless_changed(&file) {
+            Ok(Some(contents)) => contents,
+            Ok(None) => {
+                eprintln!(
+                    "warning: skipping '{}' as it changed during the scan.",
+                    file.display()
+                );
+                continue;
+            }
+            Err(e) => {
+                eprintln!(
+                    "warning: skipping '{}' as it could not be read: {e}.",
+                    file.display()
+                );
+                continue;
+            }
+        };
+        if sniff_source(&contents, usize::MAX) == Some(Suspicious::Binary) {
+            warn_binary(&file);
+            continue;
+        }
+        if let Err(e) = std::str::from_utf8(&contents) {
+            eprintln!(
+                "warning: skipping '{}' as it is not valid UTF-8 ({e}).",
+                file.display()
+            );
+            continue;
+        }
+
+        let (tree, source_code) = get_tree_from_source(&mut parser, contents);
+        functions.extend(inventory::functions(
+            &display_path(&file, args.native_paths),
+            &source_code,
+            &tree,
+        ));
+    }
+
+    match format {
+        OutputFormat::Json => print!(
+            "{}",
+            json_report::render(&inventory::Inventory {
+                schema_version: inventory::SCHEMA_VERSION,
+                functions,
+            })
+        ),
+        _ => print!("{}", inventory::render_text(&functions)),
+    }
+}
+
+fn compare(
+    old: &Path,
+    new: &Path,
+    new_only: bool,
+    mut renames: Vec<compare::Rename>,
+    renames_from: Option<PathBuf>,
+    format: CompareFormat,
+) {
+    require_feature(cfg!(feature = "formats-json"), "formats-json", "compare")
+        .unwrap_or_else(|e| exit_with_error(e));
+
+    if let Some(renames_from) = renames_from {
+        let contents = if renames_from.as_os_str() == "-" {
+            let mut contents = String::new();
+            std::io::stdin()
+                .read_to_string(&mut contents)
+                .map(|_| contents)
+        } else {
+            std::fs::read_to_string(&renames_from)
+        }
+        .unwrap_or_else(|e| {
+            exit_with_error(format!("could not read {}: {e}", renames_from.display()))
+        });
+        renames.extend(compare::parse_name_status(&contents));
+    }
+
+    let old_report = compare::read_report(old).unwrap_or_else(|e| exit_with_error(e));
+    let new_report = compare::read_report(new).unwrap_or_else(|e| exit_with_error(e));
+    if old_report.options_fingerprint != new_report.options_fingerprint {
+        eprintln!(
+            "warning: the reports were written with different options, so some findings may differ because of them."
+        );
+    }
+
+    let mut comparison = compare::compare(&old_report, &new_report, &renames);
+    let summary = format!(
+        "{} new finding(s), {} fixed, {} unchanged.",
+        comparison.new.len(),
+        comparison.fixed.len(),
+        comparison.unchanged
+    );
+    if new_only {
+        comparison.fixed.clear();
+    }
+
+    match format {
+        CompareFormat::Text => {
+            print!("{}", compare::render_text(&comparison.new));
+            if !comparison.fixed.is_empty() {
+                println!("Fixed:");
+                print!("{}", compare::render_text(&comparison.fixed));
+            }
+        }
+        CompareFormat::Json => print!("{}", json_report::render(&comparison)),
+    }
+    eprintln!("{summary}");
+
+    std::process::exit(if comparison.new.is_empty() { 0 } else { 1 });
+}
+
+fn merge(reports: &[PathBuf], output: Option<&Path>, format: MergeFormat) {
+    require_feature(cfg!(feature = "formats-json"), "formats-json", "merge")
+        .unwrap_or_else(|e| exit_with_error(e));
+
+    let reports = merge::read_reports(reports).unwrap_or_else(|e| exit_with_error(e));
+    let merged = merge::merge(&reports).unwrap_or_else(|e| exit_with_error(e));
+    let rendered = match format {
+        MergeFormat::Json => json_report::render(&merged),
+        MergeFormat::Text => merge::render_text(&merged),
+    };
+
+    match output {
+        Some(output) => metrics::write_atomically(output, &rendered).unwrap_or_else(|e| {
+            exit_with_error(format!("could not write {}: {e}", output.display()))
+        }),
+        None => print!("{rendered}"),
+    }
+}
+
+/// Expands the targets into the files to check. Directories are walked, while files are
+/// checked as given, whatever their extension. A file reached through several targets is
+/// only kept the first time. Also returns the paths that do not exist or could not be
+/// walked along with the reason, warning about each.
+fn collect_files(
+    targets: &[PathBuf],
+    filters: &[Box<dyn Filter + Sync>],
+    extensions: &[String],
+    mut on_excluded: impl FnMut(&walkdir::DirEntry, &(dyn Filter + Sync)),
+) -> (Vec<PathBuf>, Vec<(PathBuf, String)>) {
+    let mut files = Vec::new();
+    let mut seen = HashSet::new();
+    let mut add = |file: PathBuf| {
+        if seen.insert(paths::normalized(&file)) {
+            files.push(file);
+        }
+    };
+    let mut errors = Vec::new();
+    for target in targets {
+        // A target missing before the walk is rather a typo than a file that changed
+        // during the scan. Broken symbolic links are reported when they are read.
+        if target.symlink_metadata().is_err() {
+            eprintln!(
+                "warning: skipping '{}' as it does not exist.",
+                target.display()
+            );
+            errors.push((target.clone(), "does not exist".to_string()));
+            continue;
+        }
+        if !target.is_dir() {
+            add(target.clone());
+            continue;
+        }
+
+        for entry in walk_python_files(target, filters, extensions, &mut on_excluded) {
+            match entry {
+                Ok(entry) => add(entry.into_path()),
+                Err(e) => {
+                    let path = e.path().unwrap_or(target).to_path_buf();
+                    let reason = match e.io_error() {
+                        Some(io_error) => io_error.to_string(),
+                        None => e.to_string(),
+                    };
+                    eprintln!(
+                        "warning: skipping '{}' as it could not be walked: {reason}.",
+                        path.display()
+                    );
+                    errors.push((path, reason));
+                }
+            }
+        }
+    }
+
+    (files, errors)
+}
+
+/// Walks the directory, yielding the Python files that pass all filters and the entries
+/// that could not be walked, such as unreadable directories, and reporting every pruned
+/// entry alongside the filter that excluded it.
+fn walk_python_files<'a>(
+    path: &Path,
+    filters: &'a [Box<dyn Filter + Sync>],
+    extensions: &'a [String],
+    mut on_excluded: impl FnMut(&walkdir::DirEntry, &(dyn Filter + Sync)) + 'a,
+) -> impl Iterator<Item = walkdir::Result<walkdir::DirEntry>> + 'a {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_entry(move |entry| match rejecting_filter(filters, entry) {
+            Some(filter) => {
+                on_excluded(entry, filter);
+                false
+            }
+            None => true,
+        })
+        .filter(|entry| {
+            entry.as_ref().map_or(true, |entry| {
+                !entry.file_type().is_dir() && is_python_file(entry.path(), extensions)
+            })
+        })
+}
+
+/// The single place deciding whether a walked file is checked, comparing its extension
+/// case-insensitively against the configured ones (`py` by default).
+fn is_python_file(path: &Path, extensions: &[String]) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| {
+            extensions.iter().any(|expected| {
+                expected
+                    .trim_start_matches('.')
+                    .eq_ignore_ascii_case(extension)
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// Checks the files, returning their rendered findings along with the summary of the
+/// run. See [`stream_files`].
+fn check_files(files: Vec<PathBuf>, options: &CheckOptions) -> (String, Summary) {
+    let mut message = String::new();
+    let summary = stream_files(files, options, |block| message += block);
+
+    (message, summary)
+}
+
+/// Checks the files in parallel, handing the rendering of each file with findings to
+/// `write` as soon as the file is done, or once every file is done, in alphabetical
+/// order, with --sorted. Past the deadline, if any, or once interrupted, the remaining
+/// files are counted as unchecked instead.
+fn stream_files(
+    files: Vec<PathBuf>,
+    options: &CheckOptions,
+    mut write: impl FnMut(&str),
+) -> Summary {
+    let total = files.len();
+    let progress = Progress::start(options.progress, total);
+
+    let (sender, reports) = mpsc::channel();
+    {
+        let options = options.clone();
+        std::thread::spawn(move || {
+            files.into_par_iter().for_each_with(sender, |sender, file| {
+                if options.interrupts.interrupted()
+                    || options.deadline.is_some_and(|deadline| deadline.expired())
+                {
+                    return;
+                }
+
+                let outcome = check_file(&file, &options);
+                let _ = sender.send(FileReport {
+                    path: file,
+                    outcome,
+                });
+            })
+        });
+    }
+
+    // Only this thread records and renders, so the files' blocks never interleave.
+    let mut summary = Summary::default();
+    let mut finished = 0;
+    let mut sorted = Vec::new();
+    let mut separator = "";
+    let mut write_block = |path: &Path, positions: &[Position]| {
+        write(&(separator.to_string() + &render_file(path, positions, options)));
+        separator = report::block_separator(options);
+    };
+    loop {
+        // Files being checked when the deadline passed, such as when reading one hangs,
+        // should not keep the others from being rendered.
+        let report = match &options.deadline {
+            Some(deadline) => deadline.receive(&reports),
+            None => reports.recv().ok(),
+        };
+        let Some(FileReport { path, outcome }) = report else {
+            break;
+        };
+        if matches!(outcome, FileOutcome::Stopped) {
+            continue;
+        }
+        finished += 1;
+
+        let positions = summary.record(&path, outcome, options);
+        progress.file_done(
+            &display_path(&path, options.native_paths),
+            positions.as_ref().map_or(0, |positions| positions.len()),
+        );
+
+        match positions {
+            Some(positions) if !positions.is_empty() => {
+                if options.sorted {
+                    sorted.push((path, positions));
+                } else {
+                    write_block(&path, &positions);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    progress.finish();
+
+    sorted.sort_by(|(a, _), (b, _)| a.cmp(b));
+    for (path, positions) in sorted {
+        write_block(&path, &positions);
+    }
+    summary.unchecked = total - finished;
+
+    summary
+}
+
+/// Outcome of a file, sent by the workers checking it to the thread rendering it.
+struct FileReport {
+    path: PathBuf,
+    outcome: FileOutcome,
+}
+
+/// Options affecting how each individual file is checked.
+#[derive(Clone)]
+struct CheckOptions {
+    rules: Rules,
+    max_line_length: usize,
+    skip_minified: bool,
+    extensions: Vec<String>,
+    native_paths: bool,
+    aggregate: bool,
+    /// Lists text findings under a header for each file, rather than one per line.
+    group_by_file: bool,
+    /// Spaces before each finding listed under a file.
+    indent: usize,
+    /// Renders the files in alphabetical order once they are all checked, rather than
+    /// as soon as each is done.
+    sorted: bool,
+    /// Terminates --aggregate lines with NUL instead of a newline.
+    null: bool,
+    /// Source roots that module names are shown relative to, when --module-names is
+    /// given.
+    module_names: Option<Vec<PathBuf>>,
+    /// Leaves out findings about functions that the adjacent stub annotates.
+    prefer_stubs: bool,
+    /// Checks the functions defined in docstring examples too.
+    check_doctests: bool,
+    /// Replaces invalid UTF-8 instead of skipping the file.
+    lossy_utf8: bool,
+    /// Files checked with strict rules as they belong to a typed package, when
+    /// --strict-typed-packages is given.
+    typed_files: Option<HashSet<PathBuf>>,
+    /// Turns locations into links in formats that can show them.
+    links: Option<links::Links>,
+    /// When to stop checking files, with --timeout.
+    deadline: Option<deadline::Deadline>,
+    /// Ctrl-C presses, after the first of which no more files are checked.
+    interrupts: &'static interrupt::Interrupts,
+    progress: ProgressMode,
+    format: OutputFormat,
+}
+
+/// Result of sniffing and checking a single file.
+#[derive(Debug)]
+enum FileOutcome {
+    Checked(CheckedFile),
+    Binary,
+    /// Removed or resized between the walk and the read, even after a retry.
+    Changed,
+    Minified {
+        longest_line: usize,
+        checked: Option<CheckedFile>,
+    },
+    /// Not parsed before the --timeout deadline.
+    Stopped,
+    /// Could not be read, for the given reason.
+    Unreadable(String),
+    /// A symbolic link to a file that does not exist.
+    BrokenLink,
+    /// Not valid UTF-8, without --lossy-utf8.
+    NotUtf8(std::str::Utf8Error),
+}
+
+/// Findings of a checked file along with its annotation counts.
+#[derive(Debug)]
+struct CheckedFile {
+    positions: Vec<Position>,
+    counts: AnnotationCounts,
+    /// Whether the file belongs to a typed package, when --strict-typed-packages is given.
+    typed_package: Option<bool>,
+}
+
+/// Tallies of a single checked file.
+#[derive(Debug, Clone)]
+struct FileStats {
+    path: PathBuf,
+    missing_parameters: usize,
+    missing_returns: usize,
+    findings: usize,
+    counts: AnnotationCounts,
+    typed_package: Option<bool>,
+}
+
+impl FileStats {
+    fn new(path: &Path, checked: &CheckedFile) -> Self {
+        let count = |predicate: fn(&MissingType) -> bool| {
+            checked
+                .positions
+                .iter()
+                .filter(|position| predicate(&position.missing_type))
+                .count()
+        };
+
+        FileStats {
+            path: path.to_path_buf(),
+            missing_parameters: count(|kind| matches!(kind, MissingType::Parameter(_))),
+            missing_returns: count(|kind| matches!(kind, MissingType::Return(_))),
+            findings: checked.positions.len(),
+            counts: checked.counts,
+            typed_package: checked.typed_package,
+        }
+    }
+}
+
+/// Counts of files seen during the run, printed to stderr at the end.
+#[derive(Debug, Default)]
+struct Summary {
+    files_checked: usize,
+    findings: usize,
+    files: Vec<FileStats>,
+    binary: Vec<PathBuf>,
+    minified: Vec<PathBuf>,
+    changed: Vec<PathBuf>,
+    /// Paths that could not be walked or read, along with the reason.
+    could_not_check: Vec<(PathBuf, String)>,
+    /// Files that do not parse, each with a syntax error finding.
+    syntax_errors: Vec<PathBuf>,
+    /// Files left unchecked when the --timeout deadline passed or the run was interrupted.
+    unchecked: usize,
+}
+
+impl Summary {
+    /// Registers the outcome of a file, printing warnings for unusual files, and
+    /// returns its findings if it was checked.
+    fn record(
+        &mut self,
+        path: &Path,
+        outcome: FileOutcome,
+        options: &CheckOptions,
+    ) -> Option<Vec<Position>> {
+        match outcome {
+            FileOutcome::Checked(checked) => Some(self.record_checked(path, checked)),
+            FileOutcome::Binary => {
+                warn_binary(path);
+                self.binary.push(path.to_path_buf());
+                None
+            }
+            FileOutcome::Changed => {
+                eprintln!(
+                    "warning: skipping '{}' as it changed during the scan.",
+                    path.display()
+                );
+                self.changed.push(path.to_path_buf());
+                None
+            }
+            FileOutcome::Minified {
+                longest_line,
+                checked,
+            } => {
+                let action = if checked.is_some() {
+                    "checking anyway"
+                } else {
+                    "skipping"
+                };
+                eprintln!(
+                    "warning: '{}' has a line of {longest_line} characters (limit is {}), it is likely minified; {action}.",
+                    path.display(),
+                    options.max_line_length,
+                );
+                self.minified.push(path.to_path_buf());
+                checked.map(|checked| self.record_checked(path, checked))
+            }
+            FileOutcome::Stopped => None,
+            FileOutcome::Unreadable(e) => {
+                eprintln!(
+                    "warning: skipping '{}' as it could not be read: {e}.",
+                    path.display()
+                );
+                self.could_not_check.push((path.to_path_buf(), e));
+                None
+            }
+            FileOutcome::BrokenLink => {
+                eprintln!(
+                    "warning: skipping '{}' as it is a broken symbolic link.",
+                    path.display()
+                );
+                self.could_not_check
+                    .push((path.to_path_buf(), "broken symbolic link".to_string()));
+                None
+            }
+            FileOutcome::NotUtf8(e) => {
+                eprintln!(
+                    "warning: skipping '{}' as it is not valid UTF-8 ({e}), pass --lossy-utf8 to check it anyway.",
+                    path.display()
+                );
+                self.could_not_check
+                    .push((path.to_path_buf(), format!("not valid UTF-8 ({e})")));
+                None
+            }
+        }
+    }
+
+    fn record_checked(&mut self, path: &Path, checked: CheckedFile) -> Vec<Position> {
+        self.files_checked += 1;
+        self.findings += checked.positions.len();
+        self.files.push(FileStats::new(path, &checked));
+        if checked
+            .positions
+            .iter()
+            .any(|position| matches!(position.missing_type, MissingType::SyntaxError { .. }))
+        {
+            self.syntax_errors.push(path.to_path_buf());
+        }
+
+        checked.positions
+    }
+
+    fn functions(&self) -> usize {
+        self.files.iter().map(|file| file.counts.functions).sum()
+    }
+
+    fn files_with_findings(&self) -> usize {
+        self.files.iter().filter(|file| file.findings > 0).count()
+    }
+
+    fn parameter_findings(&self) -> usize {
+        self.files.iter().map(|file| file.missing_parameters).sum()
+    }
+
+    fn return_findings(&self) -> usize {
+        self.files.iter().map(|file| file.missing_returns).sum()
+    }
+
+    /// Share of checked parameters and return types that are annotated, or `None` when
+    /// there was nothing to annotate.
+    fn coverage(&self) -> Option<f64> {
+        coverage(self.files.iter())
+    }
+
+    /// Files and findings inside and outside of typed packages, when
+    /// --strict-typed-packages is given.
+    fn typed_package_split(&self) -> Option<String> {
+        if self.files.iter().all(|file| file.typed_package.is_none()) {
+            return None;
+        }
+
+        let tally = |typed: bool| {
+            self.files
+                .iter()
+                .filter(|file| file.typed_package == Some(typed))
+                .fold((0, 0), |(files, findings), file| {
+                    (files + 1, findings + file.findings)
+                })
+        };
+        let (typed_files, typed_findings) = tally(true);
+        let (untyped_files, untyped_findings) = tally(false);
+
+        Some(format!(
+            "Typed packages: {typed_files} file(s) with {typed_findings} finding(s); elsewhere: {untyped_files} file(s) with {untyped_findings} finding(s).\n"
+        ))
+    }
+
+    /// Tallies the functions and findings of the checked files, shown after the findings.
+    fn render_totals(&self) -> String {
+        format!(
+            "Found {} missing return type(s) and {} missing parameter hint(s) in {} of {} file(s), across {} function(s).\n",
+            self.return_findings(),
+            self.parameter_findings(),
+            self.files_with_findings(),
+            self.files_checked,
+            self.functions()
+        )
+    }
+
+    fn render(&self, verbose: bool) -> String {
+        let mut rendered = self.typed_package_split().unwrap_or_default();
+
+        if !verbose
+            && self.binary.is_empty()
+            && self.minified.is_empty()
+            && self.changed.is_empty()
+            && self.could_not_check.is_empty()
+            && self.syntax_errors.is_empty()
+        {
+            return rendered;
+        }
+
+        rendered += &format!(
+            "Checked {} file(s); {} binary file(s) skipped; {} likely minified file(s)",
+            self.files_checked,
+            self.binary.len(),
+            self.minified.len()
+        );
+        if !self.changed.is_empty() {
+            rendered += &format!(
+                "; {} file(s) skipped as they changed during the scan",
+                self.changed.len()
+            );
+        }
+        if !self.could_not_check.is_empty() {
+            rendered += &format!(
+                "; {} path(s) could not be checked",
+                self.could_not_check.len()
+            );
+        }
+        if !self.syntax_errors.is_empty() {
+            rendered += &format!(
+                "; {} file(s) with syntax errors, only checked outside of them",
+                self.syntax_errors.len()
+            );
+        }
+        rendered += ".\n";
+
+        if verbose {
+            let mut binary = self.binary.clone();
+            binary.sort();
+            for path in binary {
+                rendered += &format!("    binary: {}\n", path.display());
+            }
+
+            let mut minified = self.minified.clone();
+            minified.sort();
+            for path in minified {
+                rendered += &format!("    minified: {}\n", path.display());
+            }
+
+            let mut changed = self.changed.clone();
+            changed.sort();
+            for path in changed {
+                rendered += &format!("    changed: {}\n", path.display());
+            }
+        }
+
+        // Listed whether verbose or not, as they make the run fail.
+        if !self.could_not_check.is_empty() {
+            let mut could_not_check = self.could_not_check.clone();
+            could_not_check.sort();
+            rendered += &format!("Could not check {} path(s):\n", could_not_check.len());
+            for (path, reason) in could_not_check {
+                rendered += &format!("    {}: {reason}\n", path.display());
+            }
+        }
+
+        rendered
+    }
+}
+
+/// Kinds of files that are named `.py` but are unlikely to be hand-written Python.
+#[derive(Debug, PartialEq)]
+enum Suspicious {
+    Binary,
+    Minified { longest_line: usize },
+}
+
+fn coverage<'a>(files: impl Iterator<Item = &'a FileStats>) -> Option<f64> {
+    let (slots, findings) = files.fold((0, 0), |(slots, findings), file| {
+        (
+            slots + file.counts.slots,
+            findings + file.missing_parameters + file.missing_returns,
+        )
+    });
+
+    (slots > 0).then(|| (slots - findings) as f64 / slots as f64)
+}
+
+/// Warns that a file is skipped for containing NUL bytes, the same way for every command.
+fn warn_binary(path: &Path) {
+    eprintln!(
+        "warning: skipping '{}' as it looks like a binary file.",
+        path.display()
+    );
+}
+
+/// Detects binary (containing NUL bytes) and minified (overly long lines) sources.
+fn sniff_source(contents: &[u8], max_line_length: usize) -> Option<Suspicious> {
+    if contents.contains(&0) {
+        return Some(Suspicious::Binary);
+    }
+
+    // Counts characters rather than bytes, by leaving out UTF-8 continuation bytes.
+    let longest_line = contents
+        .split(|&byte| byte == b'\n')
+        .map(|line| line.iter().filter(|&&byte| byte & 0xC0 != 0x80).count())
+        .max()
+        .unwrap_or(0);
+
+    if longest_line > max_line_length {
+        return Some(Suspicious::Minified { longest_line });
+    }
+
+    None
+}
+
+/// Whether the path is a symbolic link whose target does not exist.
+fn is_broken_link(path: &Path) -> bool {
+    path.is_symlink() && !path.exists()
+}
+
+/// Reads a file that another process may be writing or removing during the scan,
+/// returning `None` when it is gone or its size changed while it was read.
+fn read_unless_changed(file: &Path) -> std::io::Result<Option<Vec<u8>>> {
+    let openable = openable_path(file);
+    let not_found = |e: &std::io::Error| e.kind() == std::io::ErrorKind::NotFound;
+
+    let expected_size = match std::fs::metadata(&openable) {
+        Ok(metadata) => metadata.len(),
+        Err(e) if not_found(&e) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let contents = match std::fs::read(&openable) {
+        Ok(contents) => contents,
+        Err(e) if not_found(&e) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    Ok((contents.len() as u64 == expected_size).then_some(contents))
+}
+
+fn check_file(file: &Path, options: &CheckOptions) -> FileOutcome {
+    let mut parser = create_python_parser();
+
+    let path = file.to_string_lossy();
+    let contents = match read_unless_changed(file) {
+        Ok(None) => read_unless_changed(file),
+        read => read,
+    };
+    let contents = match contents {
+        Ok(Some(contents)) => contents,
+        Ok(None) if is_broken_link(file) => return FileOutcome::BrokenLink,
+        Ok(None) => return FileOutcome::Changed,
+        Err(e) => return FileOutcome::Unreadable(e.to_string()),
+    };
+
+    let minified = match sniff_source(&contents, options.max_line_length) {
+        Some(Suspicious::Binary) => return FileOutcome::Binary,
+        Some(Suspicious::Minified { longest_line }) if options.skip_minified => {
+            return FileOutcome::Minified {
+                longest_line,
+                checked: None,
+            }
+        }
+        Some(Suspicious::Minified { longest_line }) => Some(longest_line),
+        None => None,
+    };
+
+    let contents = match std::str::from_utf8(&contents) {
+        Ok(_) => contents,
+        Err(_) if options.lossy_utf8 => {
+            eprintln!("note: decoding '{path}' lossily, as it is not valid UTF-8.");
+            String::from_utf8_lossy(&contents).into_owned().into_bytes()
+        }
+        Err(e) => return FileOutcome::NotUtf8(e),
+    };
+
+    if let Some(format) = docs::DocsFormat::of(file) {
+        let text = String::from_utf8_lossy(&contents);
+        let findings = snippets::check_snippets(format.code_blocks(&text), &options.rules);
+        for line in findings.unparsable {
+            eprintln!("warning: skipping the code block at {path}:{line}, as it does not parse.");
+        }
+
+        return FileOutcome::Checked(CheckedFile {
+            positions: findings.positions,
+            counts: findings.counts,
+            typed_package: None,
+        });
+    }
+
+    let typed_package = options
+        .typed_files
+        .as_ref()
+        .map(|typed_files| typed_files.contains(file));
+    let strict_rules;
+    let rules = if typed_package == Some(true) {
+        strict_rules = options.rules.strict();
+        &strict_rules
+    } else {
+        &options.rules
+    };
+
+    let (tree, source_code) = match options.deadline {
+        Some(deadline) => {
+            // A timeout of 0 would mean none at all.
+            parser.set_timeout_micros((deadline.remaining().as_micros() as u64).max(1));
+            match parser.parse(&contents, None) {
+                Some(tree) => (tree, contents),
+                None => return FileOutcome::Stopped,
+            }
+        }
+        None => get_tree_from_source(&mut parser, contents),
+    };
+    // The doctests are checked apart from the file, so the comment suppressing the whole
+    // file has to be honored for them as well.
+    let ignores_file = !rules.no_inline_ignores && inline_ignores::ignores_file(&source_code);
+    let doctests = (options.check_doctests && !ignores_file)
+        .then(|| doctests::check_doctests(&tree, &source_code, rules));
+    let (mut positions, mut counts) = find_missing_types_positions(&source_code, tree, rules);
+
+    if options.prefer_stubs {
+        if let Some(stub) = stubs::adjacent_stub(file) {
+            let annotated = stubs::annotated_functions(&stub, rules);
+            positions.retain(|position| {
+                position
+                    .function
+                    .as_ref()
+                    .is_none_or(|function| !annotated.contains(&function.qualified_name))
+            });
+        }
+    }
+
+    if let Some(doctests) = doctests {
+        for line in doctests.unparsable {
+            eprintln!("warning: skipping the doctest at {path}:{line}, as it does not parse.");
+        }
+        positions.extend(doctests.positions.into_iter().map(|mut position| {
+            if let Some(function) = &mut position.function {
+                function.group_id += counts.functions;
+            }
+            position
+        }));
+        counts.functions += doctests.counts.functions;
+        counts.slots += doctests.counts.slots;
+    }
+
+    let checked = CheckedFile {
+        positions,
+        counts,
+        typed_package,
+    };
+
+    match minified {
+        Some(longest_line) => FileOutcome::Minified {
+            longest_line,
+            checked: Some(checked),
+        },
+        None => FileOutcome::Checked(checked),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    #[test]
+    fn tree_from_test_file() {
+        let mut parser = create_python_parser();
+        get_tree_from_file(&mut parser, "test_file.py").unwrap();
+    }
+
+    #[test]
+    fn find_args_test() {
+        let mut parser = create_python_parser();
+        let (tree, source_code) = get_tree_from_file(&mut parser, "test_file.py").unwrap();
+        println!(
             "{:?}",
-            find_missing_types_positions(&source_code, tree, false)
+            find_missing_types_positions(&source_code, tree, &Rules::default())
+        );
+    }
+
+    #[test]
+    fn parameter_kinds_test() {
+        let mut parser = create_python_parser();
+        let (tree, source_code) = get_tree_from_source(
+            &mut parser,
+            b"class A:\n    def f(self, a, /, b, *, c: int, **kwargs) -> None: ...\n\n\
+              def g(*args: int, **kwargs: str) -> None: ...\n\n\
+              def h(*args, d) -> None: ...\n"
+                .to_vec(),
+        );
+        let (positions, counts) =
+            find_missing_types_positions(&source_code, tree, &Rules::default());
+
+        let parameters: Vec<&MissingType> = positions
+            .iter()
+            .map(|position| &position.missing_type)
+            .collect();
+        assert_eq!(
+            parameters,
+            ["a", "b", "**kwargs", "*args", "d"]
+                .map(|name| MissingType::Parameter(name.to_string()))
+                .iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            counts,
+            AnnotationCounts {
+                functions: 3,
+                slots: 11
+            }
+        );
+    }
+
+    #[test]
+    fn syntax_error_test() {
+        let mut parser = create_python_parser();
+        let (tree, source_code) = get_tree_from_source(
+            &mut parser,
+            b"def fine(x) -> int:\n    return 1\n\n\
+              def broken(y):\n    return y +\n"
+                .to_vec(),
+        );
+        let (positions, _) = find_missing_types_positions(&source_code, tree, &Rules::default());
+
+        let findings: Vec<(&MissingType, usize)> = positions
+            .iter()
+            .map(|position| (&position.missing_type, position.start.row + 1))
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                (
+                    &MissingType::SyntaxError {
+                        text: "return y +".to_string(),
+                        missing: false
+                    },
+                    5
+                ),
+                (&MissingType::Parameter("x".to_string()), 1),
+            ]
+        );
+        assert_eq!(
+            report::get_finding_message(&positions[0]),
+            "Syntax error, could not parse 'return y +'."
+        );
+
+        let dir = fixture_dir();
+        std::fs::write(dir.path().join("broken.py"), &source_code).unwrap();
+        std::fs::write(dir.path().join("fine.py"), "def f() -> None: ...\n").unwrap();
+        let (_, summary) = check_directory(dir.path(), &[], &default_options());
+        assert_eq!(summary.syntax_errors, [dir.path().join("broken.py")]);
+        assert!(summary
+            .render(false)
+            .contains("; 1 file(s) with syntax errors, only checked outside of them."));
+    }
+
+    #[test]
+    fn function_shapes_test() {
+        let mut parser = create_python_parser();
+        let (tree, source_code) = get_tree_from_source(
+            &mut parser,
+            b"async def fetch(url):\n    pass\n\n\
+              @app.route(\"/\")\n@cache\nasync def index(request) -> str:\n\
+              \x20   def helper(x) -> int:\n        async def deeper(y: int): ...\n\
+              \x20       return 0\n    return \"\"\n"
+                .to_vec(),
+        );
+        let (positions, _) = find_missing_types_positions(&source_code, tree, &Rules::default());
+
+        let findings: Vec<(&str, &MissingType, usize)> = positions
+            .iter()
+            .map(|position| {
+                (
+                    position.function.as_ref().unwrap().name.as_str(),
+                    &position.missing_type,
+                    position.start.row + 1,
+                )
+            })
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                ("fetch", &MissingType::Parameter("url".to_string()), 1),
+                ("fetch", &MissingType::Return("fetch".to_string()), 1),
+                ("index", &MissingType::Parameter("request".to_string()), 6),
+                ("helper", &MissingType::Parameter("x".to_string()), 7),
+                ("deeper", &MissingType::Return("deeper".to_string()), 8),
+            ]
+        );
+    }
+
+    #[test]
+    fn dunder_returns_test() {
+        let source =
+            b"class Case:\n    def __init__(self, x): ...\n\n    def __exit__(self, *exc): ...\n\n\
+                       \x20   def setUp(self): ...\n\n    def build(self): ...\n";
+        let flagged = |arguments: &[&str]| {
+            let args = Cli::try_parse_from(arguments).unwrap().args;
+            let rules = Rules {
+                ignored_names: args.ignored_names(),
+                ..Default::default()
+            };
+            let mut parser = create_python_parser();
+            let (tree, source_code) = get_tree_from_source(&mut parser, source.to_vec());
+            let (positions, _) = find_missing_types_positions(&source_code, tree, &rules);
+            positions
+                .into_iter()
+                .map(|position| position.missing_type)
+                .collect::<Vec<_>>()
+        };
+        let parameter = |name: &str| MissingType::Parameter(name.to_string());
+        let missing_return = |name: &str| MissingType::Return(name.to_string());
+
+        assert_eq!(
+            flagged(&["pythcheck", "src", "--ignore-return-for", "setUp"]),
+            [parameter("x"), parameter("*exc"), missing_return("build")]
+        );
+        assert_eq!(
+            flagged(&["pythcheck", "src", "--require-init-return"]),
+            [
+                parameter("x"),
+                missing_return("__init__"),
+                parameter("*exc"),
+                missing_return("__exit__"),
+                missing_return("setUp"),
+                missing_return("build"),
+            ]
+        );
+    }
+
+    #[test]
+    fn sniff_test() {
+        assert_eq!(
+            sniff_source(b"def f(x: int) -> int:\n    return x\n", 80),
+            None
+        );
+        assert_eq!(
+            sniff_source(b"\x7fELF\x00\x01", 80),
+            Some(Suspicious::Binary)
+        );
+        assert_eq!(
+            sniff_source(format!("x = [{}]\n", "1, ".repeat(100)).as_bytes(), 80),
+            Some(Suspicious::Minified { longest_line: 306 })
+        );
+        assert_eq!(
+            sniff_source(format!("s = \"{}\"\n", "é".repeat(50)).as_bytes(), 60),
+            None
+        );
+        assert_eq!(
+            sniff_source(format!("s = \"{}\"\n", "é".repeat(80)).as_bytes(), 60),
+            Some(Suspicious::Minified { longest_line: 86 })
+        );
+    }
+
+    #[test]
+    fn timeout_test() {
+        let dir = fixture_dir();
+        let files: Vec<PathBuf> = ["a.py", "b.py"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+        for file in &files {
+            std::fs::write(file, "def f(x): ...").unwrap();
+        }
+
+        let started = std::time::Instant::now() - std::time::Duration::from_secs(2);
+        let options = CheckOptions {
+            deadline: Some(deadline::Deadline::new(started, "1s".parse().unwrap())),
+            ..default_options()
+        };
+        let (message, summary) = check_files(files.clone(), &options);
+        assert!(message.is_empty());
+        assert_eq!((summary.files_checked, summary.unchecked), (0, 2));
+
+        let options = CheckOptions {
+            deadline: Some(deadline::Deadline::new(started, "1h".parse().unwrap())),
+            ..default_options()
+        };
+        let (_, summary) = check_files(files, &options);
+        assert_eq!((summary.files_checked, summary.unchecked), (2, 0));
+    }
+
+    #[test]
+    fn interrupted_test() {
+        static INTERRUPTS: interrupt::Interrupts = interrupt::Interrupts::new();
+        let dir = fixture_dir();
+        let files: Vec<PathBuf> = ["a.py", "b.py"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+        for file in &files {
+            std::fs::write(file, "def f(x): ...").unwrap();
+        }
+        let options = CheckOptions {
+            interrupts: &INTERRUPTS,
+            ..default_options()
+        };
+
+        let (_, summary) = check_files(files.clone(), &options);
+        assert_eq!((summary.files_checked, summary.unchecked), (2, 0));
+
+        assert!(!INTERRUPTS.press());
+        let (message, summary) = check_files(files, &options);
+        assert!(message.is_empty());
+        assert_eq!((summary.files_checked, summary.unchecked), (0, 2));
+        assert!(INTERRUPTS.press());
+    }
+
+    pub(crate) fn default_options() -> CheckOptions {
+        CheckOptions {
+            rules: Rules::default(),
+            max_line_length: 1000,
+            skip_minified: false,
+            extensions: vec!["py".to_string()],
+            native_paths: false,
+            aggregate: false,
+            group_by_file: true,
+            indent: 4,
+            sorted: false,
+            null: false,
+            module_names: None,
+            prefer_stubs: false,
+            check_doctests: false,
+            lossy_utf8: false,
+            typed_files: None,
+            links: None,
+            deadline: None,
+            interrupts: &interrupt::INTERRUPTS,
+            progress: ProgressMode::None,
+            format: OutputFormat::Text,
+        }
+    }
+
+    pub(crate) fn check_directory(
+        path: &Path,
+        filters: &[Box<dyn Filter + Sync>],
+        options: &CheckOptions,
+    ) -> (String, Summary) {
+        check_files(
+            collect_files(
+                &[path.to_path_buf()],
+                filters,
+                &options.extensions,
+                |_, _| {},
+            )
+            .0,
+            options,
+        )
+    }
+
+    pub(crate) fn fixture_dir() -> tempfile::TempDir {
+        tempfile::Builder::new()
+            .prefix("fixture")
+            .tempdir()
+            .unwrap()
+    }
+
+    #[test]
+    fn empty_directory_test() {
+        let dir = fixture_dir();
+        std::fs::write(dir.path().join("README.md"), "# Not Python").unwrap();
+
+        let (message, summary) = check_directory(dir.path(), &[], &default_options());
+        assert!(message.is_empty());
+        assert_eq!(summary.files_checked, 0);
+
+        std::fs::write(dir.path().join("module.py"), "def f(x: int) -> int: ...").unwrap();
+
+        let (_, summary) = check_directory(dir.path(), &[], &default_options());
+        assert_eq!(summary.files_checked, 1);
+    }
+
+    #[test]
+    fn ignored_file_doctests_test() {
+        let dir = fixture_dir();
+        let source = "\"\"\"Examples.\n\n>>> def f(x): ...\n\"\"\"\n";
+        std::fs::write(dir.path().join("module.py"), source).unwrap();
+
+        let options = CheckOptions {
+            check_doctests: true,
+            ..default_options()
+        };
+        let (_, summary) = check_directory(dir.path(), &[], &options);
+        assert_eq!(summary.findings, 2);
+
+        std::fs::write(
+            dir.path().join("module.py"),
+            format!("# hints: ignore\n{source}"),
+        )
+        .unwrap();
+        let (message, summary) = check_directory(dir.path(), &[], &options);
+        assert_eq!(summary.findings, 0, "{message}");
+    }
+
+    #[test]
+    fn aggregate_counts_findings_test() {
+        let dir = fixture_dir();
+        std::fs::write(
+            dir.path().join("module.py"),
+            "def f(a, b, c, d):\n    pass\n",
+        )
+        .unwrap();
+
+        let options = CheckOptions {
+            aggregate: true,
+            ..default_options()
+        };
+        let (message, summary) = check_directory(dir.path(), &[], &options);
+        assert_eq!(message.lines().count(), 1);
+        assert_eq!(summary.findings, 5);
+    }
+
+    #[test]
+    fn stream_files_test() {
+        let dir = fixture_dir();
+        let files: Vec<PathBuf> = ["c.py", "a.py", "clean.py", "b.py"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+        for file in &files {
+            let source = if file.ends_with("clean.py") {
+                "def f() -> None: ...\n"
+            } else {
+                "def f(x):\n    pass\n\ndef g(y) -> None: ...\n"
+            };
+            std::fs::write(file, source).unwrap();
+        }
+
+        let options = CheckOptions {
+            sorted: true,
+            ..default_options()
+        };
+        let mut writes = Vec::new();
+        let summary = stream_files(files, &options, |block| writes.push(block.to_string()));
+        assert_eq!(
+            summary.render_totals(),
+            "Found 3 missing return type(s) and 6 missing parameter hint(s) in 3 of 4 file(s), across 7 function(s).\n"
+        );
+
+        // Each file is written in one go, after the separator from the previous one.
+        let headers: Vec<&str> = writes
+            .iter()
+            .map(|block| {
+                block
+                    .trim_start()
+                    .lines()
+                    .next()
+                    .unwrap()
+                    .rsplit('/')
+                    .next()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(headers, ["a.py", "b.py", "c.py"]);
+        assert!(writes[1..]
+            .iter()
+            .all(|block| block.starts_with("\nFile: ")));
+        assert!(
+            writes.iter().all(|block| block.contains("'x'")
+                && block
+                    .ends_with("Parameter 'y' in line 4 and column 7 is missing a type hint.\n")),
+            "{writes:?}"
+        );
+    }
+
+    #[test]
+    fn could_not_check_test() {
+        let dir = fixture_dir();
+        let unreadable = dir.path().join("package.py");
+        std::fs::create_dir(&unreadable).unwrap();
+        let latin1 = dir.path().join("legacy.py");
+        std::fs::write(&latin1, b"# caf\xe9\ndef f(x): ...\n").unwrap();
+        std::fs::write(dir.path().join("ok.py"), "def g() -> None: ...\n").unwrap();
+        let files = vec![unreadable.clone(), latin1.clone(), dir.path().join("ok.py")];
+
+        let (_, summary) = check_files(files.clone(), &default_options());
+        assert_eq!(summary.files_checked, 1);
+        let paths: Vec<&PathBuf> = summary
+            .could_not_check
+            .iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(paths.len(), 2);
+        assert!(paths.contains(&&unreadable) && paths.contains(&&latin1));
+        let rendered = summary.render(false);
+        assert!(
+            rendered.contains("; 2 path(s) could not be checked."),
+            "{rendered}"
+        );
+        assert!(rendered.contains(&format!(
+            "Could not check 2 path(s):\n    {}: not valid UTF-8 (invalid utf-8 sequence of 1 bytes from index 5)\n",
+            latin1.display()
+        )));
+
+        let options = CheckOptions {
+            lossy_utf8: true,
+            ..default_options()
+        };
+        let (_, summary) = check_files(files, &options);
+        assert_eq!((summary.files_checked, summary.findings), (2, 2));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn broken_link_test() {
+        let dir = fixture_dir();
+        std::os::unix::fs::symlink(dir.path().join("gone.py"), dir.path().join("link.py")).unwrap();
+
+        let (files, walk_errors) = collect_files(
+            &[dir.path().to_path_buf()],
+            &[],
+            &["py".to_string()],
+            |_, _| {},
+        );
+        assert!(walk_errors.is_empty());
+        let (_, summary) = check_files(files, &default_options());
+        assert_eq!(
+            summary.could_not_check,
+            [(
+                dir.path().join("link.py"),
+                "broken symbolic link".to_string()
+            )]
+        );
+        assert!(summary.changed.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn null_separated_round_trip_test() {
+        let dir = fixture_dir();
+        let weird = dir.path().join("we\nird.py");
+        std::fs::write(&weird, "def f(a):\n    pass\n").unwrap();
+        std::fs::write(dir.path().join("ok.py"), "def g() -> None: ...\n").unwrap();
+
+        let mut list = Vec::new();
+        for file in [&weird, &dir.path().join("ok.py")] {
+            list.extend_from_slice(file.to_str().unwrap().as_bytes());
+            list.push(b'\0');
+        }
+        let files = paths::read_path_list(&list, true).unwrap();
+        assert_eq!(files[0], weird);
+
+        let options = CheckOptions {
+            aggregate: true,
+            null: true,
+            ..default_options()
+        };
+        let (message, summary) = check_files(files, &options);
+        assert_eq!(summary.files_checked, 2);
+
+        let records: Vec<&str> = message.split_terminator('\0').collect();
+        assert_eq!(
+            records,
+            [format!(
+                "{}:1: function 'f' missing hints: parameter a; return type",
+                weird.display()
+            )]
+        );
+    }
+
+    #[test]
+    fn changed_during_scan_test() {
+        let dir = fixture_dir();
+        std::fs::write(dir.path().join("kept.py"), "def f(x): ...\n").unwrap();
+        std::fs::write(dir.path().join("generated.py"), "def g(x): ...\n").unwrap();
+
+        let (files, _) = collect_files(
+            &[dir.path().to_path_buf()],
+            &[],
+            &["py".to_string()],
+            |_, _| {},
+        );
+        assert_eq!(files.len(), 2);
+        std::fs::remove_file(dir.path().join("generated.py")).unwrap();
+
+        let (_, summary) = check_files(files, &default_options());
+        assert_eq!(summary.files_checked, 1);
+        assert_eq!(summary.changed, [dir.path().join("generated.py")]);
+        assert_eq!(
+            summary.render(false),
+            "Checked 1 file(s); 0 binary file(s) skipped; 0 likely minified file(s); 1 file(s) skipped as they changed during the scan.\n"
+        );
+    }
+
+    #[test]
+    fn overlapping_targets_test() {
+        let dir = fixture_dir();
+        std::fs::create_dir_all(dir.path().join("pkg")).unwrap();
+        std::fs::write(dir.path().join("pkg/a.py"), "def f(x): ...\n").unwrap();
+        std::fs::write(dir.path().join("b.py"), "def g(x): ...\n").unwrap();
+
+        let targets = [
+            dir.path().join("pkg/a.py"),
+            dir.path().to_path_buf(),
+            dir.path().join("pkg/../b.py"),
+            dir.path().join("pkg"),
+        ];
+        let (mut files, _) = collect_files(&targets, &[], &["py".to_string()], |_, _| {});
+        files.sort();
+        assert_eq!(
+            files,
+            [dir.path().join("b.py"), dir.path().join("pkg/a.py")]
+        );
+
+        let cli = Cli::try_parse_from(["pythcheck", "a.py", "pkg", "--stdin-filenames"]).unwrap();
+        assert_eq!(
+            cli.args.paths,
+            [PathBuf::from("a.py"), PathBuf::from("pkg")]
+        );
+        assert_eq!(cli.args.path_list(), Some(Path::new("-")));
+        assert!(
+            Cli::try_parse_from(["pythcheck", "--stdin-filenames", "--files-from", "list"])
+                .is_err()
+        );
+        assert!(Cli::try_parse_from(["pythcheck"]).is_err());
+    }
+
+    #[test]
+    fn require_feature_test() {
+        assert!(require_feature(true, "formats-json", "--progress json").is_ok());
+        assert_eq!(
+            require_feature(false, "formats-json", "--progress json"),
+            Err("--progress json is not available as pythcheck was built without the 'formats-json' feature.".to_string())
+        );
+    }
+
+    #[test]
+    fn python_file_test() {
+        let extensions = ["py".to_string()];
+        assert!(is_python_file(Path::new("src/module.py"), &extensions));
+        assert!(is_python_file(Path::new("SCRIPT.PY"), &extensions));
+        assert!(is_python_file(Path::new("module.Py"), &extensions));
+        assert!(!is_python_file(Path::new("module.pyc"), &extensions));
+        assert!(!is_python_file(Path::new("module.pyi"), &extensions));
+        assert!(!is_python_file(Path::new("py"), &extensions));
+        assert!(!is_python_file(Path::new(".py"), &extensions));
+
+        let extensions = ["py".to_string(), ".pyi".to_string()];
+        assert!(is_python_file(Path::new("module.pyi"), &extensions));
+    }
+
+    #[test]
+    fn file_block_test() {
+        let block = report::get_file_block(
+            "src/module.py",
+            "Function 'f' in line 1 and column 1 is missing a return type.\n",
+            4,
+        );
+
+        assert_eq!(
+            block,
+            "File: src/module.py\n    Function 'f' in line 1 and column 1 is missing a return type.\n"
         );
     }
 }
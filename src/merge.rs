@@ -0,0 +1,273 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+
+use crate::{
+    json_report::{FileRecord, FindingRecord, JsonReport, Totals},
+    report::{get_file_block, get_located_message, with_suggestion},
+    AnnotationCounts, FileStats,
+};
+
+/// Combines reports of runs over parts of a tree, such as shards, into the report of a
+/// single run. Findings are de-duplicated by fingerprint, keeping as many of each as the
+/// report with the most of them has, and files by path, so that overlapping parts are
+/// only counted once. The result does not depend on the order of the reports.
+pub fn merge(reports: &[(PathBuf, JsonReport)]) -> Result<JsonReport, String> {
+    let Some((first_path, first)) = reports.first() else {
+        return Err("no reports to merge".to_string());
+    };
+    for (path, report) in reports {
+        if report.options_fingerprint != first.options_fingerprint {
+            return Err(format!(
+                "{} and {} were written with different options, so they cannot be merged",
+                first_path.display(),
+                path.display()
+            ));
+        }
+        if report.version != first.version {
+            return Err(format!(
+                "{} and {} were written by different versions ({} and {}), so they cannot be merged",
+                first_path.display(),
+                path.display(),
+                first.version,
+                report.version
+            ));
+        }
+    }
+
+    let mut occurrences: BTreeMap<&str, usize> = BTreeMap::new();
+    for (_, report) in reports {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for finding in &report.findings {
+            *counts.entry(&finding.fingerprint).or_default() += 1;
+        }
+        for (fingerprint, count) in counts {
+            let kept = occurrences.entry(fingerprint).or_default();
+            *kept = (*kept).max(count);
+        }
+    }
+
+    let mut candidates: Vec<&FindingRecord> = reports
+        .iter()
+        .flat_map(|(_, report)| &report.findings)
+        .collect();
+    candidates.sort_by(|a, b| finding_key(a).cmp(&finding_key(b)));
+    candidates.dedup();
+    let mut findings = Vec::new();
+    for finding in candidates {
+        let remaining = occurrences
+            .get_mut(finding.fingerprint.as_str())
+            .expect("Every finding should have been counted.");
+        if *remaining > 0 {
+            *remaining -= 1;
+            findings.push(finding.clone());
+        }
+    }
+
+    let mut files: Vec<&FileRecord> = reports
+        .iter()
+        .flat_map(|(_, report)| &report.files)
+        .collect();
+    files.sort_by(|a, b| file_key(a).cmp(&file_key(b)));
+    files.dedup_by(|a, b| a.path == b.path);
+    let files: Vec<FileRecord> = files.into_iter().cloned().collect();
+
+    let stats: Vec<FileStats> = files
+        .iter()
+        .map(|file| FileStats {
+            path: PathBuf::from(&file.path),
+            missing_parameters: file.missing_parameters,
+            missing_returns: file.missing_returns,
+            findings: file.findings,
+            counts: AnnotationCounts {
+                functions: file.functions,
+                slots: file.slots,
+            },
+            typed_package: None,
+        })
+        .collect();
+
+    Ok(JsonReport {
+        schema_version: first.schema_version,
+        version: first.version.clone(),
+        options_fingerprint: first.options_fingerprint.clone(),
+        summary: Totals::new(&stats, findings.len()),
+        findings,
+        files,
+        partial: reports.iter().any(|(_, report)| report.partial),
+    })
+}
+
+/// Orders findings as [`JsonReport`] does, breaking ties on every other field.
+fn finding_key(
+    finding: &FindingRecord,
+) -> (&str, usize, usize, &str, &str, &str, usize, usize, &str) {
+    (
+        &finding.path,
+        finding.line,
+        finding.column,
+        &finding.code,
+        &finding.fingerprint,
+        &finding.message,
+        finding.end_line,
+        finding.end_column,
+        finding.url.as_deref().unwrap_or_default(),
+    )
+}
+
+fn file_key(file: &FileRecord) -> (&str, usize, usize, usize, usize, usize) {
+    (
+        &file.path,
+        file.findings,
+        file.slots,
+        file.functions,
+        file.missing_parameters,
+        file.missing_returns,
+    )
+}
+
+/// Renders the findings as a single run's text output would, with a `File:` block per
+/// path.
+pub fn render_text(report: &JsonReport) -> String {
+    if report.findings.is_empty() {
+        return if report.summary.files_checked > 0 {
+            "✨ All good!\n".to_string()
+        } else {
+            String::new()
+        };
+    }
+
+    report
+        .findings
+        .chunk_by(|a, b| a.path == b.path)
+        .map(|findings| {
+            let messages: String = findings
+                .iter()
+                .map(|finding| {
+                    let message = with_suggestion(
+                        &finding.message,
+                        &finding.kind,
+                        finding.suggestion.as_deref(),
+                    );
+                    get_located_message(&message, &finding.name, finding.line, finding.column)
+                        + "\n"
+                })
+                .collect();
+            get_file_block(&findings[0].path, &messages, 4)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Reads the reports to merge, each of which must be a `--format json` report.
+pub fn read_reports(paths: &[PathBuf]) -> Result<Vec<(PathBuf, JsonReport)>, String> {
+    paths
+        .iter()
+        .map(|path| crate::compare::read_report(path).map(|report| (path.clone(), report)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_report::{fingerprint, SCHEMA_VERSION};
+
+    fn finding(path: &str, line: usize, name: &str) -> FindingRecord {
+        FindingRecord {
+            path: path.to_string(),
+            line,
+            column: 7,
+            end_line: line,
+            end_column: 8,
+            code: "THC001".to_string(),
+            kind: "missing_parameter".to_string(),
+            name: name.to_string(),
+            function: Some("f".to_string()),
+            message: format!("Parameter '{name}' is missing a type hint."),
+            suggestion: None,
+            url: None,
+            fingerprint: fingerprint(path, Some("f"), "missing_parameter", name),
+        }
+    }
+
+    fn file(path: &str, findings: usize) -> FileRecord {
+        FileRecord {
+            path: path.to_string(),
+            functions: 1,
+            slots: 4,
+            missing_parameters: findings,
+            missing_returns: 0,
+            findings,
+        }
+    }
+
+    fn report(findings: Vec<FindingRecord>, files: Vec<FileRecord>) -> (PathBuf, JsonReport) {
+        (
+            PathBuf::from(format!("shard{}.json", files.len())),
+            JsonReport {
+                schema_version: SCHEMA_VERSION,
+                version: "1.0.0".to_string(),
+                options_fingerprint: "fp".to_string(),
+                summary: Totals {
+                    files_checked: files.len(),
+                    findings: findings.len(),
+                    ..Default::default()
+                },
+                findings,
+                files,
+                partial: false,
+            },
+        )
+    }
+
+    #[test]
+    fn merge_test() {
+        let first = report(
+            vec![finding("b.py", 1, "x"), finding("b.py", 2, "x")],
+            vec![file("b.py", 2)],
+        );
+        let second = report(
+            vec![finding("a.py", 3, "y"), finding("b.py", 1, "x")],
+            vec![file("a.py", 1), file("b.py", 2), file("c.py", 0)],
+        );
+
+        let merged = merge(&[first.clone(), second.clone()]).unwrap();
+        assert_eq!(merge(&[second.clone(), first.clone()]), Ok(merged.clone()));
+        assert_eq!(
+            merged.findings,
+            [
+                finding("a.py", 3, "y"),
+                finding("b.py", 1, "x"),
+                finding("b.py", 2, "x")
+            ]
+        );
+        assert_eq!(merged.files.len(), 3);
+        assert_eq!(
+            merged.summary,
+            Totals {
+                files_checked: 3,
+                findings: 3,
+                coverage: Some(0.75),
+                functions: 3,
+                missing_parameters: 3,
+                missing_returns: 0,
+                files_with_findings: 2,
+            }
+        );
+
+        assert_eq!(
+            render_text(&merged),
+            "File: a.py\n    Parameter 'y' in line 3 and column 7 is missing a type hint.\n\n\
+             File: b.py\n    Parameter 'x' in line 1 and column 7 is missing a type hint.\n    \
+             Parameter 'x' in line 2 and column 7 is missing a type hint.\n"
+        );
+
+        let mut other_options = second.clone();
+        other_options.1.options_fingerprint = "other".to_string();
+        assert!(merge(&[first, other_options])
+            .unwrap_err()
+            .contains("different options"));
+        assert!(merge(&[]).is_err());
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::{Component, Path},
+    time::Duration,
+};
+
+use crate::{coverage, FileStats, Summary};
+
+/// Renders the run's totals in the Prometheus text exposition format, as read by
+/// node_exporter's textfile collector. With `by_directory`, series labeled by the
+/// top-level directory (relative to `root`) of each checked file are added.
+pub fn render_metrics(
+    summary: &Summary,
+    root: &Path,
+    duration: Duration,
+    by_directory: bool,
+) -> String {
+    let mut metrics = String::new();
+
+    write_header(
+        &mut metrics,
+        "thc_findings_total",
+        "Missing type hints found, by kind.",
+    );
+    let _ = writeln!(
+        metrics,
+        "thc_findings_total{{kind=\"parameter\"}} {}",
+        summary.parameter_findings()
+    );
+    let _ = writeln!(
+        metrics,
+        "thc_findings_total{{kind=\"return\"}} {}",
+        summary.return_findings()
+    );
+
+    write_header(&mut metrics, "thc_files_checked", "Python files checked.");
+    let _ = writeln!(metrics, "thc_files_checked {}", summary.files_checked);
+
+    write_header(
+        &mut metrics,
+        "thc_annotation_coverage_ratio",
+        "Share of checked parameters and return types that are annotated.",
+    );
+    let _ = writeln!(
+        metrics,
+        "thc_annotation_coverage_ratio {}",
+        summary.coverage().unwrap_or(1.0)
+    );
+
+    write_header(
+        &mut metrics,
+        "thc_scan_duration_seconds",
+        "Wall-clock duration of the scan.",
+    );
+    let _ = writeln!(
+        metrics,
+        "thc_scan_duration_seconds {}",
+        duration.as_secs_f64()
+    );
+
+    if by_directory {
+        render_directory_metrics(&mut metrics, summary, root);
+    }
+
+    metrics
+}
+
+fn render_directory_metrics(metrics: &mut String, summary: &Summary, root: &Path) {
+    let mut directories: BTreeMap<String, Vec<&FileStats>> = BTreeMap::new();
+    for file in &summary.files {
+        directories
+            .entry(top_level_directory(&file.path, root))
+            .or_default()
+            .push(file);
+    }
+
+    write_header(
+        metrics,
+        "thc_directory_findings_total",
+        "Missing type hints found, by top-level directory and kind.",
+    );
+    for (directory, files) in &directories {
+        let directory = escape_label_value(directory);
+        let parameters: usize = files.iter().map(|file| file.missing_parameters).sum();
+        let returns: usize = files.iter().map(|file| file.missing_returns).sum();
+
+        let _ = writeln!(
+            metrics,
+            "thc_directory_findings_total{{directory=\"{directory}\",kind=\"parameter\"}} {parameters}"
+        );
+        let _ = writeln!(
+            metrics,
+            "thc_directory_findings_total{{directory=\"{directory}\",kind=\"return\"}} {returns}"
+        );
+    }
+
+    write_header(
+        metrics,
+        "thc_directory_files_checked",
+        "Python files checked, by top-level directory.",
+    );
+    for (directory, files) in &directories {
+        let _ = writeln!(
+            metrics,
+            "thc_directory_files_checked{{directory=\"{}\"}} {}",
+            escape_label_value(directory),
+            files.len()
+        );
+    }
+
+    write_header(
+        metrics,
+        "thc_directory_annotation_coverage_ratio",
+        "Share of checked parameters and return types that are annotated, by top-level directory.",
+    );
+    for (directory, files) in &directories {
+        let _ = writeln!(
+            metrics,
+            "thc_directory_annotation_coverage_ratio{{directory=\"{}\"}} {}",
+            escape_label_value(directory),
+            coverage(files.iter().copied()).unwrap_or(1.0)
+        );
+    }
+}
+
+fn write_header(metrics: &mut String, name: &str, help: &str) {
+    let _ = writeln!(metrics, "# HELP {name} {help}");
+    let _ = writeln!(metrics, "# TYPE {name} gauge");
+}
+
+/// First directory of the file's path below the root, or `.` for files directly in it.
+pub fn top_level_directory(path: &Path, root: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let mut components = relative
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)));
+
+    match (components.next(), components.next()) {
+        (Some(directory), Some(_)) => directory.as_os_str().to_string_lossy().into_owned(),
+        _ => ".".to_string(),
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', "\\\"")
+        .replace('\n', r"\n")
+}
+
+/// Writes to a temporary file next to the destination before renaming it over, so that
+/// readers never observe a partially written file.
+pub fn write_atomically(path: &Path, contents: &str) -> std::io::Result<()> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let temporary = path.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+
+    std::fs::write(&temporary, contents)?;
+    std::fs::rename(&temporary, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temporary);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::AnnotationCounts;
+
+    fn file_stats(
+        path: &str,
+        missing_parameters: usize,
+        missing_returns: usize,
+        slots: usize,
+    ) -> FileStats {
+        FileStats {
+            path: PathBuf::from(path),
+            missing_parameters,
+            missing_returns,
+            findings: missing_parameters + missing_returns,
+            counts: AnnotationCounts {
+                functions: 1,
+                slots,
+            },
+            typed_package: None,
+        }
+    }
+
+    #[test]
+    fn metrics_test() {
+        let summary = Summary {
+            files_checked: 3,
+            findings: 4,
+            files: vec![
+                file_stats("root/app.py", 1, 0, 4),
+                file_stats("root/src/module.py", 2, 1, 4),
+                file_stats("root/we\"ird\\dir/module.py", 0, 0, 2),
+            ],
+            ..Default::default()
+        };
+
+        let metrics = render_metrics(
+            &summary,
+            Path::new("root"),
+            Duration::from_millis(1500),
+            true,
+        );
+
+        assert!(metrics.contains("thc_findings_total{kind=\"parameter\"} 3\n"));
+        assert!(metrics.contains("thc_findings_total{kind=\"return\"} 1\n"));
+        assert!(metrics.contains("thc_files_checked 3\n"));
+        assert!(metrics.contains("thc_annotation_coverage_ratio 0.6\n"));
+        assert!(metrics.contains("thc_scan_duration_seconds 1.5\n"));
+        assert!(metrics
+            .contains("thc_directory_findings_total{directory=\".\",kind=\"parameter\"} 1\n"));
+        assert!(
+            metrics.contains("thc_directory_findings_total{directory=\"src\",kind=\"return\"} 1\n")
+        );
+        assert!(metrics.contains("thc_directory_files_checked{directory=\"we\\\"ird\\\\dir\"} 1\n"));
+        assert!(
+            metrics.contains("thc_directory_annotation_coverage_ratio{directory=\"src\"} 0.25\n")
+        );
+
+        let metrics = render_metrics(
+            &summary,
+            Path::new("root"),
+            Duration::from_millis(1500),
+            false,
+        );
+        assert!(!metrics.contains("thc_directory"));
+    }
+
+    #[test]
+    fn write_atomically_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("thc.prom");
+
+        write_atomically(&path, "first\n").unwrap();
+        write_atomically(&path, "second\n").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second\n");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    fmt,
+    path::{Component, Path, PathBuf},
+};
+
+/// Dotted name of the module a file defines, such as `billing.invoices.render`.
+#[derive(Debug, PartialEq)]
+pub struct ModuleName {
+    pub name: String,
+    /// Whether the name follows a chain of regular packages up to a source root, rather
+    /// than being derived from the path of a file in a namespace package or outside of
+    /// any package.
+    pub exact: bool,
+}
+
+/// Derived names are marked with a leading `~`.
+impl fmt::Display for ModuleName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.exact {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "~{}", self.name)
+        }
+    }
+}
+
+/// Computes the module name of a file. Below the nearest of the source roots containing
+/// it, the name is the file's path relative to the root; otherwise it is made of the
+/// directories with an `__init__.py` leading to the file.
+pub fn module_name(file: &Path, src_roots: &[PathBuf]) -> ModuleName {
+    let absolute_file = std::path::absolute(file).unwrap_or_else(|_| file.to_path_buf());
+    let root = src_roots
+        .iter()
+        .filter_map(|root| std::path::absolute(root).ok())
+        .filter(|root| absolute_file.starts_with(root))
+        .max_by_key(|root| root.components().count());
+    let is_package = |directory: &Path| directory.join("__init__.py").is_file();
+    let directories = absolute_file.ancestors().skip(1);
+
+    let (directories, exact): (Vec<&Path>, bool) = match &root {
+        Some(root) => {
+            let directories: Vec<&Path> = directories
+                .take_while(|directory| directory != root)
+                .collect();
+            let exact = directories.iter().all(|directory| is_package(directory));
+            (directories, exact)
+        }
+        None => {
+            let directories: Vec<&Path> = directories
+                .take_while(|directory| is_package(directory))
+                .collect();
+            if directories.is_empty() {
+                // Files outside of any package are only named after their path.
+                return ModuleName {
+                    name: path_derived_name(file),
+                    exact: false,
+                };
+            }
+            (directories, true)
+        }
+    };
+
+    let mut parts: Vec<String> = directories
+        .iter()
+        .rev()
+        .filter_map(|directory| directory.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect();
+    let stem = file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if stem != "__init__" || parts.is_empty() {
+        parts.push(stem);
+    }
+
+    ModuleName {
+        name: parts.join("."),
+        exact,
+    }
+}
+
+fn path_derived_name(file: &Path) -> String {
+    let mut parts: Vec<String> = file
+        .with_extension("")
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    if parts.len() > 1 && parts.last().is_some_and(|part| part == "__init__") {
+        parts.pop();
+    }
+
+    parts.join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixture_dir;
+
+    #[test]
+    fn module_name_test() {
+        let dir = fixture_dir();
+        for directory in ["src/billing/invoices", "src/company/product"] {
+            std::fs::create_dir_all(dir.path().join(directory)).unwrap();
+        }
+        for file in [
+            "src/billing/__init__.py",
+            "src/billing/invoices/__init__.py",
+            "src/billing/invoices/render.py",
+            "src/company/product/__init__.py",
+            "src/company/product/api.py",
+        ] {
+            std::fs::write(dir.path().join(file), "").unwrap();
+        }
+        let src_roots = [dir.path().join("src")];
+        let src = &src_roots[0];
+        let exact = |name: &str| ModuleName {
+            name: name.to_string(),
+            exact: true,
+        };
+
+        assert_eq!(
+            module_name(&src.join("billing/invoices/render.py"), &[]),
+            exact("billing.invoices.render")
+        );
+        assert_eq!(
+            module_name(&src.join("billing/invoices/__init__.py"), &[]),
+            exact("billing.invoices")
+        );
+        assert_eq!(
+            module_name(&src.join("billing/invoices/render.py"), &src_roots),
+            exact("billing.invoices.render")
+        );
+        assert_eq!(
+            module_name(&src.join("company/product/api.py"), &[]),
+            exact("product.api")
+        );
+
+        let namespaced = module_name(&src.join("company/product/api.py"), &src_roots);
+        assert_eq!(namespaced.to_string(), "~company.product.api");
+
+        let script = module_name(Path::new("scripts/one_off.py"), &[]);
+        assert_eq!(script.to_string(), "~scripts.one_off");
+    }
+}
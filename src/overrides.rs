@@ -0,0 +1,335 @@
+use std::{fmt, str::FromStr};
+
+use clap::parser::ValueSource;
+
+/// A `--set KEY=VALUE` or `--set KEY+=VALUE` override of the option named KEY.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Override {
+    key: String,
+    value: String,
+    append: bool,
+}
+
+impl FromStr for Override {
+    type Err = String;
+
+    fn from_str(assignment: &str) -> Result<Self, Self::Err> {
+        let (key, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| format!("'{assignment}' is not an override such as key=value"))?;
+        let (key, append) = match key.strip_suffix('+') {
+            Some(key) => (key, true),
+            None => (key, false),
+        };
+        if key.trim().is_empty() {
+            return Err(format!("'{assignment}' does not name an option"));
+        }
+
+        Ok(Override {
+            key: key.trim().to_string(),
+            value: value.to_string(),
+            append,
+        })
+    }
+}
+
+impl fmt::Display for Override {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operator = if self.append { "+=" } else { "=" };
+        write!(f, "{}{operator}{}", self.key, self.value)
+    }
+}
+
+/// A value for an option, which [`apply`] makes the option's default before the
+/// arguments are parsed again.
+#[derive(Debug, PartialEq)]
+pub struct Assignment {
+    /// The option's command-line name, as shown by --print-config.
+    pub key: String,
+    /// The option's argument id.
+    pub id: String,
+    pub value: String,
+}
+
+/// Resolves the overrides into assignments, in order, so that a later override of the
+/// same option builds on the earlier ones and the first builds on the value the option
+/// was parsed with, from its environment variable or its default.
+pub fn assignments(
+    overrides: &[Override],
+    command: &clap::Command,
+    matches: &clap::ArgMatches,
+) -> Result<Vec<Assignment>, String> {
+    let mut assignments: Vec<Assignment> = Vec::new();
+
+    for item in overrides {
+        let key = item.key.replace('_', "-");
+        let argument = command
+            .get_arguments()
+            .find(|argument| argument.get_long() == Some(key.as_str()))
+            .filter(|argument| argument.get_env().is_some() && argument.get_id() != "set")
+            .ok_or_else(|| format!("--set {item}: '{key}' is not an option that can be set"))?;
+        let id = argument.get_id().to_string();
+
+        if matches.value_source(&id) == Some(ValueSource::CommandLine) {
+            eprintln!("note: --set {item} is ignored, as --{key} is given.");
+            continue;
+        }
+
+        let is_list = argument.get_value_delimiter().is_some();
+        let value = if item.append {
+            if !is_list {
+                return Err(format!(
+                    "--set {item}: '+=' appends to lists, but {key} expects {}",
+                    expected_type(argument)
+                ));
+            }
+
+            let previous = assignments
+                .iter()
+                .rev()
+                .find(|assignment| assignment.key == key)
+                .map(|assignment| assignment.value.clone())
+                .or_else(|| {
+                    let parsed: Vec<_> = matches
+                        .get_raw(&id)?
+                        .map(|value| value.to_string_lossy().into_owned())
+                        .collect();
+                    Some(parsed.join(","))
+                })
+                .filter(|previous| !previous.is_empty());
+            match previous {
+                Some(previous) => format!("{previous},{}", item.value),
+                None => item.value.clone(),
+            }
+        } else {
+            item.value.clone()
+        };
+
+        let values: Vec<&str> = if is_list {
+            value.split(',').collect()
+        } else {
+            vec![value.as_str()]
+        };
+        for element in values {
+            validate(argument, element).map_err(|reason| {
+                format!(
+                    "--set {item}: {key} expects {}, got '{element}'{reason}",
+                    expected_type(argument)
+                )
+            })?;
+        }
+
+        assignments.push(Assignment { key, id, value });
+    }
+
+    Ok(assignments)
+}
+
+/// Makes the assigned values the defaults of their options, in the subcommand the
+/// arguments were given to, if any, so that parsing the arguments again resolves them
+/// below the flags given on the command line. With `over_environment`, as for `--set`,
+/// the options no longer read their environment variable either, which otherwise takes
+/// precedence over the assigned values.
+pub fn apply(
+    command: clap::Command,
+    subcommand: Option<&str>,
+    assignments: &[Assignment],
+    over_environment: bool,
+) -> clap::Command {
+    let assign = |mut command: clap::Command| {
+        for assignment in assignments {
+            command = command.mut_arg(&assignment.id, |argument| {
+                let values: Vec<String> = if argument.get_value_delimiter().is_none() {
+                    vec![assignment.value.clone()]
+                } else if assignment.value.is_empty() {
+                    Vec::new()
+                } else {
+                    assignment.value.split(',').map(str::to_string).collect()
+                };
+                let argument = argument.default_values(values);
+                if over_environment {
+                    argument.env(None::<&'static str>)
+                } else {
+                    argument
+                }
+            });
+        }
+        command
+    };
+
+    match subcommand {
+        Some(name) => command.mut_subcommand(name, assign),
+        None => assign(command),
+    }
+}
+
+/// Parses the value the way the option would, returning the parser's reason, if any,
+/// when it is invalid. The option is probed on its own so that its relations to other
+/// options do not get in the way.
+pub fn validate(argument: &clap::Arg, value: &str) -> Result<(), String> {
+    let probe = clap::Command::new("set").no_binary_name(true).arg(
+        clap::Arg::new("value")
+            .long("value")
+            .action(clap::ArgAction::Set)
+            .value_parser(argument.get_value_parser().clone()),
+    );
+
+    match probe.try_get_matches_from([format!("--value={value}")]) {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            let rendered = e.to_string();
+            let first_line = rendered.lines().next().unwrap_or_default();
+            Err(match first_line.split_once("': ") {
+                Some((_, reason)) => format!(" ({reason})"),
+                None => String::new(),
+            })
+        }
+    }
+}
+
+/// Describes the values an option takes, for errors.
+pub fn expected_type(argument: &clap::Arg) -> String {
+    // Flags are checked first, as the parser of a `bool` also lists `true` and `false`
+    // as its possible values.
+    let element = if !argument.get_action().takes_values() {
+        "true or false".to_string()
+    } else if !argument.get_possible_values().is_empty() {
+        let names: Vec<_> = argument
+            .get_possible_values()
+            .iter()
+            .map(|value| value.get_name().to_string())
+            .collect();
+        format!("one of {}", names.join(", "))
+    } else if validate(argument, "0").is_ok() && validate(argument, "a").is_err() {
+        "a number".to_string()
+    } else {
+        match argument.get_value_names() {
+            Some(names) => format!("a {}", names[0]),
+            None => "a value".to_string(),
+        }
+    };
+
+    if argument.get_value_delimiter().is_some() {
+        format!("a comma-separated list of {element}")
+    } else {
+        element
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, FromArgMatches};
+
+    use super::*;
+    use crate::Cli;
+
+    /// Resolves the overrides against the arguments, parsed with the options in `parsed`
+    /// taking the value they would have from their environment variable, for instance.
+    fn resolve(
+        overrides: &[&str],
+        arguments: &[&str],
+        parsed: &[(&str, &str, &str)],
+    ) -> Result<Vec<Assignment>, String> {
+        let parsed: Vec<Assignment> = parsed
+            .iter()
+            .map(|(key, id, value)| assignment(key, id, value))
+            .collect();
+        let command = apply(Cli::command(), None, &parsed, false);
+        let matches = command.clone().try_get_matches_from(arguments).unwrap();
+        let overrides: Vec<Override> = overrides.iter().map(|item| item.parse().unwrap()).collect();
+
+        assignments(&overrides, &command, &matches)
+    }
+
+    fn assignment(key: &str, id: &str, value: &str) -> Assignment {
+        Assignment {
+            key: key.to_string(),
+            id: id.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn assignments_test() {
+        assert_eq!(
+            resolve(
+                &[
+                    "ignore-dirs+=migrations",
+                    "max_line_length=500",
+                    "ignore-dirs+=vendored"
+                ],
+                &["pythcheck", "src"],
+                &[("ignore-dirs", "ignore_dirs", "build")],
+            ),
+            Ok(vec![
+                assignment("ignore-dirs", "ignore_dirs", "build,migrations"),
+                assignment("max-line-length", "max_line_length", "500"),
+                assignment("ignore-dirs", "ignore_dirs", "build,migrations,vendored"),
+            ])
+        );
+        assert_eq!(
+            resolve(
+                &["extension+=pyi", "ignore-return=true"],
+                &["pythcheck", "src"],
+                &[]
+            ),
+            Ok(vec![
+                assignment("extension", "extensions", "py,pyi"),
+                assignment("ignore-return", "ignore_return", "true"),
+            ])
+        );
+
+        assert_eq!(
+            resolve(
+                &["ignore-return=true"],
+                &["pythcheck", "src", "--ignore-return"],
+                &[]
+            ),
+            Ok(vec![])
+        );
+        assert_eq!(
+            resolve(&["max-line-length=long"], &["pythcheck", "src"], &[]),
+            Err("--set max-line-length=long: max-line-length expects a number, got 'long' (invalid digit found in string)".to_string())
+        );
+        assert!(resolve(&["format=xml"], &["pythcheck", "src"], &[])
+            .unwrap_err()
+            .contains("format expects one of text, github"));
+        assert!(resolve(&["max-line-length+=1"], &["pythcheck", "src"], &[]).is_err());
+        assert!(resolve(&["colour=always"], &["pythcheck", "src"], &[]).is_err());
+        assert!("=1".parse::<Override>().is_err());
+        assert!("key".parse::<Override>().is_err());
+    }
+
+    #[test]
+    fn apply_test() {
+        let assignments = [
+            assignment("ignore-dirs", "ignore_dirs", "build,vendored"),
+            assignment("ignore-return", "ignore_return", "true"),
+            assignment("max-line-length", "max_line_length", "500"),
+        ];
+        let command = apply(Cli::command(), None, &assignments, true);
+        assert!(command
+            .get_arguments()
+            .filter(|argument| argument.get_id() == "ignore_return")
+            .all(|argument| argument.get_env().is_none()));
+
+        let matches = command
+            .try_get_matches_from(["pythcheck", "src", "--max-line-length", "80"])
+            .unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        assert_eq!(cli.args.ignore_dirs, ["build", "vendored"]);
+        assert!(cli.args.ignore_return);
+        assert_eq!(cli.args.max_line_length, 80);
+        assert_eq!(
+            matches.value_source("ignore_return"),
+            Some(ValueSource::DefaultValue)
+        );
+
+        let command = apply(Cli::command(), Some("list-files"), &assignments, false);
+        let matches = command
+            .try_get_matches_from(["pythcheck", "list-files", "src"])
+            .unwrap();
+        let (_, matches) = matches.subcommand().unwrap();
+        assert_eq!(matches.get_one::<usize>("max_line_length"), Some(&500));
+    }
+}
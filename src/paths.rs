@@ -0,0 +1,187 @@
+use std::{
+    path::{Component, Path, PathBuf},
+    sync::OnceLock,
+};
+
+/// Windows' `MAX_PATH`, beyond which paths need the extended-length prefix to be opened.
+#[cfg(windows)]
+const MAX_PATH: usize = 260;
+
+/// Renders a path for output, relative to the current directory when it is an absolute
+/// path under it. Separators are normalized to forward slashes unless `native` is set,
+/// which only makes a difference on Windows.
+pub fn display_path(path: &Path, native: bool) -> String {
+    let path = relative_to_current_dir(path);
+    let displayed = path.to_str().expect("Should be valid path name.");
+    let displayed = displayed.strip_prefix(r"\\?\").unwrap_or(displayed);
+
+    if native || std::path::MAIN_SEPARATOR == '/' {
+        displayed.to_string()
+    } else {
+        displayed.replace(std::path::MAIN_SEPARATOR, "/")
+    }
+}
+
+fn relative_to_current_dir(path: &Path) -> &Path {
+    static CURRENT_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+    if !path.is_absolute() {
+        return path;
+    }
+    let current_dir = CURRENT_DIR.get_or_init(|| std::env::current_dir().ok());
+    current_dir
+        .as_deref()
+        .and_then(|current_dir| path.strip_prefix(current_dir).ok())
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .unwrap_or(path)
+}
+
+/// Makes the path absolute and removes its `.` and `..` components without touching the
+/// file system, so that different spellings of a path compare equal.
+pub fn normalized(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+
+    let mut normalized = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Returns a path that can be opened even when its absolute form exceeds `MAX_PATH`, by
+/// switching to Windows' `\\?\` extended-length syntax.
+#[cfg(windows)]
+pub fn openable_path(path: &Path) -> PathBuf {
+    let Ok(absolute) = std::path::absolute(path) else {
+        return path.to_path_buf();
+    };
+
+    let absolute = absolute.as_os_str().to_string_lossy();
+    if absolute.len() < MAX_PATH || absolute.starts_with(r"\\") {
+        return path.to_path_buf();
+    }
+
+    PathBuf::from(format!(r"\\?\{}", absolute.replace('/', r"\")))
+}
+
+#[cfg(not(windows))]
+pub fn openable_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Splits a list of paths separated by newlines, or by NUL when `null` is set so that
+/// paths may contain newlines. Empty entries are skipped.
+pub fn read_path_list(contents: &[u8], null: bool) -> Result<Vec<PathBuf>, String> {
+    let separator = if null { b'\0' } else { b'\n' };
+
+    contents
+        .split(|byte| *byte == separator)
+        .map(|entry| {
+            if null {
+                entry
+            } else {
+                entry.strip_suffix(b"\r").unwrap_or(entry)
+            }
+        })
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            std::str::from_utf8(entry)
+                .map(PathBuf::from)
+                .map_err(|_| format!("'{}' is not a UTF-8 path", entry.escape_ascii()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_path_test() {
+        assert_eq!(display_path(Path::new("src/app.py"), false), "src/app.py");
+        assert_eq!(display_path(Path::new("src/app.py"), true), "src/app.py");
+
+        let current_dir = std::env::current_dir().unwrap();
+        assert_eq!(
+            display_path(&current_dir.join("src/app.py"), false),
+            "src/app.py"
+        );
+    }
+
+    #[test]
+    fn normalized_test() {
+        let current_dir = std::env::current_dir().unwrap();
+        assert_eq!(
+            normalized(Path::new("./src/../src/app.py")),
+            current_dir.join("src/app.py")
+        );
+        assert_eq!(
+            normalized(&current_dir.join("src/./app.py")),
+            normalized(Path::new("src/app.py"))
+        );
+    }
+
+    #[test]
+    fn read_path_list_test() {
+        assert_eq!(
+            read_path_list(b"src/a.py\r\n\nsrc/b.py\n", false),
+            Ok(vec![PathBuf::from("src/a.py"), PathBuf::from("src/b.py")])
+        );
+        assert_eq!(
+            read_path_list(b"src/we\nird.py\0src/b.py\0", true),
+            Ok(vec![
+                PathBuf::from("src/we\nird.py"),
+                PathBuf::from("src/b.py")
+            ])
+        );
+        assert_eq!(
+            read_path_list(b"src/\xff.py", false),
+            Err("'src/\\xff.py' is not a UTF-8 path".to_string())
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_display_path_test() {
+        assert_eq!(
+            display_path(Path::new(r"src\pkg\app.py"), false),
+            "src/pkg/app.py"
+        );
+        assert_eq!(
+            display_path(Path::new(r"src\pkg\app.py"), true),
+            r"src\pkg\app.py"
+        );
+        assert_eq!(
+            display_path(Path::new(r"\\?\C:\src\app.py"), false),
+            "C:/src/app.py"
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_long_path_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut nested = dir.path().to_path_buf();
+        while nested.as_os_str().len() < MAX_PATH + 20 {
+            nested.push("deeply_nested_directory");
+        }
+
+        std::fs::create_dir_all(openable_path(&nested)).unwrap();
+        let file = nested.join("module.py");
+        std::fs::write(openable_path(&file), "def f(x): ...").unwrap();
+
+        let contents = std::fs::read(openable_path(&file)).unwrap();
+        assert_eq!(contents, b"def f(x): ...");
+        assert_eq!(
+            display_path(&file, false),
+            file.to_str().unwrap().replace('\\', "/")
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    io::Write,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+/// How progress is reported on stderr while files are being checked.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressMode {
+    /// No progress output.
+    #[default]
+    None,
+    /// A human-readable counter, redrawn in place.
+    Bar,
+    /// Newline-delimited JSON events, for tools rendering their own progress UI.
+    Json,
+}
+
+/// Counts checked files and findings, rendering them in the chosen mode. Both modes read
+/// from the same counters so they always agree.
+pub struct Progress {
+    mode: ProgressMode,
+    total: usize,
+    done: AtomicUsize,
+    findings: AtomicUsize,
+    started: Instant,
+}
+
+impl Progress {
+    pub fn start(mode: ProgressMode, total: usize) -> Self {
+        let progress = Progress {
+            mode,
+            total,
+            done: AtomicUsize::new(0),
+            findings: AtomicUsize::new(0),
+            started: Instant::now(),
+        };
+
+        if mode == ProgressMode::Json {
+            emit_event(&Event::Start {
+                total_estimate: total,
+            });
+        }
+
+        progress
+    }
+
+    pub fn file_done(&self, path: &str, findings: usize) {
+        let done = self.done.fetch_add(1, Ordering::SeqCst) + 1;
+        self.findings.fetch_add(findings, Ordering::SeqCst);
+
+        match self.mode {
+            ProgressMode::None => {}
+            ProgressMode::Bar => {
+                let mut stderr = std::io::stderr().lock();
+                let _ = write!(stderr, "\r[{done}/{}] files checked", self.total);
+                let _ = stderr.flush();
+            }
+            ProgressMode::Json => emit_event(&Event::FileDone {
+                path,
+                findings,
+                done,
+            }),
+        }
+    }
+
+    pub fn finish(&self) {
+        let files = self.done.load(Ordering::SeqCst);
+        let findings = self.findings.load(Ordering::SeqCst);
+
+        match self.mode {
+            ProgressMode::None => {}
+            ProgressMode::Bar => eprintln!(),
+            ProgressMode::Json => emit_event(&Event::Finished {
+                files,
+                findings,
+                elapsed_ms: self.started.elapsed().as_millis(),
+            }),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    Start {
+        total_estimate: usize,
+    },
+    FileDone {
+        path: &'a str,
+        findings: usize,
+        done: usize,
+    },
+    Finished {
+        files: usize,
+        findings: usize,
+        elapsed_ms: u128,
+    },
+}
+
+/// Writes a whole event line while holding the stderr lock, so that events coming from
+/// different workers never interleave.
+#[cfg(feature = "formats-json")]
+fn emit_event(event: &Event) {
+    let event = serde_json::to_string(event).expect("Progress events should be serializable.");
+
+    let mut stderr = std::io::stderr().lock();
+    let _ = writeln!(stderr, "{event}");
+}
+
+#[cfg(not(feature = "formats-json"))]
+fn emit_event(_event: &Event) {
+    unreachable!("JSON progress should have been rejected at startup.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counting_test() {
+        let progress = Progress::start(ProgressMode::None, 2);
+        progress.file_done("a.py", 3);
+        progress.file_done("b.py", 0);
+
+        assert_eq!(progress.done.load(Ordering::SeqCst), 2);
+        assert_eq!(progress.findings.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// A `major.minor` Python version that the checked code targets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PythonVersion {
+    pub major: u8,
+    pub minor: u8,
+}
+
+/// Oldest and newest versions whose syntax and typing features the rules know about.
+pub const SUPPORTED: (PythonVersion, PythonVersion) =
+    (PythonVersion::new(3, 8), PythonVersion::new(3, 14));
+
+/// The version from which type parameter lists and `type` statements (PEP 695) parse.
+pub const TYPE_PARAMETER_SYNTAX: PythonVersion = PythonVersion::new(3, 12);
+
+impl PythonVersion {
+    pub const fn new(major: u8, minor: u8) -> Self {
+        PythonVersion { major, minor }
+    }
+
+    /// Errors for versions outside of the supported range.
+    pub fn supported(self) -> Result<Self, String> {
+        let (oldest, newest) = SUPPORTED;
+        if self < oldest || self > newest {
+            return Err(format!(
+                "Python {self} is not supported, use a version from {oldest} to {newest}"
+            ));
+        }
+
+        Ok(self)
+    }
+}
+
+/// The oldest version still maintained upstream, assumed when no version is given or
+/// found in `pyproject.toml`.
+impl Default for PythonVersion {
+    fn default() -> Self {
+        PythonVersion::new(3, 10)
+    }
+}
+
+impl FromStr for PythonVersion {
+    type Err = String;
+
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{version}' is not a version such as 3.12");
+
+        let (major, minor) = version.trim().split_once('.').ok_or_else(invalid)?;
+        PythonVersion {
+            major: major.parse().map_err(|_| invalid())?,
+            minor: minor.parse().map_err(|_| invalid())?,
+        }
+        .supported()
+    }
+}
+
+impl fmt::Display for PythonVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl serde::Serialize for PythonVersion {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Looks for a `pyproject.toml` declaring `requires-python` in `start` and its ancestors,
+/// returning the oldest version it allows along with the file it was read from.
+pub fn find_requires_python(start: &Path) -> Result<Option<(PythonVersion, PathBuf)>, String> {
+    let start = std::path::absolute(start).map_err(|e| e.to_string())?;
+    let start = if start.is_dir() {
+        start.as_path()
+    } else {
+        start.parent().unwrap_or(&start)
+    };
+
+    for directory in start.ancestors() {
+        let path = directory.join("pyproject.toml");
+        if !path.is_file() {
+            continue;
+        }
+
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+        let table: toml::Table = contents
+            .parse()
+            .map_err(|e| format!("could not parse {}: {e}", path.display()))?;
+
+        let Some(specifier) = table
+            .get("project")
+            .and_then(|project| project.get("requires-python"))
+            .and_then(|requires| requires.as_str())
+        else {
+            continue;
+        };
+
+        let Some(version) = minimum_version(specifier) else {
+            continue;
+        };
+        let version = version.supported().map_err(|e| {
+            format!(
+                "{e} (from requires-python in {}), pass --python-version to override it",
+                path.display()
+            )
+        })?;
+
+        return Ok(Some((version, path)));
+    }
+
+    Ok(None)
+}
+
+/// The oldest `major.minor` version a specifier such as `>=3.9,<4` allows, if it has a
+/// lower bound.
+fn minimum_version(specifier: &str) -> Option<PythonVersion> {
+    specifier
+        .split(',')
+        .filter_map(|clause| {
+            let clause = clause.trim();
+            let (operator, version) = ["~=", ">=", "==", ">"]
+                .iter()
+                .find_map(|operator| Some((*operator, clause.strip_prefix(operator)?)))?;
+
+            let mut components = version.trim().trim_end_matches(".*").split('.');
+            let major = components.next()?.parse().ok()?;
+            let minor = components
+                .next()
+                .map_or(Some(0), |minor| minor.parse().ok())?;
+            let has_patch = components.next().is_some();
+
+            // `>3.9` excludes 3.9 itself, while `>3.9.1` still allows later 3.9 releases.
+            let minor = if operator == ">" && !has_patch {
+                minor + 1
+            } else {
+                minor
+            };
+
+            Some(PythonVersion::new(major, minor))
+        })
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixture_dir;
+
+    #[test]
+    fn parse_test() {
+        assert_eq!("3.10".parse(), Ok(PythonVersion::new(3, 10)));
+        assert!("3".parse::<PythonVersion>().is_err());
+        assert!("3.x".parse::<PythonVersion>().is_err());
+        assert_eq!(
+            "2.7".parse::<PythonVersion>(),
+            Err("Python 2.7 is not supported, use a version from 3.8 to 3.14".to_string())
+        );
+        assert!("3.99".parse::<PythonVersion>().is_err());
+        assert!(PythonVersion::new(3, 9) < PythonVersion::new(3, 10));
+        assert_eq!(PythonVersion::new(3, 12).to_string(), "3.12");
+    }
+
+    #[test]
+    fn minimum_version_test() {
+        assert_eq!(minimum_version(">=3.9"), Some(PythonVersion::new(3, 9)));
+        assert_eq!(minimum_version(">=3.9, <4"), Some(PythonVersion::new(3, 9)));
+        assert_eq!(minimum_version("~=3.11"), Some(PythonVersion::new(3, 11)));
+        assert_eq!(minimum_version("==3.12.*"), Some(PythonVersion::new(3, 12)));
+        assert_eq!(minimum_version(">3.9"), Some(PythonVersion::new(3, 10)));
+        assert_eq!(minimum_version(">3.9.1"), Some(PythonVersion::new(3, 9)));
+        assert_eq!(
+            minimum_version("!=3.9.0,>=3.8"),
+            Some(PythonVersion::new(3, 8))
+        );
+        assert_eq!(minimum_version("<3.13"), None);
+    }
+
+    #[test]
+    fn find_requires_python_test() {
+        let dir = fixture_dir();
+        std::fs::create_dir_all(dir.path().join("src/app")).unwrap();
+        std::fs::write(dir.path().join("src/pyproject.toml"), "[tool.ruff]\n").unwrap();
+        std::fs::write(
+            dir.path().join("pyproject.toml"),
+            "[project]\nrequires-python = \">=3.11\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            find_requires_python(&dir.path().join("src/app")).unwrap(),
+            Some((PythonVersion::new(3, 11), dir.path().join("pyproject.toml")))
+        );
+
+        std::fs::write(
+            dir.path().join("pyproject.toml"),
+            "[project]\nrequires-python = \">=3.6\"\n",
+        )
+        .unwrap();
+        assert!(find_requires_python(&dir.path().join("src/app")).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use crate::{metrics::top_level_directory, Summary};
+
+/// The finding budget stored in a `--ratchet` file: a maximum for the whole run and,
+/// optionally, one per top-level directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ratchet {
+    pub total: usize,
+    pub directories: BTreeMap<String, usize>,
+}
+
+impl Ratchet {
+    /// The counts of a run, used as the budget of a new ratchet file.
+    pub fn from_summary(summary: &Summary, root: &Path) -> Self {
+        Ratchet {
+            total: summary.findings,
+            directories: directory_findings(summary, root),
+        }
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let table: toml::Table = contents.parse().map_err(|e| format!("{e}"))?;
+        let count = |key: &str, value: &toml::Value| {
+            value
+                .as_integer()
+                .and_then(|count| usize::try_from(count).ok())
+                .ok_or_else(|| format!("'{key}' should be a finding count"))
+        };
+
+        let total = table.get("total").ok_or("'total' is missing")?;
+        let mut ratchet = Ratchet {
+            total: count("total", total)?,
+            directories: BTreeMap::new(),
+        };
+        if let Some(directories) = table.get("directories") {
+            let directories = directories
+                .as_table()
+                .ok_or("'directories' should be a table")?;
+            for (directory, value) in directories {
+                ratchet
+                    .directories
+                    .insert(directory.clone(), count(directory, value)?);
+            }
+        }
+
+        Ok(ratchet)
+    }
+
+    /// Renders the budget with one entry per line, sorted by directory, so that changes
+    /// to it merge like any other file.
+    pub fn render(&self) -> String {
+        let mut rendered = String::from(
+            "# Finding budget for pythcheck --ratchet, lowered by --ratchet-update.\n",
+        );
+        let _ = writeln!(rendered, "total = {}", self.total);
+
+        if !self.directories.is_empty() {
+            rendered += "\n[directories]\n";
+            for (directory, count) in &self.directories {
+                let _ = writeln!(
+                    rendered,
+                    "{} = {count}",
+                    toml::Value::from(directory.as_str())
+                );
+            }
+        }
+
+        rendered
+    }
+
+    /// Compares a run with the budget. Directories without a budget of their own are
+    /// allowed no findings once any directory has one.
+    pub fn check(&self, summary: &Summary, root: &Path) -> Check {
+        let mut directories: BTreeMap<String, (usize, usize)> = self
+            .directories
+            .iter()
+            .map(|(directory, budget)| (directory.clone(), (*budget, 0)))
+            .collect();
+        if !self.directories.is_empty() {
+            for (directory, actual) in directory_findings(summary, root) {
+                directories.entry(directory).or_insert((0, 0)).1 = actual;
+            }
+        }
+
+        Check {
+            total: (self.total, summary.findings),
+            directories,
+        }
+    }
+}
+
+fn directory_findings(summary: &Summary, root: &Path) -> BTreeMap<String, usize> {
+    let mut directories = BTreeMap::new();
+    for file in &summary.files {
+        *directories
+            .entry(top_level_directory(&file.path, root))
+            .or_default() += file.findings;
+    }
+
+    directories
+}
+
+/// Budgets and actual finding counts of a run.
+#[derive(Debug, PartialEq)]
+pub struct Check {
+    total: (usize, usize),
+    directories: BTreeMap<String, (usize, usize)>,
+}
+
+impl Check {
+    pub fn passed(&self) -> bool {
+        std::iter::once(&self.total)
+            .chain(self.directories.values())
+            .all(|(budget, actual)| actual <= budget)
+    }
+
+    /// The ratchet lowered to the actual counts, for a run within budget.
+    pub fn lowered(&self) -> Ratchet {
+        Ratchet {
+            total: self.total.1,
+            directories: self
+                .directories
+                .iter()
+                .map(|(directory, (_, actual))| (directory.clone(), *actual))
+                .collect(),
+        }
+    }
+
+    /// Renders e.g. `Ratchet: 95 finding(s) of a budget of 100, 5 of headroom.`, followed
+    /// by one line per directory.
+    pub fn render(&self) -> String {
+        let mut rendered = format!("Ratchet: {}.\n", describe(self.total));
+        for (directory, counts) in &self.directories {
+            let _ = writeln!(rendered, "    {directory}: {}.", describe(*counts));
+        }
+
+        rendered
+    }
+}
+
+fn describe((budget, actual): (usize, usize)) -> String {
+    if actual > budget {
+        format!(
+            "{actual} finding(s) of a budget of {budget}, {} over budget",
+            actual - budget
+        )
+    } else {
+        format!(
+            "{actual} finding(s) of a budget of {budget}, {} of headroom",
+            budget - actual
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{AnnotationCounts, FileStats};
+
+    fn file_stats(path: &str, findings: usize) -> FileStats {
+        FileStats {
+            path: PathBuf::from(path),
+            missing_parameters: findings,
+            missing_returns: 0,
+            findings,
+            counts: AnnotationCounts {
+                functions: 1,
+                slots: findings,
+            },
+            typed_package: None,
+        }
+    }
+
+    fn summary(files: Vec<FileStats>) -> Summary {
+        Summary {
+            files_checked: files.len(),
+            findings: files.iter().map(|file| file.findings).sum(),
+            files,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ratchet_test() {
+        let root = Path::new("root");
+        let ratchet = Ratchet::from_summary(
+            &summary(vec![
+                file_stats("root/app.py", 1),
+                file_stats("root/src/a.py", 2),
+                file_stats("root/src/b.py", 3),
+            ]),
+            root,
+        );
+        let rendered = ratchet.render();
+        assert_eq!(
+            rendered,
+            "# Finding budget for pythcheck --ratchet, lowered by --ratchet-update.\n\
+             total = 6\n\n[directories]\n\".\" = 1\n\"src\" = 5\n"
+        );
+        assert_eq!(Ratchet::parse(&rendered), Ok(ratchet.clone()));
+
+        let check = ratchet.check(
+            &summary(vec![
+                file_stats("root/src/a.py", 4),
+                file_stats("root/tests/test_a.py", 1),
+            ]),
+            root,
+        );
+        assert!(!check.passed());
+        assert_eq!(
+            check.render(),
+            "Ratchet: 5 finding(s) of a budget of 6, 1 of headroom.\n    \
+             .: 0 finding(s) of a budget of 1, 1 of headroom.\n    \
+             src: 4 finding(s) of a budget of 5, 1 of headroom.\n    \
+             tests: 1 finding(s) of a budget of 0, 1 over budget.\n"
+        );
+
+        let check = ratchet.check(&summary(vec![file_stats("root/src/a.py", 4)]), root);
+        assert!(check.passed());
+        assert_eq!(check.lowered().total, 4);
+        assert_eq!(check.lowered().directories["."], 0);
+
+        assert!(Ratchet::parse("total = -1\n").is_err());
+        assert!(Ratchet::parse("[directories]\nsrc = 1\n").is_err());
+    }
+}
@@ -0,0 +1,864 @@
+use std::{fmt::Write, path::Path};
+
+use crate::{
+    gitlab_report::get_gitlab_records,
+    json_report::{get_json_records, kind_and_name},
+    modules::{module_name, ModuleName},
+    paths::display_path,
+    CheckOptions, FileStats, MissingType, Position,
+};
+
+/// Typical length of a rendered finding, for sizing the rendered output up front.
+const LINE_CAPACITY: usize = 80;
+
+/// How findings are written to stdout.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human-readable messages grouped by file.
+    #[default]
+    Text,
+    /// GitHub Actions workflow commands, shown as annotations on the diff.
+    Github,
+    /// Azure DevOps logging commands, shown as issues of the pipeline run.
+    Azure,
+    /// TeamCity service messages, shown in the Code Inspections tab of the build.
+    Teamcity,
+    /// A JSON document listing findings and annotation counts, sorted by path.
+    Json,
+    /// A GitLab Code Quality report, shown in the merge request widget and diff.
+    Gitlab,
+}
+
+/// Code, name and description of every rule, for formats that declare them up front.
+const RULES: &[(&str, &str, &str)] = &[
+    (
+        "THC001",
+        "Missing parameter hint",
+        "Function parameters without a type hint",
+    ),
+    (
+        "THC002",
+        "Missing return hint",
+        "Functions without a return type",
+    ),
+    (
+        "THC101",
+        "Untyped empty collection",
+        "Empty collections assigned without a type hint",
+    ),
+    (
+        "THC102",
+        "Bare generic",
+        "Generics annotated without type arguments",
+    ),
+    (
+        "THC103",
+        "Deprecated typing alias",
+        "typing aliases with a builtin replacement in the target Python version",
+    ),
+    (
+        "THC104",
+        "Quoted annotation",
+        "String annotations in modules whose annotations are deferred",
+    ),
+    (
+        "THC105",
+        "Annotation style",
+        "Optional annotations not spelled in the configured style",
+    ),
+    (
+        "THC106",
+        "Lambda binding",
+        "Lambdas bound to a name, which cannot be annotated",
+    ),
+    (
+        "THC107",
+        "Partial annotation",
+        "Functions annotating some of their parameters but not the others",
+    ),
+    (
+        "THC108",
+        "Explicit Any",
+        "Parameters and return types annotated as Any",
+    ),
+    (
+        "THC201",
+        "Newer syntax",
+        "Syntax that does not parse in the target Python version",
+    ),
+    (
+        "THC202",
+        "Syntax error",
+        "Files that do not parse, which are only checked outside of the broken regions",
+    ),
+];
+
+/// CI environments recognized by the variable they set, along with the format their
+/// job logs understand. GitLab (`GITLAB_CI`) is left out, as `--format gitlab` writes a
+/// report meant to be saved as an artifact and its job logs only show text.
+const CI_FORMATS: &[(&str, OutputFormat)] = &[
+    ("GITHUB_ACTIONS", OutputFormat::Github),
+    ("TF_BUILD", OutputFormat::Azure),
+];
+
+/// Picks the output format: an explicit `--format` wins, then the format of a detected
+/// CI environment, then text. Also returns the variable a format was detected from, so
+/// that the choice can be announced.
+pub fn resolve_format(
+    explicit: Option<OutputFormat>,
+    env: impl Fn(&str) -> Option<String>,
+) -> (OutputFormat, Option<&'static str>) {
+    if let Some(format) = explicit {
+        return (format, None);
+    }
+
+    CI_FORMATS
+        .iter()
+        .find(|(variable, _)| env(variable).is_some_and(|value| value.eq_ignore_ascii_case("true")))
+        .map_or((OutputFormat::Text, None), |(variable, format)| {
+            (*format, Some(*variable))
+        })
+}
+
+/// Renders the findings of a file in the chosen format. Text findings are grouped under
+/// a `File:` header or, with `aggregate`, rendered as one line per function, or without
+/// `group_by_file`, as one line per finding.
+pub fn render_file(path: &Path, positions: &[Position], options: &CheckOptions) -> String {
+    match options.format {
+        OutputFormat::Text => {
+            let module = options
+                .module_names
+                .as_ref()
+                .map(|src_roots| module_name(path, src_roots));
+            let path = display_path(path, options.native_paths);
+
+            if !options.group_by_file && !options.aggregate {
+                get_finding_lines(&path, positions)
+            } else if options.aggregate {
+                let terminator = if options.null { '\0' } else { '\n' };
+                get_aggregated_message_from_positions(&path, positions, terminator, module.as_ref())
+            } else {
+                let header = match &module {
+                    Some(module) => format!("{path} ({module})"),
+                    None => path,
+                };
+                get_file_block(
+                    &header,
+                    &get_message_from_positions(positions),
+                    options.indent,
+                )
+            }
+        }
+        OutputFormat::Github => get_github_commands(&display_path(path, false), positions),
+        OutputFormat::Azure => get_azure_commands(&display_path(path, false), positions),
+        OutputFormat::Teamcity => get_teamcity_messages(&display_path(path, false), positions),
+        OutputFormat::Json => get_json_records(path, positions, options.links.as_ref()),
+        OutputFormat::Gitlab => get_gitlab_records(path, positions),
+    }
+}
+
+/// What goes between the renderings of two files: a blank line between text blocks,
+/// which are otherwise not told apart at a glance.
+pub fn block_separator(options: &CheckOptions) -> &'static str {
+    if options.format == OutputFormat::Text && options.group_by_file && !options.aggregate {
+        "\n"
+    } else {
+        ""
+    }
+}
+
+/// Renders a table of the files with findings, the most findings first, for
+/// --statistics.
+pub fn render_statistics(files: &[FileStats], native_paths: bool) -> String {
+    let mut files: Vec<&FileStats> = files.iter().filter(|file| file.findings > 0).collect();
+    files.sort_by(|a, b| {
+        b.findings
+            .cmp(&a.findings)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    if files.is_empty() {
+        return String::new();
+    }
+
+    let mut table = String::from("Findings  Parameters  Returns  Functions  File\n");
+    for file in files {
+        let _ = writeln!(
+            table,
+            "{:>8}  {:>10}  {:>7}  {:>9}  {}",
+            file.findings,
+            file.missing_parameters,
+            file.missing_returns,
+            file.counts.functions,
+            display_path(&file.path, native_paths)
+        );
+    }
+
+    table
+}
+
+/// Renders what a format needs before any finding: TeamCity has every inspection type
+/// declared.
+pub fn render_run_start(format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Text
+        | OutputFormat::Github
+        | OutputFormat::Azure
+        | OutputFormat::Json
+        | OutputFormat::Gitlab => String::new(),
+        OutputFormat::Teamcity => RULES
+            .iter()
+            .map(|(code, name, description)| {
+                format!(
+                    "##teamcity[inspectionType id='{code}' name='{}' description='{}' category='Type hints']\n",
+                    escape_teamcity(name),
+                    escape_teamcity(description)
+                )
+            })
+            .collect(),
+    }
+}
+
+/// Renders what a format needs once all files are checked: Azure DevOps sets the result
+/// of the task from the findings and the exit status.
+pub fn render_run_end(format: OutputFormat, findings: usize, exit_status: i32) -> String {
+    match format {
+        OutputFormat::Text
+        | OutputFormat::Github
+        | OutputFormat::Teamcity
+        | OutputFormat::Json
+        | OutputFormat::Gitlab => String::new(),
+        OutputFormat::Azure => {
+            let result = if exit_status != 0 {
+                "Failed"
+            } else if findings > 0 {
+                "SucceededWithIssues"
+            } else {
+                "Succeeded"
+            };
+            format!("##vso[task.complete result={result};]\n")
+        }
+    }
+}
+
+pub fn get_message_from_positions(positions: &[Position]) -> String {
+    let mut message = String::with_capacity(positions.len() * LINE_CAPACITY);
+
+    for position in positions {
+        let (_, subject) = kind_and_name(&position.missing_type);
+        write_located_message(
+            &mut message,
+            &get_finding_message(position),
+            &subject,
+            position.start.row + 1,
+            position.start.column + 1,
+        );
+        message.push('\n');
+    }
+
+    message
+}
+
+/// Inserts the location into a message right after its subject, turning e.g.
+/// `Parameter 'x' is missing a type hint.` into `Parameter 'x' in line 3 and column 7 is
+/// missing a type hint.`, so that messages read back from a report render the same way.
+pub fn get_located_message(message: &str, subject: &str, line: usize, column: usize) -> String {
+    let mut located = String::with_capacity(LINE_CAPACITY);
+    write_located_message(&mut located, message, subject, line, column);
+
+    located
+}
+
+fn write_located_message(
+    out: &mut String,
+    message: &str,
+    subject: &str,
+    line: usize,
+    column: usize,
+) {
+    let quoted = format!("'{subject}'");
+    let end = message
+        .find(&quoted)
+        .map(|start| start + quoted.len())
+        .or_else(|| message.find(subject).map(|start| start + subject.len()))
+        .unwrap_or(0);
+
+    let _ = write!(
+        out,
+        "{} in line {line} and column {column}{}",
+        &message[..end],
+        &message[end..]
+    );
+}
+
+/// Renders the findings of a file under a `File:` header, indenting each line by
+/// `indent` spaces.
+pub fn get_file_block(path: &str, messages_from_file: &str, indent: usize) -> String {
+    let lines = messages_from_file.lines().count();
+    let mut block =
+        String::with_capacity(path.len() + messages_from_file.len() + lines * indent + 8);
+    let _ = writeln!(block, "File: {path}");
+
+    for line in messages_from_file.lines() {
+        let _ = writeln!(block, "{:indent$}{line}", "");
+    }
+
+    block
+}
+
+/// Renders one `path:line:column: message` line per finding.
+pub fn get_finding_lines(path: &str, positions: &[Position]) -> String {
+    let mut lines = String::with_capacity(positions.len() * (path.len() + LINE_CAPACITY));
+
+    for position in positions {
+        let _ = writeln!(
+            lines,
+            "{path}:{}:{}: {}",
+            position.start.row + 1,
+            position.start.column + 1,
+            get_finding_message(position)
+        );
+    }
+
+    lines
+}
+
+/// Describes a finding without its location, for formats carrying it separately.
+pub fn get_short_message(missing_type: &MissingType) -> String {
+    match missing_type {
+        MissingType::Return(name) => format!("Function '{name}' is missing a return type."),
+        MissingType::Parameter(name) => format!("Parameter '{name}' is missing a type hint."),
+        MissingType::EmptyCollection(name) => {
+            format!("Variable '{name}' is assigned an empty collection without a type hint.")
+        }
+        MissingType::BareGeneric(name) => format!("Annotation '{name}' is missing type arguments."),
+        MissingType::QuotedAnnotation(contents) => {
+            format!("Annotation '{contents}' does not need quotes.")
+        }
+        MissingType::SyntaxError {
+            text,
+            missing: true,
+        } => format!("Syntax error, expected '{text}' here."),
+        MissingType::SyntaxError {
+            text,
+            missing: false,
+        } => format!("Syntax error, could not parse '{text}'."),
+        MissingType::NewerSyntax { syntax, since } => {
+            format!("The {syntax} needs Python {since} or later.")
+        }
+        MissingType::AnnotationStyle { found, replacement } => {
+            format!("Annotation '{found}' does not follow the configured style, prefer '{replacement}'.")
+        }
+        MissingType::DeprecatedTyping {
+            alias,
+            replacement: Some(replacement),
+        } => {
+            format!("Annotation '{alias}' is a deprecated typing alias, prefer '{replacement}'.")
+        }
+        MissingType::DeprecatedTyping {
+            alias,
+            replacement: None,
+        } => format!("Annotation '{alias}' is a deprecated typing alias."),
+        MissingType::Lambda(name) => {
+            format!("Lambda '{name}' cannot be annotated, define it with def instead.")
+        }
+        MissingType::PartiallyAnnotated(name) => {
+            format!("Function '{name}' is only partially annotated, annotate all of its parameters or none.")
+        }
+        MissingType::ExplicitAny {
+            name,
+            return_type: true,
+        } => format!("Function '{name}' is annotated to return Any."),
+        MissingType::ExplicitAny {
+            name,
+            return_type: false,
+        } => format!("Parameter '{name}' is annotated as Any."),
+    }
+}
+
+/// Describes a finding without its location, along with the annotation suggested for
+/// it, if any.
+pub fn get_finding_message(position: &Position) -> String {
+    with_suggestion(
+        &get_short_message(&position.missing_type),
+        crate::json_report::kind_and_name(&position.missing_type).0,
+        position.suggestion.as_deref(),
+    )
+}
+
+/// Appends the suggested return type, if any, to a message without a location. The
+/// replacements suggested for other kinds of findings are part of their messages.
+pub fn with_suggestion(message: &str, kind: &str, suggestion: Option<&str>) -> String {
+    match suggestion.filter(|_| kind == "missing_return") {
+        Some(suggestion) => {
+            let message = message.strip_suffix('.').unwrap_or(message);
+            format!("{message}, suggested: -> {suggestion}.")
+        }
+        None => message.to_string(),
+    }
+}
+
+/// Renders one line per function, listing all of its missing hints, e.g.
+/// `src/x.py:12: function 'f' missing hints: parameters a, b; return type`. Findings
+/// that are not about a function get a line of their own. Lines end with `terminator`.
+/// Given the file's module, functions are named by their fully dotted name.
+pub fn get_aggregated_message_from_positions(
+    path: &str,
+    positions: &[Position],
+    terminator: char,
+    module: Option<&ModuleName>,
+) -> String {
+    let mut message = String::with_capacity(positions.len() * (path.len() + LINE_CAPACITY));
+
+    let same_function = |a: &Position, b: &Position| match (&a.function, &b.function) {
+        (Some(a), Some(b)) => a.group_id == b.group_id,
+        _ => false,
+    };
+
+    for group in positions.chunk_by(same_function) {
+        let Some(function) = &group[0].function else {
+            let _ = write!(
+                message,
+                "{path}:{}: {}{terminator}",
+                group[0].start.row + 1,
+                get_short_message(&group[0].missing_type)
+            );
+            continue;
+        };
+
+        let parameters: Vec<&str> = group
+            .iter()
+            .filter_map(|position| match &position.missing_type {
+                MissingType::Parameter(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        let missing_return = group
+            .iter()
+            .find(|position| matches!(position.missing_type, MissingType::Return(_)));
+
+        let mut missing = Vec::new();
+        match parameters.len() {
+            0 => {}
+            1 => missing.push(format!("parameter {}", parameters[0])),
+            _ => missing.push(format!("parameters {}", parameters.join(", "))),
+        }
+        if let Some(position) = missing_return {
+            missing.push(match &position.suggestion {
+                Some(suggestion) => format!("return type (-> {suggestion})"),
+                None => "return type".to_string(),
+            });
+        }
+
+        let _ = write!(
+            message,
+            "{path}:{}: function '{}' missing hints: {}{terminator}",
+            function.start.row + 1,
+            match module {
+                Some(module) => format!("{module}.{}", function.qualified_name),
+                None => function.name.clone(),
+            },
+            missing.join("; ")
+        );
+    }
+
+    message
+}
+
+/// Renders one `::warning` workflow command per finding, titled with the rule's code and
+/// escaping properties and messages as GitHub's runner expects.
+pub fn get_github_commands(path: &str, positions: &[Position]) -> String {
+    let file = escape_github_property(path);
+    let mut commands = String::with_capacity(positions.len() * (file.len() + LINE_CAPACITY));
+
+    for position in positions {
+        let _ = writeln!(
+            commands,
+            "::{} file={file},line={},col={},title={}::{}",
+            position.missing_type.severity().name(),
+            position.start.row + 1,
+            position.start.column + 1,
+            position.missing_type.code(),
+            escape_github_data(&get_finding_message(position))
+        );
+    }
+
+    commands
+}
+
+/// Renders one `task.logissue` logging command per finding, with the rule's code and
+/// escaping as the Azure Pipelines agent expects.
+pub fn get_azure_commands(path: &str, positions: &[Position]) -> String {
+    let file = escape_azure(path);
+    let mut commands = String::with_capacity(positions.len() * (file.len() + LINE_CAPACITY));
+
+    for position in positions {
+        let _ = writeln!(
+            commands,
+            "##vso[task.logissue type={};sourcepath={file};linenumber={};columnnumber={};code={};]{}",
+            position.missing_type.severity().name(),
+            position.start.row + 1,
+            position.start.column + 1,
+            position.missing_type.code(),
+            escape_azure(&get_finding_message(position))
+        );
+    }
+
+    commands
+}
+
+/// Escapes both properties and messages, which share the same rules.
+fn escape_azure(value: &str) -> String {
+    value
+        .replace('%', "%AZP25")
+        .replace(';', "%3B")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+        .replace(']', "%5D")
+}
+
+/// Renders one `inspection` service message per finding, typed by the rule's code.
+pub fn get_teamcity_messages(path: &str, positions: &[Position]) -> String {
+    let file = escape_teamcity(path);
+    let mut messages = String::with_capacity(positions.len() * (file.len() + LINE_CAPACITY));
+
+    for position in positions {
+        let _ = writeln!(
+            messages,
+            "##teamcity[inspection typeId='{}' message='{}' file='{file}' line='{}' SEVERITY='{}']",
+            position.missing_type.code(),
+            escape_teamcity(&get_finding_message(position)),
+            position.start.row + 1,
+            position.missing_type.severity().name().to_uppercase()
+        );
+    }
+
+    messages
+}
+
+/// Escapes attribute values with TeamCity's `|` escape character.
+fn escape_teamcity(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for character in value.chars() {
+        match character {
+            '|' => escaped.push_str("||"),
+            '\'' => escaped.push_str("|'"),
+            '\n' => escaped.push_str("|n"),
+            '\r' => escaped.push_str("|r"),
+            '[' => escaped.push_str("|["),
+            ']' => escaped.push_str("|]"),
+            '\u{0085}' => escaped.push_str("|x"),
+            '\u{2028}' => escaped.push_str("|l"),
+            '\u{2029}' => escaped.push_str("|p"),
+            _ => escaped.push(character),
+        }
+    }
+
+    escaped
+}
+
+fn escape_github_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_github_property(value: &str) -> String {
+    escape_github_data(value)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::tests::default_options;
+    use crate::{
+        check_files, create_python_parser, find_missing_types_positions, get_tree_from_source,
+        Rules,
+    };
+
+    fn positions_from_source(source: &str) -> Vec<Position> {
+        let mut parser = create_python_parser();
+        let (tree, source_code) = get_tree_from_source(&mut parser, source.as_bytes().to_vec());
+
+        find_missing_types_positions(&source_code, tree, &Rules::default()).0
+    }
+
+    #[test]
+    fn aggregate_test() {
+        let positions = positions_from_source(
+            "def f(a, b, c, d):\n    pass\n\n\ndef g(x: int, y):\n    pass\n\n\ndef h() -> int:\n    return 1\n\n\ndef k(x: int):\n    pass\n",
+        );
+        assert_eq!(positions.len(), 8);
+
+        let options = CheckOptions {
+            aggregate: true,
+            ..default_options()
+        };
+        assert_eq!(
+            render_file(Path::new("src/x.py"), &positions, &options),
+            "src/x.py:1: function 'f' missing hints: parameters a, b, c, d; return type\n\
+             src/x.py:5: function 'g' missing hints: parameter y; return type\n\
+             src/x.py:13: function 'k' missing hints: return type\n"
+        );
+    }
+
+    #[test]
+    fn text_layout_test() {
+        let positions = positions_from_source("def f(a, b: int):\n    pass\n");
+        let path = Path::new("src/x.py");
+
+        let options = default_options();
+        assert_eq!(
+            render_file(path, &positions, &options),
+            "File: src/x.py\n    \
+             Parameter 'a' in line 1 and column 7 is missing a type hint.\n    \
+             Function 'f' in line 1 and column 1 is missing a return type.\n"
+        );
+        assert_eq!(block_separator(&options), "\n");
+
+        let options = CheckOptions {
+            indent: 2,
+            ..default_options()
+        };
+        assert_eq!(
+            render_file(path, &positions, &options),
+            "File: src/x.py\n  \
+             Parameter 'a' in line 1 and column 7 is missing a type hint.\n  \
+             Function 'f' in line 1 and column 1 is missing a return type.\n"
+        );
+
+        let options = CheckOptions {
+            group_by_file: false,
+            ..default_options()
+        };
+        assert_eq!(
+            render_file(path, &positions, &options),
+            "src/x.py:1:7: Parameter 'a' is missing a type hint.\n\
+             src/x.py:1:1: Function 'f' is missing a return type.\n"
+        );
+        assert_eq!(block_separator(&options), "");
+
+        let options = CheckOptions {
+            format: OutputFormat::Github,
+            ..default_options()
+        };
+        assert_eq!(block_separator(&options), "");
+    }
+
+    #[test]
+    fn many_findings_test() {
+        let source: String = (0..10_000)
+            .map(|i| format!("def f{i}(a, b, c, d):\n    pass\n"))
+            .collect();
+        let positions = positions_from_source(&source);
+        assert_eq!(positions.len(), 50_000);
+
+        let block = render_file(Path::new("src/x.py"), &positions, &default_options());
+        assert_eq!(block.lines().count(), 50_001);
+        assert!(block
+            .ends_with("Function 'f9999' in line 19999 and column 1 is missing a return type.\n"));
+    }
+
+    #[test]
+    fn statistics_test() {
+        let stats = |path: &str, missing_parameters: usize, missing_returns: usize| FileStats {
+            path: PathBuf::from(path),
+            missing_parameters,
+            missing_returns,
+            findings: missing_parameters + missing_returns,
+            counts: crate::AnnotationCounts {
+                functions: 3,
+                slots: 6,
+            },
+            typed_package: None,
+        };
+        let files = [
+            stats("src/clean.py", 0, 0),
+            stats("src/b.py", 1, 1),
+            stats("src/legacy.py", 10, 3),
+            stats("src/a.py", 2, 0),
+        ];
+
+        assert_eq!(
+            render_statistics(&files, false),
+            "Findings  Parameters  Returns  Functions  File\n\
+             \x20     13          10        3          3  src/legacy.py\n\
+             \x20      2           2        0          3  src/a.py\n\
+             \x20      2           1        1          3  src/b.py\n"
+        );
+        assert_eq!(render_statistics(&files[..1], false), "");
+    }
+
+    #[test]
+    fn file_blocks_test() {
+        let dir = crate::tests::fixture_dir();
+        let files: Vec<PathBuf> = ["a.py", "b.py"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+        for file in &files {
+            std::fs::write(file, "def f(x):\n    pass\n").unwrap();
+        }
+
+        let (message, _) = check_files(files, &default_options());
+        let blocks: Vec<&str> = message.split("\n\n").collect();
+        assert_eq!(blocks.len(), 2, "{message}");
+        assert!(blocks.iter().all(|block| block.starts_with("File: ")));
+        assert!(message.ends_with(".\n"));
+        assert!(!message
+            .lines()
+            .any(|line| !line.is_empty() && line.trim().is_empty()));
+    }
+
+    #[test]
+    fn module_names_test() {
+        let dir = crate::tests::fixture_dir();
+        std::fs::create_dir_all(dir.path().join("billing")).unwrap();
+        std::fs::write(dir.path().join("billing/__init__.py"), "").unwrap();
+        let file = dir.path().join("billing/render.py");
+        std::fs::write(
+            &file,
+            "class Pdf:\n    def build(self, pages):\n        pass\n",
+        )
+        .unwrap();
+
+        let options = CheckOptions {
+            aggregate: true,
+            module_names: Some(Vec::new()),
+            ..default_options()
+        };
+        let (message, _) = check_files(vec![file], &options);
+        assert!(
+            message.ends_with(
+                ":2: function 'billing.render.Pdf.build' missing hints: parameter pages; return type\n"
+            ),
+            "{message}"
+        );
+
+        let options = CheckOptions {
+            module_names: Some(Vec::new()),
+            ..default_options()
+        };
+        let (message, _) = check_files(vec![PathBuf::from("test_file.py")], &options);
+        assert!(
+            message.starts_with("File: test_file.py (~test_file)\n"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn resolve_format_test() {
+        let unset = |_: &str| None;
+        let github = |variable: &str| (variable == "GITHUB_ACTIONS").then(|| "true".to_string());
+
+        assert_eq!(resolve_format(None, unset), (OutputFormat::Text, None));
+        assert_eq!(
+            resolve_format(None, github),
+            (OutputFormat::Github, Some("GITHUB_ACTIONS"))
+        );
+        assert_eq!(
+            resolve_format(Some(OutputFormat::Text), github),
+            (OutputFormat::Text, None)
+        );
+        assert_eq!(
+            resolve_format(Some(OutputFormat::Github), unset),
+            (OutputFormat::Github, None)
+        );
+        assert_eq!(
+            resolve_format(None, |variable: &str| (variable == "TF_BUILD")
+                .then(|| "True".to_string())),
+            (OutputFormat::Azure, Some("TF_BUILD"))
+        );
+        assert_eq!(
+            resolve_format(None, |_| Some("false".to_string())),
+            (OutputFormat::Text, None)
+        );
+        assert_eq!(
+            resolve_format(None, |variable: &str| (variable == "GITLAB_CI")
+                .then(|| "true".to_string())),
+            (OutputFormat::Text, None)
+        );
+    }
+
+    #[test]
+    fn github_test() {
+        let positions = positions_from_source("def f(a, b: int):\n    pass\n");
+        let options = CheckOptions {
+            format: OutputFormat::Github,
+            aggregate: true,
+            ..default_options()
+        };
+
+        assert_eq!(
+            render_file(Path::new("src/we,ird:dir/x.py"), &positions, &options),
+            "::warning file=src/we%2Cird%3Adir/x.py,line=1,col=7,title=THC001::Parameter 'a' is missing a type hint.\n\
+             ::warning file=src/we%2Cird%3Adir/x.py,line=1,col=1,title=THC002::Function 'f' is missing a return type.\n"
+        );
+        assert_eq!(escape_github_data("100%\ndone"), "100%25%0Adone");
+    }
+
+    /// The fixture tree's findings, rendered file by file in path order.
+    #[test]
+    fn teamcity_golden_test() {
+        let options = CheckOptions {
+            format: OutputFormat::Teamcity,
+            rules: Rules {
+                flag_quoted_annotations: true,
+                ..Default::default()
+            },
+            ..default_options()
+        };
+
+        let mut output = render_run_start(options.format);
+        let mut files: Vec<PathBuf> = walkdir::WalkDir::new("fixtures/teamcity")
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.into_path())
+            .collect();
+        files.sort();
+        for file in files {
+            output += &check_files(vec![file], &options).0;
+        }
+
+        assert_eq!(
+            output,
+            std::fs::read_to_string("fixtures/teamcity.expected").unwrap()
+        );
+        assert_eq!(escape_teamcity("a|b\r\nc"), "a||b|r|nc");
+    }
+
+    #[test]
+    fn azure_test() {
+        let positions = positions_from_source("def f(a) -> None:\n    pass\n");
+        let options = CheckOptions {
+            format: OutputFormat::Azure,
+            ..default_options()
+        };
+
+        assert_eq!(
+            render_file(Path::new("src/we;ird]/x.py"), &positions, &options),
+            "##vso[task.logissue type=warning;sourcepath=src/we%3Bird%5D/x.py;linenumber=1;columnnumber=7;code=THC001;]Parameter 'a' is missing a type hint.\n"
+        );
+        assert_eq!(escape_azure("50%;\r\n]"), "50%AZP25%3B%0D%0A%5D");
+
+        assert_eq!(
+            render_run_end(OutputFormat::Azure, 0, 0),
+            "##vso[task.complete result=Succeeded;]\n"
+        );
+        assert_eq!(
+            render_run_end(OutputFormat::Azure, 3, 0),
+            "##vso[task.complete result=SucceededWithIssues;]\n"
+        );
+        assert_eq!(
+            render_run_end(OutputFormat::Azure, 0, 2),
+            "##vso[task.complete result=Failed;]\n"
+        );
+        assert_eq!(render_run_end(OutputFormat::Github, 3, 0), "");
+    }
+}
@@ -0,0 +1,933 @@
+use crate::{compat, python_version::PythonVersion};
+
+/// Scopes in which `--flag-empty-collections` looks for assignments.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CollectionScope {
+    /// Module and class bodies, where the inferred type leaks to other modules.
+    TopLevel,
+    /// Function bodies as well.
+    All,
+}
+
+/// Returns the target of an unannotated assignment of an empty `[]`, `{}`, `()` or
+/// `set()`, if the assignment is in one of the given scopes. Augmented assignments are
+/// `augmented_assignment` nodes, so they never get here.
+pub fn empty_collection_target<'a>(
+    assignment: tree_sitter::Node,
+    source_code: &'a [u8],
+    scope: CollectionScope,
+) -> Option<&'a str> {
+    if assignment.child_by_field_name("type").is_some() {
+        return None;
+    }
+
+    let right = assignment.child_by_field_name("right")?;
+    if !is_empty_collection(right, source_code) {
+        return None;
+    }
+
+    if scope == CollectionScope::TopLevel && in_function(assignment) {
+        return None;
+    }
+
+    assignment
+        .child_by_field_name("left")?
+        .utf8_text(source_code)
+        .ok()
+}
+
+/// Returns the name a lambda is bound to by an unannotated assignment, such as
+/// `handler = lambda request: ...`. Annotated ones, such as `handler: Callable[...] =
+/// lambda request: ...`, are typed through their target.
+pub fn lambda_binding<'a>(assignment: tree_sitter::Node, source_code: &'a [u8]) -> Option<&'a str> {
+    if assignment.child_by_field_name("type").is_some() {
+        return None;
+    }
+
+    let left = assignment.child_by_field_name("left")?;
+    let right = assignment.child_by_field_name("right")?;
+    if left.kind() != "identifier" || right.kind() != "lambda" {
+        return None;
+    }
+
+    left.utf8_text(source_code).ok()
+}
+
+fn is_empty_collection(node: tree_sitter::Node, source_code: &[u8]) -> bool {
+    match node.kind() {
+        "list" | "dictionary" | "tuple" => node.named_child_count() == 0,
+        "call" => {
+            let is_set = node
+                .child_by_field_name("function")
+                .and_then(|function| function.utf8_text(source_code).ok())
+                == Some("set");
+            let has_no_arguments = node
+                .child_by_field_name("arguments")
+                .is_some_and(|arguments| arguments.named_child_count() == 0);
+
+            is_set && has_no_arguments
+        }
+        _ => false,
+    }
+}
+
+fn in_function(node: tree_sitter::Node) -> bool {
+    std::iter::successors(node.parent(), |node| node.parent())
+        .any(|ancestor| ancestor.kind() == "function_definition")
+}
+
+/// Generics reported by `--flag-bare-generics` when used without type arguments, along
+/// with their `typing` aliases. Each can also be spelled with a `typing.` prefix.
+const BARE_GENERICS: &[&str] = &[
+    "list",
+    "dict",
+    "set",
+    "tuple",
+    "frozenset",
+    "List",
+    "Dict",
+    "Set",
+    "Tuple",
+    "FrozenSet",
+    "Callable",
+    "Awaitable",
+];
+
+/// Returns the generic a `type` node consists of, if it is used bare and not allowed.
+/// Nested annotations such as `dict[str, list]` wrap each argument in a `type` node of
+/// its own, so every bare generic is found exactly once, at its own location.
+pub fn bare_generic<'a>(
+    annotation: tree_sitter::Node,
+    source_code: &'a [u8],
+    allowed: &[String],
+) -> Option<&'a str> {
+    let inner = annotation.named_child(0)?;
+    if !matches!(inner.kind(), "identifier" | "attribute") {
+        return None;
+    }
+
+    let spelling = inner.utf8_text(source_code).ok()?;
+    let name = spelling.strip_prefix("typing.").unwrap_or(spelling);
+
+    let is_allowed = allowed.iter().any(|allowed| allowed == name);
+
+    (BARE_GENERICS.contains(&name) && !is_allowed).then_some(spelling)
+}
+
+/// `typing` aliases made redundant by PEP 585 (3.9) and PEP 604 (3.10), along with the
+/// version from which their replacement can be used.
+const DEPRECATED_TYPING: &[(&str, PythonVersion)] = &[
+    ("List", PythonVersion::new(3, 9)),
+    ("Dict", PythonVersion::new(3, 9)),
+    ("Set", PythonVersion::new(3, 9)),
+    ("FrozenSet", PythonVersion::new(3, 9)),
+    ("Tuple", PythonVersion::new(3, 9)),
+    ("Type", PythonVersion::new(3, 9)),
+    ("Optional", PythonVersion::new(3, 10)),
+    ("Union", PythonVersion::new(3, 10)),
+];
+
+/// A deprecated `typing` alias along with the spelling that replaces it, unless it is a
+/// bare `Optional` or `Union`, which have no replacement without their arguments.
+#[derive(Debug, PartialEq)]
+pub struct DeprecatedAlias<'a> {
+    pub alias: &'a str,
+    pub replacement: Option<String>,
+}
+
+/// A name used in an annotation, possibly subscripted, such as `typing.List[int]`.
+struct AnnotationReference<'a> {
+    /// The name as spelled, such as `typing.List`.
+    spelling: &'a str,
+    /// The name without a `typing.` prefix.
+    name: &'a str,
+    arguments: Vec<&'a str>,
+}
+
+/// Reads the name and arguments of a `generic_type` node (`List[int]`), a `subscript`
+/// node within an annotation (`typing.List[int]`) or a `type` node for a bare name
+/// (`List`). The traversal reaches nested ones on its own, so rules built on this report
+/// each name once, at the subexpression using it.
+fn annotation_reference<'a>(
+    node: tree_sitter::Node,
+    source_code: &'a [u8],
+) -> Option<AnnotationReference<'a>> {
+    let (spelling, arguments): (_, Vec<tree_sitter::Node>) = match node.kind() {
+        "generic_type" => {
+            let parameters = node.named_child(1)?;
+            let mut cursor = parameters.walk();
+            let arguments = parameters.named_children(&mut cursor).collect();
+            (node.named_child(0)?, arguments)
+        }
+        "subscript" if in_annotation(node) => {
+            let mut cursor = node.walk();
+            let arguments = node
+                .children_by_field_name("subscript", &mut cursor)
+                .collect();
+            (node.child_by_field_name("value")?, arguments)
+        }
+        "type" => match node.named_child(0)? {
+            inner if matches!(inner.kind(), "identifier" | "attribute") => (inner, Vec::new()),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let spelling = spelling.utf8_text(source_code).ok()?;
+    let arguments = arguments
+        .iter()
+        .map(|argument| argument.utf8_text(source_code).unwrap_or_default())
+        .collect();
+
+    Some(AnnotationReference {
+        spelling,
+        name: spelling.strip_prefix("typing.").unwrap_or(spelling),
+        arguments,
+    })
+}
+
+/// Returns the deprecated alias the node spells, if any, along with its replacement.
+pub fn deprecated_alias<'a>(
+    node: tree_sitter::Node,
+    source_code: &'a [u8],
+    version: PythonVersion,
+) -> Option<DeprecatedAlias<'a>> {
+    let reference = annotation_reference(node, source_code)?;
+    let (_, since) = DEPRECATED_TYPING
+        .iter()
+        .find(|(deprecated, _)| *deprecated == reference.name)?;
+    if version < *since {
+        return None;
+    }
+
+    let name = reference.name;
+    let arguments = reference.arguments;
+    let replacement = match name {
+        "Optional" | "Union" if arguments.is_empty() => None,
+        "Optional" => Some(format!("{} | None", arguments.join(", "))),
+        "Union" => Some(arguments.join(" | ")),
+        _ if arguments.is_empty() => Some(name.to_lowercase()),
+        _ => Some(format!("{}[{}]", name.to_lowercase(), arguments.join(", "))),
+    };
+
+    Some(DeprecatedAlias {
+        alias: reference.spelling,
+        replacement,
+    })
+}
+
+/// How consistently functions need to be annotated, by `--strictness`. Each level
+/// reports everything the previous one does.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, PartialOrd, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Strictness {
+    /// Missing parameter and return types.
+    #[default]
+    Basic,
+    /// Functions annotating some of their parameters but not the others.
+    Consistent,
+    /// Parameters and return types annotated as `Any`.
+    Strict,
+}
+
+/// Whether the annotation is `Any` itself, spelled `Any` or `typing.Any`, rather than a
+/// type merely containing it.
+pub fn is_any(annotation: tree_sitter::Node, source_code: &[u8]) -> bool {
+    matches!(annotation.utf8_text(source_code), Ok("Any" | "typing.Any"))
+}
+
+/// Returns the name of an annotated parameter, along with its annotation, when it is
+/// annotated as `Any`.
+pub fn any_parameter<'tree, 'a>(
+    parameter: tree_sitter::Node<'tree>,
+    source_code: &'a [u8],
+) -> Option<(&'a str, tree_sitter::Node<'tree>)> {
+    let annotation = parameter.child_by_field_name("type")?;
+    if !is_any(annotation, source_code) {
+        return None;
+    }
+
+    let name = parameter
+        .child_by_field_name("name")
+        .or_else(|| parameter.named_child(0))?;
+
+    Some((name.utf8_text(source_code).ok()?, annotation))
+}
+
+/// Spelling preferred for optional annotations by `--annotation-style`.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationStyle {
+    /// `X | None`.
+    Union,
+    /// `Optional[X]`.
+    Optional,
+}
+
+/// An optional annotation spelled against the preferred style, as the offending
+/// subexpression along with its rewrite.
+#[derive(Debug, PartialEq)]
+pub struct StyleViolation<'a> {
+    pub found: &'a str,
+    pub replacement: String,
+}
+
+/// Returns the node's optional annotation if it is spelled against the preferred style.
+/// Only the outermost `|` of a chain such as `int | str | None` is reported.
+pub fn style_violation<'a>(
+    node: tree_sitter::Node,
+    source_code: &'a [u8],
+    style: AnnotationStyle,
+) -> Option<StyleViolation<'a>> {
+    let replacement = match style {
+        AnnotationStyle::Union => {
+            let reference = annotation_reference(node, source_code)?;
+            if reference.name != "Optional" || reference.arguments.len() != 1 {
+                return None;
+            }
+            format!("{} | None", reference.arguments[0])
+        }
+        AnnotationStyle::Optional => {
+            // Members of a `union_type` are wrapped in `type` nodes.
+            let mut parent = node.parent();
+            if parent.is_some_and(|parent| parent.kind() == "type") {
+                parent = parent.and_then(|parent| parent.parent());
+            }
+            let is_outermost = parent.is_none_or(|parent| !is_union(parent, source_code));
+            if !is_union(node, source_code) || !is_outermost || !in_annotation(node) {
+                return None;
+            }
+
+            let mut members = Vec::new();
+            union_members(node, source_code, &mut members);
+            let (nones, others): (Vec<_>, Vec<_>) = members
+                .into_iter()
+                .partition(|member| member.kind() == "none");
+            if nones.is_empty() || others.is_empty() {
+                return None;
+            }
+
+            let others: Vec<&str> = others
+                .iter()
+                .map(|member| member.utf8_text(source_code).unwrap_or_default())
+                .collect();
+            format!("Optional[{}]", others.join(" | "))
+        }
+    };
+
+    Some(StyleViolation {
+        found: node.utf8_text(source_code).ok()?,
+        replacement,
+    })
+}
+
+/// Whether the node is a `|` union, which the grammar parses as a `union_type` of `type`
+/// nodes or, with more than two members, as nested `binary_operator` expressions.
+fn is_union(node: tree_sitter::Node, source_code: &[u8]) -> bool {
+    match node.kind() {
+        "union_type" => true,
+        "binary_operator" => node
+            .child_by_field_name("operator")
+            .is_some_and(|operator| operator.utf8_text(source_code) == Ok("|")),
+        _ => false,
+    }
+}
+
+/// Flattens a union such as `(A | B) | C` into its members.
+fn union_members<'a>(
+    union: tree_sitter::Node<'a>,
+    source_code: &[u8],
+    members: &mut Vec<tree_sitter::Node<'a>>,
+) {
+    let mut cursor = union.walk();
+    for member in union.named_children(&mut cursor) {
+        let member = match member.kind() {
+            "type" => match member.named_child(0) {
+                Some(inner) => inner,
+                None => continue,
+            },
+            _ => member,
+        };
+
+        if is_union(member, source_code) {
+            union_members(member, source_code, members);
+        } else {
+            members.push(member);
+        }
+    }
+}
+
+/// The version from which `typing.Self` (PEP 673) can be used.
+const SELF_TYPE: PythonVersion = PythonVersion::new(3, 11);
+
+/// Suggests a return type for a method whose every value-returning statement is exactly
+/// `return self`: `Self`, or the quoted class name before Python 3.11. Returns and
+/// yields of nested functions, classes and lambdas do not count.
+pub fn self_return_suggestion(
+    function: tree_sitter::Node,
+    source_code: &[u8],
+    version: PythonVersion,
+) -> Option<String> {
+    if !compat::binds_first_parameter(function, source_code) {
+        return None;
+    }
+    let body = function.child_by_field_name("body")?;
+
+    let mut returns_self = false;
+    let mut pending = vec![body];
+    while let Some(node) = pending.pop() {
+        match node.kind() {
+            "function_definition" | "class_definition" | "lambda" => continue,
+            "yield" => return None,
+            "return_statement" => match node.named_child(0) {
+                None => {}
+                Some(value)
+                    if value.kind() == "identifier"
+                        && value.utf8_text(source_code) == Ok("self") =>
+                {
+                    returns_self = true
+                }
+                Some(_) => return None,
+            },
+            _ => {}
+        }
+
+        let mut cursor = node.walk();
+        pending.extend(node.named_children(&mut cursor));
+    }
+    if !returns_self {
+        return None;
+    }
+
+    if version >= SELF_TYPE {
+        return Some("Self".to_string());
+    }
+    let class = std::iter::successors(function.parent(), |node| node.parent())
+        .find(|ancestor| ancestor.kind() == "class_definition")?;
+    let name = class
+        .child_by_field_name("name")?
+        .utf8_text(source_code)
+        .ok()?;
+
+    Some(format!("\"{name}\""))
+}
+
+fn in_annotation(node: tree_sitter::Node) -> bool {
+    std::iter::successors(node.parent(), |node| node.parent())
+        .take_while(|ancestor| !matches!(ancestor.kind(), "function_definition" | "block"))
+        .any(|ancestor| ancestor.kind() == "type")
+}
+
+/// The version from which annotations are evaluated lazily (PEP 649), making quotes
+/// unnecessary even without the `__future__` import.
+const LAZY_ANNOTATIONS: PythonVersion = PythonVersion::new(3, 14);
+
+/// Whether quoted annotations can be unquoted in the module, as its annotations are not
+/// evaluated at definition time.
+pub fn annotations_are_deferred(
+    module: tree_sitter::Node,
+    source_code: &[u8],
+    version: PythonVersion,
+) -> bool {
+    if version >= LAZY_ANNOTATIONS {
+        return true;
+    }
+
+    let mut cursor = module.walk();
+    let deferred = module
+        .named_children(&mut cursor)
+        .filter(|statement| statement.kind() == "future_import_statement")
+        .any(|statement| {
+            let mut cursor = statement.walk();
+            let imports_annotations = statement
+                .children_by_field_name("name", &mut cursor)
+                .any(|name| name.utf8_text(source_code) == Ok("annotations"));
+            imports_annotations
+        });
+    deferred
+}
+
+/// Returns the contents of a `type` node consisting of a string, if they are a valid
+/// expression. Strings that are not, such as prose, are left alone.
+pub fn quoted_annotation<'a>(
+    annotation: tree_sitter::Node,
+    source_code: &'a [u8],
+) -> Option<&'a str> {
+    let string = annotation.named_child(0)?;
+    if string.kind() != "string" {
+        return None;
+    }
+
+    let text = string.utf8_text(source_code).ok()?;
+    let quote_start = text.find(['"', '\''])?;
+    if text[..quote_start].contains(['f', 'F', 'b', 'B']) {
+        return None;
+    }
+
+    let quoted = &text[quote_start..];
+    let quotes = if quoted.starts_with("\"\"\"") || quoted.starts_with("'''") {
+        3
+    } else {
+        1
+    };
+    let contents = quoted.get(quotes..quoted.len().checked_sub(quotes)?)?;
+
+    is_expression(contents).then_some(contents)
+}
+
+fn is_expression(contents: &str) -> bool {
+    let mut parser = crate::create_python_parser();
+    let Some(tree) = parser.parse(contents.trim(), None) else {
+        return false;
+    };
+
+    let module = tree.root_node();
+    !module.has_error()
+        && module.named_child_count() == 1
+        && module
+            .named_child(0)
+            .is_some_and(|statement| statement.kind() == "expression_statement")
+}
+
+/// Returns the first node that does not parse, either an `ERROR` node or a zero-width
+/// `MISSING` one that the parser inserted to recover.
+pub fn first_syntax_error(root: tree_sitter::Node) -> Option<tree_sitter::Node> {
+    if !root.has_error() {
+        return None;
+    }
+
+    tree_sitter_traversal::traverse(root.walk(), tree_sitter_traversal::Order::Pre)
+        .find(|node| node.is_error() || node.is_missing())
+}
+
+/// Whether the node lies in a region the parser could not make sense of, as a part of
+/// an `ERROR` node or as a function definition containing one, where findings would be
+/// made up.
+pub fn in_error_region(node: tree_sitter::Node) -> bool {
+    if node.kind() == "function_definition" && node.has_error() {
+        return true;
+    }
+
+    let mut current = Some(node);
+    while let Some(node) = current {
+        if node.is_error() {
+            return true;
+        }
+        current = node.parent();
+    }
+
+    false
+}
+
+/// Describes the syntax error for its finding: the kind of token the parser expected, or
+/// the start of the text it could not parse.
+pub fn syntax_error_text(node: tree_sitter::Node, source_code: &[u8]) -> (String, bool) {
+    /// Characters of unparsable text quoted in the finding.
+    const MAX_QUOTED: usize = 30;
+
+    if node.is_missing() {
+        return (node.kind().to_string(), true);
+    }
+
+    let text = String::from_utf8_lossy(&source_code[node.byte_range()]);
+    let first_line = text.lines().map(str::trim).find(|line| !line.is_empty());
+    let first_line = first_line.unwrap_or_default();
+    let quoted = match first_line.char_indices().nth(MAX_QUOTED) {
+        Some((end, _)) => format!("{}...", &first_line[..end]),
+        None => first_line.to_string(),
+    };
+
+    (quoted, false)
+}
+
+/// Returns the type parameter list or `type` statement (PEP 695) the node introduces,
+/// along with a description of that syntax.
+pub fn type_parameter_syntax(node: tree_sitter::Node) -> Option<(tree_sitter::Node, &'static str)> {
+    match node.kind() {
+        "type_alias_statement" => Some((node, "type statement")),
+        "function_definition" | "class_definition" => Some((
+            node.child_by_field_name("type_parameters")?,
+            "type parameter list",
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::python_version::PythonVersion;
+    use crate::{
+        create_python_parser, find_missing_types_positions, get_tree_from_source, MissingType,
+        Rules,
+    };
+
+    use super::*;
+
+    /// Findings of the opt-in rules as `(line, column, kind)`, without the parameter and
+    /// return type findings.
+    fn flagged(source: &str, rules: Rules) -> Vec<(usize, usize, MissingType)> {
+        let mut parser = create_python_parser();
+        let (tree, source_code) = get_tree_from_source(&mut parser, source.as_bytes().to_vec());
+
+        find_missing_types_positions(&source_code, tree, &rules)
+            .0
+            .into_iter()
+            .filter(|position| position.function.is_none())
+            .map(|position| {
+                (
+                    position.start.row + 1,
+                    position.start.column + 1,
+                    position.missing_type,
+                )
+            })
+            .collect()
+    }
+
+    fn empty_collections(scope: CollectionScope) -> Rules {
+        Rules {
+            empty_collections: Some(scope),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn empty_collections_test() {
+        let source = "\
+items = []
+cache = {}
+seen = set()
+pair = ()
+typed: list[int] = []
+full = [1]
+calls = set([1])
+count = 0
+count += 1
+
+class Registry:
+    entries = {}
+
+    def __init__(self) -> None:
+        self.local = []
+        buffer: list[str] = []
+";
+
+        let target = |name: &str| MissingType::EmptyCollection(name.to_string());
+        assert_eq!(
+            flagged(source, empty_collections(CollectionScope::TopLevel)),
+            vec![
+                (1, 1, target("items")),
+                (2, 1, target("cache")),
+                (3, 1, target("seen")),
+                (4, 1, target("pair")),
+                (12, 5, target("entries")),
+            ]
+        );
+        assert_eq!(
+            flagged(source, empty_collections(CollectionScope::All)).last(),
+            Some(&(15, 9, target("self.local")))
+        );
+    }
+
+    #[test]
+    fn lambda_binding_test() {
+        let source = "\
+handler = lambda request: request.body
+typed: Callable[[Request], bytes] = lambda request: request.body
+self.callback = lambda: None
+key = sorted(items, key=lambda item: item.name)
+
+def f() -> None:
+    inner = lambda: 0
+";
+        let rules = Rules {
+            check_lambdas: true,
+            ..Default::default()
+        };
+
+        let lambda = |name: &str| MissingType::Lambda(name.to_string());
+        assert_eq!(
+            flagged(source, rules),
+            vec![(1, 1, lambda("handler")), (7, 5, lambda("inner"))]
+        );
+        assert_eq!(flagged(source, Rules::default()), vec![]);
+    }
+
+    #[test]
+    fn strictness_test() {
+        let source = "\
+def partial(a: int, b):
+    def nested(c, d):
+        ...
+
+def consistent(a: int, *args: typing.Any, **kwargs: list[Any]) -> int: ...
+
+def escapes(a, b=1) -> Any: ...
+
+class A:
+    def method(self, a: int): ...
+";
+        let flagged = |strictness: Strictness| {
+            let mut parser = create_python_parser();
+            let (tree, source_code) = get_tree_from_source(&mut parser, source.as_bytes().to_vec());
+            let rules = Rules {
+                strictness,
+                ..Default::default()
+            };
+
+            find_missing_types_positions(&source_code, tree, &rules)
+                .0
+                .into_iter()
+                .filter(|position| {
+                    matches!(
+                        position.missing_type,
+                        MissingType::PartiallyAnnotated(_) | MissingType::ExplicitAny { .. }
+                    )
+                })
+                .map(|position| {
+                    (
+                        position.start.row + 1,
+                        position.start.column + 1,
+                        position.missing_type,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let any = |name: &str, return_type: bool| MissingType::ExplicitAny {
+            name: name.to_string(),
+            return_type,
+        };
+
+        assert_eq!(flagged(Strictness::Basic), vec![]);
+        assert_eq!(
+            flagged(Strictness::Consistent),
+            vec![(1, 1, MissingType::PartiallyAnnotated("partial".to_string()))]
+        );
+        assert_eq!(
+            flagged(Strictness::Strict),
+            vec![
+                (1, 1, MissingType::PartiallyAnnotated("partial".to_string())),
+                (5, 31, any("*args", false)),
+                (7, 24, any("escapes", true)),
+            ]
+        );
+    }
+
+    #[test]
+    fn bare_generics_test() {
+        let source = "\
+def f(a: dict[str, list], b: typing.List, c: Callable[[int], str] | None) -> tuple: ...
+x: list = []
+y: 'list'
+z: collections.Callable
+w: typing.Awaitable
+";
+        let rules = Rules {
+            flag_bare_generics: true,
+            allowed_bare_generics: vec!["Awaitable".to_string()],
+            ..Default::default()
+        };
+
+        let generic = |name: &str| MissingType::BareGeneric(name.to_string());
+        assert_eq!(
+            flagged(source, rules),
+            vec![
+                (1, 20, generic("list")),
+                (1, 30, generic("typing.List")),
+                (1, 78, generic("tuple")),
+                (2, 4, generic("list")),
+            ]
+        );
+    }
+
+    #[test]
+    fn deprecated_typing_test() {
+        let source = "\
+def f(a: Optional[List[int]], b: typing.Dict[str, int], c: Union[int, str]) -> Tuple: ...
+x: Type[A] = A
+y = items[0]
+z: Optional = None
+w: typing.Union = 0
+";
+        let rules = |version: PythonVersion| Rules {
+            flag_deprecated_typing: true,
+            python_version: version,
+            ..Default::default()
+        };
+        let deprecated = |alias: &str, replacement: &str| MissingType::DeprecatedTyping {
+            alias: alias.to_string(),
+            replacement: Some(replacement.to_string()),
+        };
+        let bare = |alias: &str| MissingType::DeprecatedTyping {
+            alias: alias.to_string(),
+            replacement: None,
+        };
+
+        assert_eq!(
+            flagged(source, rules(PythonVersion::new(3, 10))),
+            vec![
+                (1, 10, deprecated("Optional", "List[int] | None")),
+                (1, 19, deprecated("List", "list[int]")),
+                (1, 34, deprecated("typing.Dict", "dict[str, int]")),
+                (1, 60, deprecated("Union", "int | str")),
+                (1, 80, deprecated("Tuple", "tuple")),
+                (2, 4, deprecated("Type", "type[A]")),
+                (4, 4, bare("Optional")),
+                (5, 4, bare("typing.Union")),
+            ]
+        );
+        assert_eq!(flagged(source, rules(PythonVersion::new(3, 9))).len(), 4);
+        assert!(flagged(source, rules(PythonVersion::new(3, 8))).is_empty());
+    }
+
+    #[test]
+    fn quoted_annotations_test() {
+        let source = "\
+from __future__ import annotations
+
+def f(a: \"Node\", b: list[\"Node\"], c: \"the node id\", d: f\"{x}\") -> 'Node': ...
+";
+        let rules = |version: PythonVersion| Rules {
+            flag_quoted_annotations: true,
+            python_version: version,
+            ..Default::default()
+        };
+        let quoted = |contents: &str| MissingType::QuotedAnnotation(contents.to_string());
+
+        assert_eq!(
+            flagged(source, rules(PythonVersion::new(3, 10))),
+            vec![
+                (3, 10, quoted("Node")),
+                (3, 26, quoted("Node")),
+                (3, 67, quoted("Node")),
+            ]
+        );
+
+        let without_import = source.replace("from __future__ import annotations", "");
+        assert!(flagged(&without_import, rules(PythonVersion::new(3, 10))).is_empty());
+        assert_eq!(
+            flagged(&without_import, rules(PythonVersion::new(3, 14))).len(),
+            3
+        );
+    }
+
+    #[test]
+    fn annotation_style_test() {
+        let source = "\
+def f(a: Optional[int], b: int | str | None, c: typing.Optional[list[int]]) -> int | str: ...
+";
+        let rules = |style: AnnotationStyle| Rules {
+            annotation_style: Some(style),
+            ..Default::default()
+        };
+        let violation = |found: &str, replacement: &str| MissingType::AnnotationStyle {
+            found: found.to_string(),
+            replacement: replacement.to_string(),
+        };
+
+        assert_eq!(
+            flagged(source, rules(AnnotationStyle::Union)),
+            vec![
+                (1, 10, violation("Optional[int]", "int | None")),
+                (
+                    1,
+                    49,
+                    violation("typing.Optional[list[int]]", "list[int] | None")
+                ),
+            ]
+        );
+        assert_eq!(
+            flagged(source, rules(AnnotationStyle::Optional)),
+            vec![(1, 28, violation("int | str | None", "Optional[int | str]"))]
+        );
+    }
+
+    #[test]
+    fn self_return_suggestion_test() {
+        let source = "\
+class Builder:
+    def with_name(self, name: str):
+        self.name = name
+        return self
+
+    def maybe(self, flag: bool):
+        if flag:
+            return self
+        return None
+
+    def early(self):
+        if self.name:
+            return
+        def inner():
+            return 1
+        return self
+
+    def plain(self):
+        pass
+
+    @staticmethod
+    def make(self):
+        return self
+";
+        let suggestions = |version: PythonVersion| -> Vec<(String, Option<String>)> {
+            let mut parser = create_python_parser();
+            let (tree, source_code) = get_tree_from_source(&mut parser, source.as_bytes().to_vec());
+            let rules = Rules {
+                suggest_self: true,
+                python_version: version,
+                ..Default::default()
+            };
+
+            find_missing_types_positions(&source_code, tree, &rules)
+                .0
+                .into_iter()
+                .filter(|position| matches!(position.missing_type, MissingType::Return(_)))
+                .map(|position| (position.function.unwrap().name, position.suggestion))
+                .collect()
+        };
+
+        let self_type = Some("Self".to_string());
+        assert_eq!(
+            suggestions(PythonVersion::new(3, 11)),
+            vec![
+                ("with_name".to_string(), self_type.clone()),
+                ("maybe".to_string(), None),
+                ("early".to_string(), self_type),
+                ("inner".to_string(), None),
+                ("plain".to_string(), None),
+                ("make".to_string(), None),
+            ]
+        );
+        assert_eq!(
+            suggestions(PythonVersion::new(3, 10))[0].1,
+            Some("\"Builder\"".to_string())
+        );
+    }
+
+    #[test]
+    fn type_parameter_syntax_test() {
+        let source = "\
+def first[T](items: list[T]) -> T: ...
+class Box[T]: ...
+type Pair = tuple[int, int]
+";
+        let rules = |version: PythonVersion| Rules {
+            python_version: version,
+            ..Default::default()
+        };
+        let newer = |syntax: &'static str| MissingType::NewerSyntax {
+            syntax,
+            since: PythonVersion::new(3, 12),
+        };
+
+        assert_eq!(
+            flagged(source, rules(PythonVersion::new(3, 11))),
+            vec![
+                (1, 10, newer("type parameter list")),
+                (2, 10, newer("type parameter list")),
+                (3, 1, newer("type statement")),
+            ]
+        );
+        assert!(flagged(source, rules(PythonVersion::new(3, 12))).is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use crate::{sample::Sampling, Summary};
+
+/// High-level numbers about a run, written as a build artifact by `--summary-json`.
+#[derive(Debug, serde::Serialize)]
+pub struct RunSummary {
+    pub version: &'static str,
+    pub options_fingerprint: String,
+    pub exit_status: i32,
+    pub files_checked: usize,
+    pub findings: usize,
+    pub parameter_findings: usize,
+    pub return_findings: usize,
+    /// Share of checked parameters and return types that are annotated, `null` when
+    /// there was nothing to annotate.
+    pub coverage: Option<f64>,
+    pub duration_seconds: f64,
+    /// How many files were checked out of how many with --sample, in which case the
+    /// other numbers only cover the sampled files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample: Option<Sampling>,
+}
+
+impl RunSummary {
+    pub fn new(
+        summary: &Summary,
+        options_fingerprint: String,
+        exit_status: i32,
+        duration: Duration,
+    ) -> Self {
+        RunSummary {
+            version: env!("CARGO_PKG_VERSION"),
+            options_fingerprint,
+            exit_status,
+            files_checked: summary.files_checked,
+            findings: summary.findings,
+            parameter_findings: summary.parameter_findings(),
+            return_findings: summary.return_findings(),
+            coverage: summary.coverage(),
+            duration_seconds: duration.as_secs_f64(),
+            sample: None,
+        }
+    }
+
+    #[cfg(feature = "formats-json")]
+    pub fn render(&self) -> String {
+        let mut rendered =
+            serde_json::to_string_pretty(self).expect("Run summaries should be serializable.");
+        rendered.push('\n');
+
+        rendered
+    }
+
+    #[cfg(not(feature = "formats-json"))]
+    pub fn render(&self) -> String {
+        unreachable!("--summary-json should have been rejected at startup.");
+    }
+}
+
+#[cfg(all(test, feature = "formats-json"))]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{AnnotationCounts, FileStats};
+
+    #[test]
+    fn render_test() {
+        let summary = Summary {
+            files_checked: 2,
+            findings: 3,
+            files: vec![FileStats {
+                path: PathBuf::from("app.py"),
+                missing_parameters: 2,
+                missing_returns: 1,
+                findings: 3,
+                counts: AnnotationCounts {
+                    functions: 2,
+                    slots: 4,
+                },
+                typed_package: None,
+            }],
+            ..Default::default()
+        };
+
+        let rendered = RunSummary::new(
+            &summary,
+            "0123456789abcdef".to_string(),
+            0,
+            Duration::from_millis(250),
+        )
+        .render();
+        let value: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+
+        assert_eq!(value["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(value["options_fingerprint"], "0123456789abcdef");
+        assert_eq!(value["exit_status"], 0);
+        assert_eq!(value["files_checked"], 2);
+        assert_eq!(value["findings"], 3);
+        assert_eq!(value["parameter_findings"], 2);
+        assert_eq!(value["return_findings"], 1);
+        assert_eq!(value["coverage"], 0.25);
+        assert_eq!(value["duration_seconds"], 0.25);
+        assert!(value.get("sample").is_none());
+    }
+}
@@ -0,0 +1,180 @@
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use crate::Summary;
+
+/// How many of the walked files `--sample` checks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleSize {
+    Count(usize),
+    Percent(f64),
+}
+
+impl SampleSize {
+    /// Number of files to pick out of `total`, keeping at least one for a non-empty
+    /// percentage.
+    fn of(self, total: usize) -> usize {
+        match self {
+            SampleSize::Count(count) => count.min(total),
+            SampleSize::Percent(percent) => {
+                ((total as f64 * percent / 100.0).ceil() as usize).clamp(total.min(1), total)
+            }
+        }
+    }
+}
+
+impl FromStr for SampleSize {
+    type Err = String;
+
+    fn from_str(size: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{size}' is not a number of files or a percentage such as 5%");
+
+        match size.trim().strip_suffix('%') {
+            Some(percent) => {
+                let percent: f64 = percent.trim().parse().map_err(|_| invalid())?;
+                if !(percent > 0.0 && percent <= 100.0) {
+                    return Err(format!("{size} is not a percentage between 0 and 100"));
+                }
+                Ok(SampleSize::Percent(percent))
+            }
+            None => Ok(SampleSize::Count(
+                size.trim().parse().map_err(|_| invalid())?,
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SampleSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SampleSize::Count(count) => write!(f, "{count}"),
+            SampleSize::Percent(percent) => write!(f, "{percent}%"),
+        }
+    }
+}
+
+impl serde::Serialize for SampleSize {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Picks files uniformly at random. The choice only depends on the seed and on the set
+/// of files, not on the order in which they were walked.
+pub fn sample(mut files: Vec<PathBuf>, size: SampleSize, seed: u64) -> Vec<PathBuf> {
+    files.sort();
+    let picked = size.of(files.len());
+
+    // A partial Fisher-Yates shuffle, moving the picked files to the front.
+    let mut state = seed;
+    for index in 0..picked {
+        let remaining = (files.len() - index) as u64;
+        let offset = (split_mix(&mut state) % remaining) as usize;
+        files.swap(index, index + offset);
+    }
+    files.truncate(picked);
+
+    files
+}
+
+/// A seed for runs without `--seed`, which is shown so that they can be reproduced.
+pub fn random_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+}
+
+/// SplitMix64, which is plenty for picking files.
+fn split_mix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+    z ^ (z >> 31)
+}
+
+/// Which files a sampled run checked, as reported in summaries.
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct Sampling {
+    pub sampled_files: usize,
+    pub total_files: usize,
+    pub seed: u64,
+}
+
+impl Sampling {
+    /// States that the results are sampled, extrapolating the findings to all files.
+    pub fn render(&self, summary: &Summary) -> String {
+        let estimate = if self.sampled_files == 0 {
+            0
+        } else {
+            (summary.findings as f64 * self.total_files as f64 / self.sampled_files as f64).round()
+                as usize
+        };
+        let coverage = summary
+            .coverage()
+            .map(|coverage| format!(", coverage {:.1}%", coverage * 100.0))
+            .unwrap_or_default();
+
+        format!(
+            "Sampled results: checked {} of {} file(s) (--seed {}), found {} finding(s){coverage}; about {estimate} finding(s) expected across all files.\n",
+            self.sampled_files, self.total_files, self.seed, summary.findings
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        assert_eq!("200".parse(), Ok(SampleSize::Count(200)));
+        assert_eq!("5%".parse(), Ok(SampleSize::Percent(5.0)));
+        assert_eq!("0.5 %".parse(), Ok(SampleSize::Percent(0.5)));
+        assert!("150%".parse::<SampleSize>().is_err());
+        assert!("0%".parse::<SampleSize>().is_err());
+        assert!("some".parse::<SampleSize>().is_err());
+    }
+
+    #[test]
+    fn sample_test() {
+        let files: Vec<PathBuf> = (0..100)
+            .map(|index| PathBuf::from(format!("src/module_{index}.py")))
+            .collect();
+        let mut reversed = files.clone();
+        reversed.reverse();
+
+        let picked = sample(files.clone(), SampleSize::Count(10), 42);
+        assert_eq!(picked.len(), 10);
+        assert_eq!(picked, sample(reversed, SampleSize::Count(10), 42));
+        assert_ne!(picked, sample(files.clone(), SampleSize::Count(10), 43));
+
+        let mut unique = picked.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), 10);
+
+        assert_eq!(sample(files.clone(), SampleSize::Percent(2.5), 1).len(), 3);
+        assert_eq!(sample(files.clone(), SampleSize::Count(500), 1).len(), 100);
+        assert_eq!(sample(Vec::new(), SampleSize::Percent(5.0), 1).len(), 0);
+    }
+
+    #[test]
+    fn render_test() {
+        let summary = Summary {
+            files_checked: 10,
+            findings: 7,
+            ..Default::default()
+        };
+        let sampling = Sampling {
+            sampled_files: 10,
+            total_files: 1000,
+            seed: 42,
+        };
+
+        assert_eq!(
+            sampling.render(&summary),
+            "Sampled results: checked 10 of 1000 file(s) (--seed 42), found 7 finding(s); about 700 finding(s) expected across all files.\n"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{config::stable_hash, paths::display_path};
+
+/// One of `count` disjoint parts of the checked files, numbered from 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shard {
+    index: u64,
+    count: u64,
+}
+
+impl Shard {
+    /// Keeps the files assigned to this shard. Files are assigned by a stable hash of
+    /// their path relative to the directory they were found in, so that the assignment
+    /// does not depend on the walk order or on where the tree is checked out.
+    pub fn select(self, files: Vec<PathBuf>, targets: &[PathBuf]) -> Vec<PathBuf> {
+        files
+            .into_iter()
+            .filter(|file| {
+                let key = assignment_key(file, targets);
+                stable_hash(key.as_bytes()) % self.count == self.index - 1
+            })
+            .collect()
+    }
+}
+
+fn assignment_key(file: &Path, targets: &[PathBuf]) -> String {
+    let relative = targets
+        .iter()
+        .filter(|target| target.is_dir())
+        .find_map(|target| file.strip_prefix(target).ok())
+        .unwrap_or(file);
+
+    display_path(relative, false)
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(shard: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{shard}' is not a shard such as 2/4");
+
+        let (index, count) = shard.trim().split_once('/').ok_or_else(invalid)?;
+        let index: u64 = index.trim().parse().map_err(|_| invalid())?;
+        let count: u64 = count.trim().parse().map_err(|_| invalid())?;
+        if index == 0 || index > count {
+            return Err(format!(
+                "shard {index} does not exist, shards of {count} are numbered from 1 to {count}"
+            ));
+        }
+
+        Ok(Shard { index, count })
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+impl serde::Serialize for Shard {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::tests::{default_options, fixture_dir};
+    use crate::{check_files, collect_files};
+
+    #[test]
+    fn parse_test() {
+        assert_eq!("2/4".parse(), Ok(Shard { index: 2, count: 4 }));
+        assert!("0/4".parse::<Shard>().is_err());
+        assert!("5/4".parse::<Shard>().is_err());
+        assert!("2".parse::<Shard>().is_err());
+        assert_eq!(Shard { index: 1, count: 3 }.to_string(), "1/3");
+    }
+
+    #[test]
+    fn shards_partition_files_test() {
+        let dir = fixture_dir();
+        std::fs::create_dir_all(dir.path().join("pkg/sub")).unwrap();
+        for index in 0..30 {
+            let directory = ["", "pkg/", "pkg/sub/"][index % 3];
+            std::fs::write(
+                dir.path().join(format!("{directory}module_{index}.py")),
+                "def f(x): ...\n",
+            )
+            .unwrap();
+        }
+
+        let targets = [dir.path().to_path_buf()];
+        let (files, _) = collect_files(&targets, &[], &["py".to_string()], |_, _| {});
+        let (_, unsharded) = check_files(files.clone(), &default_options());
+
+        let mut seen = HashSet::new();
+        let mut findings = 0;
+        for index in 1..=4 {
+            let shard = Shard { index, count: 4 };
+            let selected = shard.select(files.clone(), &targets);
+            for file in &selected {
+                assert!(
+                    seen.insert(file.clone()),
+                    "{} is in two shards",
+                    file.display()
+                );
+            }
+
+            findings += check_files(selected, &default_options()).1.findings;
+        }
+
+        assert_eq!(seen, files.into_iter().collect());
+        assert_eq!(findings, unsharded.findings);
+
+        let elsewhere = fixture_dir();
+        assert_eq!(
+            assignment_key(&dir.path().join("pkg/module_1.py"), &targets),
+            assignment_key(
+                &elsewhere.path().join("pkg/module_1.py"),
+                &[elsewhere.path().to_path_buf()]
+            )
+        );
+    }
+}
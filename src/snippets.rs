@@ -0,0 +1,82 @@
+use tree_sitter::Point;
+
+use crate::{
+    create_python_parser, find_missing_types_positions, AnnotationCounts, Position, Rules,
+};
+
+/// Python code embedded in another file, such as a doctest or a documentation code
+/// block, along with where each of its lines starts in that file.
+#[derive(Debug, Default, PartialEq)]
+pub struct Snippet {
+    pub source: String,
+    /// Row and column in the file of the first character of each line.
+    pub origins: Vec<Point>,
+}
+
+impl Snippet {
+    pub fn push(&mut self, code: &str, origin: Point) {
+        self.source += code;
+        self.source.push('\n');
+        self.origins.push(origin);
+    }
+
+    /// The point in the file of a point in the snippet.
+    fn locate(&self, point: Point) -> Point {
+        let origin = self.origins[point.row.min(self.origins.len() - 1)];
+
+        Point {
+            row: origin.row,
+            column: origin.column + point.column,
+        }
+    }
+}
+
+/// Findings in the snippets of a file, located in the file itself.
+#[derive(Debug, Default)]
+pub struct SnippetFindings {
+    pub positions: Vec<Position>,
+    pub counts: AnnotationCounts,
+    /// Lines, starting at 1, of the snippets that do not parse.
+    pub unparsable: Vec<usize>,
+}
+
+/// Checks each snippet on its own, so that one that does not parse is reported without
+/// hiding the others. Functions are numbered across the snippets, in order.
+pub fn check_snippets(
+    snippets: impl IntoIterator<Item = Snippet>,
+    rules: &Rules,
+) -> SnippetFindings {
+    let mut findings = SnippetFindings::default();
+    let mut parser = create_python_parser();
+
+    for snippet in snippets {
+        if snippet.origins.is_empty() {
+            continue;
+        }
+        let (tree, source) =
+            crate::get_tree_from_source(&mut parser, snippet.source.as_bytes().to_vec());
+        if tree.root_node().has_error() {
+            findings.unparsable.push(snippet.origins[0].row + 1);
+            continue;
+        }
+
+        let first_group_id = findings.counts.functions;
+        let (positions, counts) = find_missing_types_positions(&source, tree, rules);
+        findings.counts.functions += counts.functions;
+        findings.counts.slots += counts.slots;
+
+        findings
+            .positions
+            .extend(positions.into_iter().map(|mut position| {
+                position.start = snippet.locate(position.start);
+                position.end = snippet.locate(position.end);
+                if let Some(function) = &mut position.function {
+                    function.group_id += first_group_id;
+                    function.start = snippet.locate(function.start);
+                }
+                position
+            }));
+    }
+
+    findings
+}
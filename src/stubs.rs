@@ -0,0 +1,108 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::{create_python_parser, find_missing_types_positions, get_tree_from_source, Rules};
+
+/// The stub next to a module, such as `foo.pyi` for `foo.py`, if there is one.
+pub fn adjacent_stub(module: &Path) -> Option<PathBuf> {
+    let stub = module.with_extension("pyi");
+
+    (stub != module && stub.is_file()).then_some(stub)
+}
+
+/// Dotted name of a function or class within its module, such as `Greeter.greet`,
+/// which is how definitions are matched between a module and its stub.
+pub fn qualified_name(definition: tree_sitter::Node, source_code: &[u8]) -> String {
+    let mut names = Vec::new();
+
+    let mut current = Some(definition);
+    while let Some(node) = current {
+        if matches!(node.kind(), "function_definition" | "class_definition") {
+            if let Some(name) = node
+                .child_by_field_name("name")
+                .and_then(|name| name.utf8_text(source_code).ok())
+            {
+                names.push(name);
+            }
+        }
+        current = node.parent();
+    }
+    names.reverse();
+
+    names.join(".")
+}
+
+/// Qualified names of the functions the stub fully annotates under the given rules. All
+/// overloads of a function need to be annotated for it to count.
+pub fn annotated_functions(stub: &Path, rules: &Rules) -> HashSet<String> {
+    let Ok(contents) = std::fs::read(stub) else {
+        return HashSet::new();
+    };
+
+    let mut parser = create_python_parser();
+    let (tree, source_code) = get_tree_from_source(&mut parser, contents);
+
+    let mut functions = HashSet::new();
+    let walk = tree.walk();
+    for node in tree_sitter_traversal::traverse(walk, tree_sitter_traversal::Order::Pre) {
+        if node.kind() == "function_definition" {
+            functions.insert(qualified_name(node, &source_code));
+        }
+    }
+
+    let (positions, _) = find_missing_types_positions(&source_code, tree, rules);
+    for function in positions
+        .iter()
+        .filter_map(|position| position.function.as_ref())
+    {
+        functions.remove(&function.qualified_name);
+    }
+
+    functions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{default_options, fixture_dir};
+    use crate::{check_files, CheckOptions};
+
+    #[test]
+    fn prefer_stubs_test() {
+        let dir = fixture_dir();
+        let module = dir.path().join("greet.py");
+        std::fs::write(
+            &module,
+            "def greet(name):\n    pass\n\n\nclass Greeter:\n    def greet(self, name):\n        pass\n\n    def wave(self, name):\n        pass\n\n\ndef undeclared(x):\n    pass\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("greet.pyi"),
+            "def greet(name: str) -> None: ...\n\nclass Greeter:\n    def greet(self, name: str) -> None: ...\n    def wave(self, name) -> None: ...\n",
+        )
+        .unwrap();
+
+        assert_eq!(adjacent_stub(&module), Some(dir.path().join("greet.pyi")));
+        assert_eq!(adjacent_stub(&dir.path().join("greet.pyi")), None);
+
+        let options = CheckOptions {
+            prefer_stubs: true,
+            aggregate: true,
+            ..default_options()
+        };
+        let (message, summary) = check_files(vec![module.clone()], &options);
+        let lines: Vec<&str> = message.lines().collect();
+        assert_eq!(lines.len(), 2, "{message}");
+        assert!(
+            lines[0].ends_with(":9: function 'wave' missing hints: parameter name; return type")
+        );
+        assert!(lines[1]
+            .ends_with(":13: function 'undeclared' missing hints: parameter x; return type"));
+        assert_eq!(summary.findings, 4);
+
+        let (_, summary) = check_files(vec![module], &default_options());
+        assert_eq!(summary.findings, 8);
+    }
+}
@@ -0,0 +1,262 @@
+use std::path::{Path, PathBuf};
+
+use crate::filters::GlobPattern;
+
+/// Linters whose exclude settings can be imported.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tool {
+    Ruff,
+    Flake8,
+}
+
+/// Exclude patterns imported from a linter's configuration file.
+pub struct ToolExcludes {
+    pub patterns: Vec<GlobPattern>,
+    pub source: PathBuf,
+}
+
+/// Looks for the tool's configuration in `start` and its ancestors, returning the
+/// exclude patterns of the first configuration found.
+pub fn find_tool_excludes(tool: Tool, start: &Path) -> Result<Option<ToolExcludes>, String> {
+    let start = std::path::absolute(start).map_err(|e| e.to_string())?;
+    let start = if start.is_dir() {
+        start.as_path()
+    } else {
+        start.parent().unwrap_or(&start)
+    };
+
+    let candidates: &[&str] = match tool {
+        Tool::Ruff => &["ruff.toml", ".ruff.toml", "pyproject.toml"],
+        Tool::Flake8 => &["setup.cfg", "tox.ini", ".flake8"],
+    };
+
+    for directory in start.ancestors() {
+        for candidate in candidates {
+            let path = directory.join(candidate);
+            if !path.is_file() {
+                continue;
+            }
+
+            let contents = std::fs::read_to_string(&path)
+                .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+
+            let raw_patterns = match tool {
+                Tool::Ruff => ruff_patterns(&contents, candidate == &"pyproject.toml")
+                    .map_err(|e| format!("could not parse {}: {e}", path.display()))?,
+                Tool::Flake8 => flake8_patterns(&contents),
+            };
+
+            let Some(raw_patterns) = raw_patterns else {
+                continue;
+            };
+
+            let source = path.display().to_string();
+            let patterns = raw_patterns
+                .iter()
+                .map(|pattern| translate_pattern(pattern, directory, &source))
+                .collect::<Result<_, _>>()?;
+
+            return Ok(Some(ToolExcludes {
+                patterns,
+                source: path,
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Both ruff and flake8 match patterns without a separator against file and directory
+/// names, and patterns with one against paths relative to the configuration file.
+/// Their globs (ruff) and fnmatch patterns (flake8) both let `*` cross separators, which
+/// is also `globset`'s default.
+fn translate_pattern(pattern: &str, directory: &Path, source: &str) -> Result<GlobPattern, String> {
+    let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+
+    if pattern.contains('/') {
+        GlobPattern::new(pattern, Some(directory), source.to_string())
+    } else {
+        GlobPattern::new(pattern, None, source.to_string())
+    }
+}
+
+/// Reads `exclude` and `extend-exclude` from the top level of `ruff.toml` or from the
+/// `[tool.ruff]` table of `pyproject.toml`, returning `None` if the table is absent.
+fn ruff_patterns(contents: &str, is_pyproject: bool) -> Result<Option<Vec<String>>, String> {
+    let table: toml::Table = contents
+        .parse()
+        .map_err(|e: toml::de::Error| e.to_string())?;
+
+    let table = if is_pyproject {
+        match table
+            .get("tool")
+            .and_then(|tool| tool.get("ruff"))
+            .and_then(|ruff| ruff.as_table())
+        {
+            Some(ruff) => ruff.clone(),
+            None => return Ok(None),
+        }
+    } else {
+        table
+    };
+
+    let mut patterns = Vec::new();
+    for key in ["exclude", "extend-exclude"] {
+        let Some(value) = table.get(key) else {
+            continue;
+        };
+
+        let values = value
+            .as_array()
+            .ok_or_else(|| format!("'{key}' should be a list of strings"))?;
+        for value in values {
+            let pattern = value
+                .as_str()
+                .ok_or_else(|| format!("'{key}' should be a list of strings"))?;
+            patterns.push(pattern.to_string());
+        }
+    }
+
+    Ok(Some(patterns))
+}
+
+/// Reads the comma and/or newline separated `exclude` and `extend-exclude` options of
+/// the `[flake8]` section, returning `None` if the section is absent.
+fn flake8_patterns(contents: &str) -> Option<Vec<String>> {
+    let mut in_section = false;
+    let mut found_section = false;
+    let mut current_key: Option<String> = None;
+    let mut patterns = Vec::new();
+
+    for line in contents.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with('#') || trimmed.starts_with(';') {
+            continue;
+        }
+
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            in_section = trimmed == "[flake8]";
+            found_section |= in_section;
+            current_key = None;
+            continue;
+        }
+
+        if !in_section || trimmed.is_empty() {
+            continue;
+        }
+
+        let is_continuation = line.starts_with(char::is_whitespace);
+
+        let value = if is_continuation {
+            trimmed
+        } else {
+            let Some((key, value)) = trimmed.split_once(['=', ':']) else {
+                current_key = None;
+                continue;
+            };
+            current_key = Some(key.trim().replace('_', "-"));
+            value
+        };
+
+        if matches!(current_key.as_deref(), Some("exclude" | "extend-exclude")) {
+            patterns.extend(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|pattern| !pattern.is_empty())
+                    .map(str::to_string),
+            );
+        }
+    }
+
+    found_section.then_some(patterns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::{Filter, GlobExclude};
+    use crate::tests::{check_directory, default_options, fixture_dir};
+
+    #[test]
+    fn ruff_patterns_test() {
+        let pyproject = r#"
+[project]
+name = "app"
+
+[tool.ruff]
+exclude = [".venv", "build"]
+extend-exclude = ["src/generated/*"]
+"#;
+        assert_eq!(
+            ruff_patterns(pyproject, true).unwrap(),
+            Some(vec![
+                ".venv".to_string(),
+                "build".to_string(),
+                "src/generated/*".to_string()
+            ])
+        );
+        assert_eq!(
+            ruff_patterns("[project]\nname = 'app'", true).unwrap(),
+            None
+        );
+        assert!(ruff_patterns("[tool.ruff]\nexclude = 'build'", true).is_err());
+    }
+
+    #[test]
+    fn flake8_patterns_test() {
+        let setup_cfg = "
+[metadata]
+name = app
+
+[flake8]
+max-line-length = 100
+exclude = .git,__pycache__,
+    build/,
+    src/legacy/*.py
+extend_exclude = migrations
+";
+        assert_eq!(
+            flake8_patterns(setup_cfg),
+            Some(vec![
+                ".git".to_string(),
+                "__pycache__".to_string(),
+                "build/".to_string(),
+                "src/legacy/*.py".to_string(),
+                "migrations".to_string()
+            ])
+        );
+        assert_eq!(flake8_patterns("[metadata]\nname = app"), None);
+    }
+
+    #[test]
+    fn tool_excludes_test() {
+        let dir = fixture_dir();
+        std::fs::create_dir_all(dir.path().join("src/legacy")).unwrap();
+        std::fs::create_dir_all(dir.path().join("src/migrations")).unwrap();
+        for file in ["src/app.py", "src/legacy/old.py", "src/migrations/0001.py"] {
+            std::fs::write(dir.path().join(file), "def f(x): ...").unwrap();
+        }
+        std::fs::write(
+            dir.path().join("setup.cfg"),
+            "[flake8]\nexclude = src/legacy/*.py,migrations\n",
+        )
+        .unwrap();
+
+        assert!(find_tool_excludes(Tool::Ruff, &dir.path().join("src"))
+            .unwrap()
+            .is_none());
+
+        let excludes = find_tool_excludes(Tool::Flake8, &dir.path().join("src"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(excludes.source, dir.path().join("setup.cfg"));
+        assert_eq!(excludes.patterns.len(), 2);
+
+        let filters: Vec<Box<dyn Filter + Sync>> = vec![Box::new(GlobExclude(excludes.patterns))];
+        let (_, summary) = check_directory(&dir.path().join("src"), &filters, &default_options());
+        assert_eq!(summary.files_checked, 1);
+    }
+}
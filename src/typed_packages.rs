@@ -0,0 +1,80 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+/// PEP 561 marker of packages that ship type information, applying to their subpackages.
+const MARKER: &str = "py.typed";
+
+/// The files that belong to a package marked with `py.typed`, either directly or through
+/// one of their parent packages. Each directory is only looked at once.
+pub fn typed_files(files: &[PathBuf]) -> HashSet<PathBuf> {
+    let mut typed_directories = HashMap::new();
+
+    files
+        .iter()
+        .filter(|file| {
+            file.parent()
+                .is_some_and(|directory| is_typed(directory, &mut typed_directories))
+        })
+        .cloned()
+        .collect()
+}
+
+fn is_typed(directory: &Path, typed_directories: &mut HashMap<PathBuf, bool>) -> bool {
+    if let Some(&typed) = typed_directories.get(directory) {
+        return typed;
+    }
+
+    let typed = directory.join(MARKER).is_file()
+        || directory
+            .parent()
+            .is_some_and(|parent| is_typed(parent, typed_directories));
+    typed_directories.insert(directory.to_path_buf(), typed);
+
+    typed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{check_directory, default_options, fixture_dir};
+    use crate::CheckOptions;
+
+    #[test]
+    fn strict_typed_packages_test() {
+        let dir = fixture_dir();
+        std::fs::create_dir_all(dir.path().join("lib/sub")).unwrap();
+        std::fs::create_dir_all(dir.path().join("scripts")).unwrap();
+        std::fs::write(dir.path().join("lib/py.typed"), "").unwrap();
+        for file in ["lib/api.py", "lib/sub/impl.py", "scripts/run.py"] {
+            std::fs::write(dir.path().join(file), "def f(x: int):\n    pass\n").unwrap();
+        }
+
+        let files = [
+            dir.path().join("lib/api.py"),
+            dir.path().join("lib/sub/impl.py"),
+            dir.path().join("scripts/run.py"),
+        ];
+        assert_eq!(
+            typed_files(&files),
+            HashSet::from([files[0].clone(), files[1].clone()])
+        );
+
+        let mut options = CheckOptions {
+            rules: crate::Rules {
+                ignore_return: true,
+                ..Default::default()
+            },
+            ..default_options()
+        };
+        options.typed_files = Some(typed_files(&files));
+        let (_, summary) = check_directory(dir.path(), &[], &options);
+
+        assert_eq!(summary.findings, 2);
+        assert_eq!(
+            summary.render(false),
+            "Typed packages: 2 file(s) with 2 finding(s); elsewhere: 1 file(s) with 0 finding(s).\n"
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use std::process::{Command, Output};
+
+fn pythcheck(arguments: &[&str], dir: &std::path::Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_pythcheck"))
+        .args(arguments)
+        .current_dir(dir)
+        .env_remove("GITHUB_ACTIONS")
+        .env_remove("TF_BUILD")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn missing_path_test() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("ok.py"), "def f() -> None: ...\n").unwrap();
+
+    for arguments in [
+        &["nonexist"][..],
+        &["nonexist", "--error-on-empty"],
+        &["ok.py", "nonexist"],
+        &["nonexist", "--format", "json"],
+    ] {
+        let output = pythcheck(arguments, dir.path());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert_eq!(output.status.code(), Some(2), "{arguments:?}: {stderr}");
+        assert!(
+            stderr.contains("warning: skipping 'nonexist' as it does not exist."),
+            "{arguments:?}: {stderr}"
+        );
+        assert!(
+            stderr.contains("nonexist: does not exist"),
+            "{arguments:?}: {stderr}"
+        );
+        assert!(!stdout.contains("All good"), "{arguments:?}: {stdout}");
+    }
+
+    let output = pythcheck(&["nonexist"], dir.path());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("warning: no Python files found under 'nonexist'."));
+
+    let output = pythcheck(&["ok.py"], dir.path());
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn print_config_path_test() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("proj/src")).unwrap();
+    std::fs::write(
+        dir.path().join("proj/pyproject.toml"),
+        "[tool.python-type-hints-checker]\nignore-return = true\n",
+    )
+    .unwrap();
+
+    for arguments in [
+        &["--print-config=proj/src"][..],
+        &["proj/src", "--print-config"],
+    ] {
+        let output = pythcheck(arguments, dir.path());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(
+            stdout
+                .lines()
+                .any(|line| line.starts_with("ignore-return = true  # config:")),
+            "{arguments:?}: {stdout}"
+        );
+    }
+}
+
+#[cfg(feature = "formats-json")]
+#[test]
+fn summary_json_test() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("ok.py"), "def f() -> None: ...\n").unwrap();
+
+    let output = pythcheck(&["ok.py", "--summary-json", "summary.json"], dir.path());
+    assert_eq!(output.status.code(), Some(0));
+    assert!(dir.path().join("summary.json").is_file());
+
+    std::fs::remove_file(dir.path().join("summary.json")).unwrap();
+    let output = pythcheck(
+        &["ok.py", "nonexist", "--summary-json", "summary.json"],
+        dir.path(),
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert!(!dir.path().join("summary.json").exists());
+}
+
+#[test]
+fn inventory_binary_test() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("bin.py"), b"def f():\0\n").unwrap();
+    let expected = "warning: skipping 'bin.py' as it looks like a binary file.";
+
+    for arguments in [&["bin.py"][..], &["inventory", "bin.py"]] {
+        let stderr = String::from_utf8_lossy(&pythcheck(arguments, dir.path()).stderr).into_owned();
+        assert!(stderr.contains(expected), "{arguments:?}: {stderr}");
+    }
+}